@@ -11,10 +11,17 @@
 // https://github.com/Drops-of-Diamond/diamond_drops/issues/86
 #![allow(renamed_and_removed_lints)]
 #![allow(unused_doc_comment)]
+// error_chain needs a deeper recursion limit for the number of error kinds.
+#![recursion_limit = "256"]
 
 //extern crate bitreader;
 extern crate diamond_drops_cli as cli;
 extern crate ethereum_types;
+extern crate libsecp256k1;
+extern crate rand;
+#[cfg(feature = "erasure-coding")]
+extern crate reed_solomon_simd;
+extern crate serde;
 extern crate tiny_keccak;
 
 #[macro_use]
@@ -23,4 +30,10 @@ extern crate error_chain;
 #[macro_use]
 extern crate log;
 
+#[macro_use]
+extern crate serde_derive;
+
+#[macro_use]
+extern crate serde_json;
+
 pub mod modules;
\ No newline at end of file
@@ -0,0 +1,183 @@
+use modules::collation::body::Body;
+use modules::collation::chunk::Chunk;
+use modules::collation::header::Header;
+use modules::collation::merkle;
+use modules::collation::merkle::{MerkleProof, MerkleTree};
+use modules::constants::CHUNKS_PER_COLLATION;
+use modules::primitives::CollationHeaderHash;
+
+use rand::Rng;
+use std::collections::HashMap;
+use std::collections::hash_map::Entry;
+
+/// Where a notary gets the chunks of collation bodies from when sampling them for
+/// availability, e.g. the bodies it has received, or other nodes on the network.
+pub trait ChunkSource {
+    /// Fetch the chunk at `index` of the body of the collation with the given header hash,
+    /// with a proof against the chunk root in the header. Indices go up to
+    /// CHUNKS_PER_COLLATION, including the zero chunks padding a short body.
+    /// Returns `None` if the chunk isn't available.
+    fn fetch_chunk(&self, header_hash: &CollationHeaderHash, index: usize) -> Option<(Chunk, MerkleProof)>;
+
+    /// Called with the body of each collation the notary receives. Sources that fetch
+    /// chunks from elsewhere can ignore it.
+    fn receive_body(&mut self, header_hash: CollationHeaderHash, body: &Body) {}
+
+    /// Called when the notary drops a collation, so that its body can be dropped too.
+    fn forget_body(&mut self, header_hash: &CollationHeaderHash) {}
+
+    /// The bytes the source keeps of the received bodies, which count towards the
+    /// notary's memory ceiling. Sources that don't keep them can leave it at zero.
+    fn stored_bytes(&self) -> usize {
+        0
+    }
+}
+
+/// A chunk source serving chunks from the collation bodies the notary has received.
+/// This is the notary's default source.
+#[derive(Default)]
+pub struct ReceivedBodies {
+    // The body and its chunk tree, so proofs don't rebuild the tree for each sample.
+    bodies: HashMap<CollationHeaderHash, (Body, MerkleTree)>,
+    // The total size of the kept bodies and trees in bytes
+    stored_bytes: usize
+}
+
+impl ReceivedBodies {
+    pub fn new() -> ReceivedBodies {
+        ReceivedBodies {
+            bodies: HashMap::new(),
+            stored_bytes: 0
+        }
+    }
+}
+
+impl ChunkSource for ReceivedBodies {
+    fn fetch_chunk(&self, header_hash: &CollationHeaderHash, index: usize) -> Option<(Chunk, MerkleProof)> {
+        let (body, tree) = self.bodies.get(header_hash)?;
+        Some((body.chunk(index)?, tree.proof(index)?))
+    }
+
+    fn receive_body(&mut self, header_hash: CollationHeaderHash, body: &Body) {
+        if let Entry::Vacant(entry) = self.bodies.entry(header_hash) {
+            let tree = body.chunk_tree();
+            self.stored_bytes += body.size() + tree.size();
+            entry.insert((body.clone(), tree));
+        }
+    }
+
+    fn forget_body(&mut self, header_hash: &CollationHeaderHash) {
+        if let Some((body, tree)) = self.bodies.remove(header_hash) {
+            self.stored_bytes -= body.size() + tree.size();
+        }
+    }
+
+    fn stored_bytes(&self) -> usize {
+        self.stored_bytes
+    }
+}
+
+/// Check that the body of the collation with the given header is available, by fetching
+/// `samples` chunks at random indices from the source and verifying each against the
+/// header's chunk root. The body is available if every sample is.
+pub fn sample_availability<S: ChunkSource + ?Sized, R: Rng>(header: &Header, source: &S,
+                                                              samples: usize, rng: &mut R) -> bool {
+    let header_hash = header.hash();
+    let chunk_root = header.chunk_root();
+    (0..samples).all(|_| {
+        let index = rng.gen_range(0, CHUNKS_PER_COLLATION);
+        match source.fetch_chunk(&header_hash, index) {
+            Some((chunk, proof)) => merkle::verify_chunk_proof(&chunk_root, index, &chunk, &proof),
+            None => {
+                debug!("Chunk {} of collation {:?} is not available", index, header_hash);
+                false
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use modules::collation::blob::Blob;
+    use modules::primitives::{ShardIdHash, ChunkRootHash, ChunkPeriodHash, ProposerAddress, ProposerBidHash};
+    use rand::{SeedableRng, XorShiftRng};
+
+    const SAMPLES: usize = 20;
+
+    fn generate_header(chunk_root: ChunkRootHash) -> Header {
+        Header::new(ShardIdHash::from_dec_str("1").unwrap(),
+                    chunk_root,
+                    ChunkPeriodHash::from_dec_str("1").unwrap(),
+                    ProposerAddress::zero(), ProposerBidHash::zero())
+    }
+
+    fn generate_rng() -> XorShiftRng {
+        XorShiftRng::from_seed([1, 2, 3, 4])
+    }
+
+    /// Serves only the chunks at even indices.
+    struct WithholdingSource {
+        received: ReceivedBodies
+    }
+
+    impl ChunkSource for WithholdingSource {
+        fn fetch_chunk(&self, header_hash: &CollationHeaderHash, index: usize) -> Option<(Chunk, MerkleProof)> {
+            if index % 2 == 1 {
+                return None;
+            }
+            self.received.fetch_chunk(header_hash, index)
+        }
+    }
+
+    #[test]
+    fn it_finds_received_body_available() {
+        let body = Blob::new(vec![1; 5000]).blob_to_collation_body().unwrap();
+        let header = generate_header(body.chunk_root());
+        let mut source = ReceivedBodies::new();
+        source.receive_body(header.hash(), &body);
+        assert!(sample_availability(&header, &source, SAMPLES, &mut generate_rng()));
+    }
+
+    #[test]
+    fn it_finds_unknown_body_unavailable() {
+        let body = Blob::new(vec![1; 5000]).blob_to_collation_body().unwrap();
+        let header = generate_header(body.chunk_root());
+        let source = ReceivedBodies::new();
+        assert!(!sample_availability(&header, &source, SAMPLES, &mut generate_rng()));
+    }
+
+    #[test]
+    fn it_forgets_bodies() {
+        let body = Blob::new(vec![1; 5000]).blob_to_collation_body().unwrap();
+        let header = generate_header(body.chunk_root());
+        let mut source = ReceivedBodies::new();
+        source.receive_body(header.hash(), &body);
+        assert_eq!(source.stored_bytes(), body.size() + body.chunk_tree().size());
+        // A body received twice, e.g. as a proposal and a collation, is kept once
+        source.receive_body(header.hash(), &body);
+        assert_eq!(source.stored_bytes(), body.size() + body.chunk_tree().size());
+        source.forget_body(&header.hash());
+        assert!(!sample_availability(&header, &source, SAMPLES, &mut generate_rng()));
+        assert_eq!(source.stored_bytes(), 0);
+    }
+
+    #[test]
+    fn it_finds_body_not_matching_chunk_root_unavailable() {
+        let body = Blob::new(vec![1; 5000]).blob_to_collation_body().unwrap();
+        let header = generate_header(ChunkRootHash::zero());
+        let mut source = ReceivedBodies::new();
+        source.receive_body(header.hash(), &body);
+        assert!(!sample_availability(&header, &source, SAMPLES, &mut generate_rng()));
+    }
+
+    #[test]
+    fn it_detects_partly_withheld_body() {
+        let body = Blob::new(vec![1; 5000]).blob_to_collation_body().unwrap();
+        let header = generate_header(body.chunk_root());
+        let mut received = ReceivedBodies::new();
+        received.receive_body(header.hash(), &body);
+        let source = WithholdingSource { received };
+        assert!(!sample_availability(&header, &source, SAMPLES, &mut generate_rng()));
+    }
+}
@@ -0,0 +1,67 @@
+use modules::primitives::{ProposerBidHash, Wei};
+
+use ethereum_types::U512;
+
+/// How a proposer decides its bid for a collation, from the fees of the blobs in it.
+/// The bid is paid from the proposer's balance in the SMC once its header is added.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum BidStrategy {
+    /// Bid the same amount for every collation
+    Fixed(ProposerBidHash),
+    /// Bid a percentage of the blob fees
+    FeePercentage(u8),
+    /// Bid a percentage of the blob fees, but at most `cap`
+    Capped { percentage: u8, cap: ProposerBidHash }
+}
+
+impl Default for BidStrategy {
+    /// Bid nothing, so a proposer doesn't need a balance
+    fn default() -> BidStrategy {
+        BidStrategy::Fixed(ProposerBidHash::zero())
+    }
+}
+
+impl BidStrategy {
+    /// The bid for a collation of blobs paying `fees` in total
+    pub fn bid(&self, fees: Wei) -> ProposerBidHash {
+        match *self {
+            BidStrategy::Fixed(bid) => bid,
+            BidStrategy::FeePercentage(percentage) => fee_percentage(fees, percentage),
+            BidStrategy::Capped { percentage, cap } => fee_percentage(fees, percentage).min(cap)
+        }
+    }
+}
+
+/// The percentage of the fees, rounded down, or the largest bid if it overflows
+fn fee_percentage(fees: Wei, percentage: u8) -> ProposerBidHash {
+    let bid = fees.full_mul(Wei::from(percentage)) / U512::from(100);
+    if bid > U512::from(ProposerBidHash::max_value()) {
+        ProposerBidHash::max_value()
+    } else {
+        ProposerBidHash::from(bid)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_bids_by_strategy() {
+        let fees = Wei::from(1_000);
+        assert_eq!(BidStrategy::default().bid(fees), ProposerBidHash::zero());
+        assert_eq!(BidStrategy::Fixed(ProposerBidHash::from(70)).bid(fees), ProposerBidHash::from(70));
+        assert_eq!(BidStrategy::FeePercentage(25).bid(fees), ProposerBidHash::from(250));
+        assert_eq!(BidStrategy::FeePercentage(25).bid(Wei::from(3)), ProposerBidHash::zero());
+
+        let capped = BidStrategy::Capped { percentage: 25, cap: ProposerBidHash::from(200) };
+        assert_eq!(capped.bid(fees), ProposerBidHash::from(200));
+        assert_eq!(capped.bid(Wei::from(400)), ProposerBidHash::from(100));
+    }
+
+    #[test]
+    fn it_bids_percentages_of_large_fees_without_overflow() {
+        assert_eq!(BidStrategy::FeePercentage(50).bid(Wei::max_value()), Wei::max_value() / Wei::from(2));
+        assert_eq!(BidStrategy::FeePercentage(200).bid(Wei::max_value()), ProposerBidHash::max_value());
+    }
+}
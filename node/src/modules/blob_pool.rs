@@ -0,0 +1,362 @@
+use modules::collation::blob::Blob;
+use modules::constants::{BLOB_POOL_EXPIRY_PERIODS, BLOB_POOL_MAX_BYTES, BLOB_POOL_MAX_SENDER_BYTES,
+    CHUNKS_PER_COLLATION};
+use modules::errors::*;
+use modules::primitives::{BlobHash, ChunkPeriodHash, Wei};
+
+use ethereum_types::{Address, U256};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+/// A blob waiting in the pool to be put into a collation
+#[derive(PartialEq, Debug, Clone)]
+pub struct PooledBlob {
+    pub blob: Blob,
+    pub hash: BlobHash,
+    /// The account that submitted the blob
+    pub sender: Address,
+    /// What the sender pays to have the blob included
+    pub fee: Wei,
+    /// The period the blob was submitted in
+    pub period: ChunkPeriodHash
+}
+
+impl PooledBlob {
+    /// Compare the fee per chunk of two blobs
+    fn cmp_fee_rate(&self, other: &PooledBlob) -> Ordering {
+        let chunks = |pooled: &PooledBlob| U256::from(pooled.blob.chunk_count().max(1));
+        self.fee.full_mul(chunks(other)).cmp(&other.fee.full_mul(chunks(self)))
+    }
+
+    /// Order blobs from the best to include to the worst: the highest fee per chunk
+    /// first, then the oldest, then by hash so the order is the same on every node.
+    fn cmp_priority(&self, other: &PooledBlob) -> Ordering {
+        other.cmp_fee_rate(self)
+            .then_with(|| self.period.cmp(&other.period))
+            .then_with(|| self.hash.cmp(&other.hash))
+    }
+}
+
+/// Blobs submitted by applications for proposers to put into collations.
+///
+/// Blobs are identified by the hash of their data, so the same data is only pooled
+/// once. The pool limits the bytes each sender and all senders together have pooled,
+/// and drops blobs that weren't included within the expiry periods.
+pub struct BlobPool {
+    blobs: HashMap<BlobHash, PooledBlob>,
+    // Bytes of blob data pooled by each sender
+    sender_sizes: HashMap<Address, usize>,
+    size: usize,
+    period: ChunkPeriodHash,
+    max_sender_bytes: usize,
+    max_bytes: usize,
+    expiry_periods: u64
+}
+
+impl Default for BlobPool {
+    fn default() -> BlobPool {
+        BlobPool::new()
+    }
+}
+
+impl BlobPool {
+    pub fn new() -> BlobPool {
+        BlobPool {
+            blobs: HashMap::new(),
+            sender_sizes: HashMap::new(),
+            size: 0,
+            period: ChunkPeriodHash::zero(),
+            max_sender_bytes: BLOB_POOL_MAX_SENDER_BYTES,
+            max_bytes: BLOB_POOL_MAX_BYTES,
+            expiry_periods: BLOB_POOL_EXPIRY_PERIODS
+        }
+    }
+
+    /// Sets how many bytes of blob data a sender can have pooled at most,
+    /// BLOB_POOL_MAX_SENDER_BYTES by default
+    pub fn set_max_sender_bytes(&mut self, max_sender_bytes: usize) {
+        self.max_sender_bytes = max_sender_bytes;
+    }
+
+    /// Sets how many bytes of blob data are pooled at most, BLOB_POOL_MAX_BYTES by default
+    pub fn set_max_bytes(&mut self, max_bytes: usize) {
+        self.max_bytes = max_bytes;
+    }
+
+    /// Sets for how many periods a blob is pooled before it expires,
+    /// BLOB_POOL_EXPIRY_PERIODS by default
+    pub fn set_expiry_periods(&mut self, expiry_periods: u64) {
+        self.expiry_periods = expiry_periods;
+    }
+
+    /// Add a blob from a sender paying `fee` to the pool, in the current period.
+    ///
+    /// Returns an error if the blob is pooled already, doesn't fit into a collation, or
+    /// would take the sender over its limit. If the pool is full, blobs paying a lower
+    /// fee per chunk are evicted to make room, otherwise the blob is rejected.
+    pub fn submit(&mut self, blob: Blob, sender: Address, fee: Wei) -> Result<BlobHash> {
+        let hash = blob.hash();
+        if self.blobs.contains_key(&hash) {
+            bail!(ErrorKind::BlobAlreadyPooled(hash));
+        }
+        let chunks = blob.chunk_count();
+        if chunks > CHUNKS_PER_COLLATION {
+            bail!(ErrorKind::BlobTooLarge(chunks));
+        }
+        let blob_size = blob.data.len();
+        let sender_size = self.sender_size(&sender) + blob_size;
+        if sender_size > self.max_sender_bytes {
+            bail!(ErrorKind::SenderBlobLimit(sender, sender_size, self.max_sender_bytes));
+        }
+
+        let pooled = PooledBlob {
+            blob,
+            hash,
+            sender,
+            fee,
+            period: self.period
+        };
+        self.make_room(&pooled)?;
+        self.size += blob_size;
+        *self.sender_sizes.entry(sender).or_insert(0) += blob_size;
+        self.blobs.insert(hash, pooled);
+        Ok(hash)
+    }
+
+    /// Evict the blobs paying the lowest fee per chunk, if that makes room for the
+    /// blob and they pay less than it
+    fn make_room(&mut self, pooled: &PooledBlob) -> Result<()> {
+        let needed = self.size + pooled.blob.data.len();
+        if needed <= self.max_bytes {
+            return Ok(());
+        }
+        let mut cheapest: Vec<&PooledBlob> = self.blobs.values()
+            .filter(|other| other.cmp_fee_rate(pooled) == Ordering::Less)
+            .collect();
+        cheapest.sort_by(|a, b| b.cmp_priority(a));
+
+        let mut evicted = vec![];
+        let mut freed = 0;
+        for other in cheapest {
+            if needed - freed <= self.max_bytes {
+                break;
+            }
+            freed += other.blob.data.len();
+            evicted.push(other.hash);
+        }
+        if needed - freed > self.max_bytes {
+            bail!(ErrorKind::BlobPoolFull(needed, self.max_bytes));
+        }
+        for hash in evicted {
+            debug!("Evicting blob {:?} from the full blob pool", hash);
+            self.remove(&hash);
+        }
+        Ok(())
+    }
+
+    /// Start a new period, dropping the blobs that expired in it. Returns the number
+    /// of expired blobs.
+    pub fn start_period(&mut self, period: ChunkPeriodHash) -> usize {
+        self.period = period;
+        let expiry = ChunkPeriodHash::from(self.expiry_periods);
+        let expired: Vec<BlobHash> = self.blobs.values()
+            .filter(|pooled| pooled.period.saturating_add(expiry) <= period)
+            .map(|pooled| pooled.hash)
+            .collect();
+        for hash in &expired {
+            self.remove(hash);
+        }
+        expired.len()
+    }
+
+    /// The best blobs that fit into `capacity` chunks together: the blobs are taken by
+    /// priority, skipping those that don't fit into the remaining capacity.
+    pub fn select(&self, capacity: usize) -> Vec<&PooledBlob> {
+        let mut candidates: Vec<&PooledBlob> = self.blobs.values().collect();
+        candidates.sort_by(|a, b| a.cmp_priority(b));
+
+        let mut free_chunks = capacity;
+        let mut selected = vec![];
+        for pooled in candidates {
+            let chunks = pooled.blob.chunk_count();
+            if chunks <= free_chunks {
+                free_chunks -= chunks;
+                selected.push(pooled);
+            }
+        }
+        selected
+    }
+
+    /// Remove a blob, e.g. once it is included in a collation
+    pub fn remove(&mut self, hash: &BlobHash) -> Option<PooledBlob> {
+        let pooled = self.blobs.remove(hash)?;
+        let blob_size = pooled.blob.data.len();
+        self.size -= blob_size;
+        let sender_is_empty = match self.sender_sizes.get_mut(&pooled.sender) {
+            Some(sender_size) => {
+                *sender_size -= blob_size;
+                *sender_size == 0
+            },
+            None => false
+        };
+        if sender_is_empty {
+            self.sender_sizes.remove(&pooled.sender);
+        }
+        Some(pooled)
+    }
+
+    pub fn get(&self, hash: &BlobHash) -> Option<&PooledBlob> {
+        self.blobs.get(hash)
+    }
+
+    pub fn contains(&self, hash: &BlobHash) -> bool {
+        self.blobs.contains_key(hash)
+    }
+
+    pub fn len(&self) -> usize {
+        self.blobs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.blobs.is_empty()
+    }
+
+    /// The bytes of blob data in the pool
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// The bytes of blob data a sender has in the pool
+    pub fn sender_size(&self, sender: &Address) -> usize {
+        self.sender_sizes.get(sender).cloned().unwrap_or(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use modules::constants::{CHUNK_DATA_SIZE, DATA_BYTES_PER_COLLATION};
+
+    fn sender(byte: u8) -> Address {
+        Address::from([byte; 20])
+    }
+
+    /// A blob of `chunks` full chunks
+    fn generate_blob(byte: u8, chunks: usize) -> Blob {
+        Blob::new(vec![byte; chunks * CHUNK_DATA_SIZE])
+    }
+
+    fn hashes(selected: Vec<&PooledBlob>) -> Vec<BlobHash> {
+        selected.into_iter().map(|pooled| pooled.hash).collect()
+    }
+
+    #[test]
+    fn it_pools_blobs_once() {
+        let mut pool = BlobPool::new();
+        let blob = generate_blob(1, 2);
+        let hash = pool.submit(blob.clone(), sender(1), Wei::from(10)).unwrap();
+        assert_eq!(hash, blob.hash());
+        assert!(pool.contains(&hash));
+        assert_eq!(pool.get(&hash).map(|pooled| pooled.sender), Some(sender(1)));
+
+        match pool.submit(blob.clone(), sender(2), Wei::from(20)) {
+            Err(Error(ErrorKind::BlobAlreadyPooled(duplicate), _)) => assert_eq!(duplicate, hash),
+            result => panic!("Expected blob already pooled error, got {:?}", result)
+        }
+        assert_eq!(pool.len(), 1);
+        assert_eq!(pool.size(), blob.data.len());
+        assert_eq!(pool.sender_size(&sender(2)), 0);
+    }
+
+    #[test]
+    fn it_rejects_blobs_larger_than_a_collation() {
+        let mut pool = BlobPool::new();
+        match pool.submit(Blob::new(vec![1; DATA_BYTES_PER_COLLATION + 1]), sender(1), Wei::from(10)) {
+            Err(Error(ErrorKind::BlobTooLarge(chunks), _)) => assert_eq!(chunks, CHUNKS_PER_COLLATION + 1),
+            result => panic!("Expected blob too large error, got {:?}", result)
+        }
+        assert!(pool.is_empty());
+    }
+
+    #[test]
+    fn it_limits_the_bytes_of_each_sender() {
+        let mut pool = BlobPool::new();
+        pool.set_max_sender_bytes(3 * CHUNK_DATA_SIZE);
+        pool.submit(generate_blob(1, 2), sender(1), Wei::from(10)).unwrap();
+        match pool.submit(generate_blob(2, 2), sender(1), Wei::from(10)) {
+            Err(Error(ErrorKind::SenderBlobLimit(limited, size, limit), _)) => {
+                assert_eq!((limited, size, limit), (sender(1), 4 * CHUNK_DATA_SIZE, 3 * CHUNK_DATA_SIZE));
+            },
+            result => panic!("Expected sender blob limit error, got {:?}", result)
+        }
+        pool.submit(generate_blob(2, 2), sender(2), Wei::from(10)).unwrap();
+
+        // Removing a blob frees the sender's bytes
+        pool.remove(&generate_blob(1, 2).hash()).unwrap();
+        assert_eq!(pool.sender_size(&sender(1)), 0);
+        pool.submit(generate_blob(3, 3), sender(1), Wei::from(10)).unwrap();
+    }
+
+    #[test]
+    fn it_evicts_cheaper_blobs_when_full() {
+        let mut pool = BlobPool::new();
+        pool.set_max_bytes(4 * CHUNK_DATA_SIZE);
+        let cheap = pool.submit(generate_blob(1, 2), sender(1), Wei::from(2)).unwrap();
+        let medium = pool.submit(generate_blob(2, 2), sender(2), Wei::from(10)).unwrap();
+
+        // Paying less per chunk than every pooled blob, a blob is rejected
+        match pool.submit(generate_blob(3, 1), sender(3), Wei::from(0)) {
+            Err(Error(ErrorKind::BlobPoolFull(size, limit), _)) => {
+                assert_eq!((size, limit), (5 * CHUNK_DATA_SIZE, 4 * CHUNK_DATA_SIZE));
+            },
+            result => panic!("Expected blob pool full error, got {:?}", result)
+        }
+
+        // Paying more, it replaces the cheapest blob
+        let expensive = pool.submit(generate_blob(4, 1), sender(4), Wei::from(20)).unwrap();
+        assert!(!pool.contains(&cheap));
+        assert!(pool.contains(&medium));
+        assert!(pool.contains(&expensive));
+        assert_eq!(pool.size(), 3 * CHUNK_DATA_SIZE);
+        assert_eq!(pool.sender_size(&sender(1)), 0);
+    }
+
+    #[test]
+    fn it_expires_stale_blobs() {
+        let mut pool = BlobPool::new();
+        pool.set_expiry_periods(2);
+        let old = pool.submit(generate_blob(1, 1), sender(1), Wei::from(10)).unwrap();
+        assert_eq!(pool.start_period(ChunkPeriodHash::from(1)), 0);
+        let recent = pool.submit(generate_blob(2, 1), sender(1), Wei::from(10)).unwrap();
+
+        assert_eq!(pool.start_period(ChunkPeriodHash::from(2)), 1);
+        assert!(!pool.contains(&old));
+        assert!(pool.contains(&recent));
+        assert_eq!(pool.start_period(ChunkPeriodHash::from(3)), 1);
+        assert!(pool.is_empty());
+        assert_eq!(pool.sender_size(&sender(1)), 0);
+    }
+
+    #[test]
+    fn it_selects_the_best_blobs_for_a_capacity() {
+        let mut pool = BlobPool::new();
+        let large = pool.submit(generate_blob(1, 4), sender(1), Wei::from(40)).unwrap();
+        let best_rate = pool.submit(generate_blob(2, 1), sender(2), Wei::from(30)).unwrap();
+        let low_rate = pool.submit(generate_blob(3, 2), sender(3), Wei::from(4)).unwrap();
+        let medium_rate = pool.submit(generate_blob(4, 2), sender(4), Wei::from(16)).unwrap();
+
+        assert_eq!(hashes(pool.select(100)), vec![best_rate, large, medium_rate, low_rate]);
+        // The large blob doesn't fit after the best one, so the next ones are taken
+        assert_eq!(hashes(pool.select(4)), vec![best_rate, medium_rate]);
+        assert!(pool.select(0).is_empty());
+    }
+
+    #[test]
+    fn it_selects_older_blobs_of_equal_fee_rates_first() {
+        let mut pool = BlobPool::new();
+        let old = pool.submit(generate_blob(1, 1), sender(1), Wei::from(10)).unwrap();
+        pool.start_period(ChunkPeriodHash::from(1));
+        let recent = pool.submit(generate_blob(2, 2), sender(2), Wei::from(20)).unwrap();
+        assert_eq!(hashes(pool.select(3)), vec![old, recent]);
+    }
+}
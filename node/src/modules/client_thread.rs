@@ -1,8 +1,18 @@
 use cli::modules::{config};
 use modules::{notary, proposer, message};
+use modules::blob_pool::BlobPool;
+use modules::constants::EVENT_FORWARDER_POLL_MILLIS;
+use modules::errors::*;
+use modules::message_log::{record_messages, MessageRecorder};
+use modules::primitives::ProposerAddress;
+use modules::smc::backend::SmcBackend;
 
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::mpsc;
 use std::thread;
+use std::time::Duration;
 
 /// A request to terminate a running thread
 #[derive(Debug)]
@@ -10,9 +20,81 @@ pub enum Command {
     Terminate
 }
 
+/// A command from the thread manager or a message from the SMC listener, for a
+/// client thread to react to
+#[derive(Debug)]
+pub enum Event {
+    Command(Command),
+    Message(message::Message)
+}
+
+/// Merges the messages of the SMC listener and the commands of the thread manager
+/// into one channel, so that a client thread can block on both. Events are received
+/// in the order they arrive, so neither source can starve the other. Each source is
+/// forwarded by its own thread, which stops when the source or the returned receiver
+/// hangs up, or once the Terminate command has been forwarded.
+pub fn merge_events(smc_listener: mpsc::Receiver<message::Message>,
+                    manager_listener: mpsc::Receiver<Command>) -> mpsc::Receiver<Event> {
+    let (event_sender, event_receiver) = mpsc::channel();
+    let terminated = Arc::new(AtomicBool::new(false));
+    forward_messages(smc_listener, event_sender.clone(), terminated.clone());
+    forward_commands(manager_listener, event_sender, terminated);
+    event_receiver
+}
+
+fn forward_messages(source: mpsc::Receiver<message::Message>,
+                    event_sender: mpsc::Sender<Event>,
+                    terminated: Arc<AtomicBool>) {
+    thread::Builder::new()
+        .name(String::from("event_forwarder"))
+        .spawn(move || {
+            // The SMC listener outlives its clients, so the source is polled to notice
+            // when the client was terminated
+            while !terminated.load(Ordering::SeqCst) {
+                match source.recv_timeout(Duration::from_millis(EVENT_FORWARDER_POLL_MILLIS)) {
+                    Ok(message) => {
+                        if event_sender.send(Event::Message(message)).is_err() {
+                            break;
+                        }
+                    },
+                    Err(mpsc::RecvTimeoutError::Timeout) => {},
+                    Err(mpsc::RecvTimeoutError::Disconnected) => { break; }
+                }
+            }
+        })
+        .expect("Failed to spawn an event forwarding thread");
+}
+
+fn forward_commands(source: mpsc::Receiver<Command>,
+                    event_sender: mpsc::Sender<Event>,
+                    terminated: Arc<AtomicBool>) {
+    thread::Builder::new()
+        .name(String::from("event_forwarder"))
+        .spawn(move || {
+            for command in source.iter() {
+                let terminate = match command {
+                    Command::Terminate => true
+                };
+                if event_sender.send(Event::Command(command)).is_err() || terminate {
+                    break;
+                }
+            }
+            terminated.store(true, Ordering::SeqCst);
+        })
+        .expect("Failed to spawn an event forwarding thread");
+}
+
 /// A client thread to run either a notary or a proposer
 pub struct ClientThread {
     mode: config::Mode,
+    smc_backend: Option<Box<dyn SmcBackend + Send>>,
+    message_log: Option<PathBuf>,
+    proposer_address: Option<ProposerAddress>,
+    blob_pool: Option<Arc<Mutex<BlobPool>>>,
+    // Where a proposer sends its commitments and revealed collations
+    proposal_sender: Option<mpsc::Sender<message::Message>>,
+    // Where a notary sends the commitments it chooses
+    chosen_sender: Option<mpsc::Sender<message::Message>>,
     pub manager: Option<mpsc::Sender<Command>>,
     pub handle: Option<thread::JoinHandle<()>>
 }
@@ -24,6 +106,12 @@ impl ClientThread {
             config::Mode::Notary => { 
                 ClientThread {
                     mode: mode.clone(),
+                    smc_backend: None,
+                    message_log: None,
+                    proposer_address: None,
+                    blob_pool: None,
+                    proposal_sender: None,
+                    chosen_sender: None,
                     manager: None,
                     handle: None
                 }
@@ -31,6 +119,12 @@ impl ClientThread {
             config::Mode::Proposer => {
                 ClientThread {
                     mode: mode.clone(),
+                    smc_backend: None,
+                    message_log: None,
+                    proposer_address: None,
+                    blob_pool: None,
+                    proposal_sender: None,
+                    chosen_sender: None,
                     manager: None,
                     handle: None
                 }
@@ -39,12 +133,65 @@ impl ClientThread {
         }
     }
 
-    /// Run the thread with the given receiver
-    pub fn run(&mut self, smc_receiver: mpsc::Receiver<message::Message>) {
+    /// Sets the SMC backend of the client, which a proposer needs to add collations
+    pub fn set_smc_backend(&mut self, smc_backend: Box<dyn SmcBackend + Send>) {
+        self.smc_backend = Some(smc_backend);
+    }
+
+    /// Sets the address a proposer registers and adds headers with
+    pub fn set_proposer_address(&mut self, proposer_address: ProposerAddress) {
+        self.proposer_address = Some(proposer_address);
+    }
+
+    /// Sets the pool a proposer collects blobs from
+    pub fn set_blob_pool(&mut self, blob_pool: Arc<Mutex<BlobPool>>) {
+        self.blob_pool = Some(blob_pool);
+    }
+
+    /// Sets where a proposer sends its commitments and revealed collations, e.g. to
+    /// the input of a notary
+    pub fn set_proposal_sender(&mut self, proposal_sender: mpsc::Sender<message::Message>) {
+        self.proposal_sender = Some(proposal_sender);
+    }
+
+    /// Sets where a notary sends the commitments it chooses, e.g. to the input of a
+    /// proposer
+    pub fn set_chosen_sender(&mut self, chosen_sender: mpsc::Sender<message::Message>) {
+        self.chosen_sender = Some(chosen_sender);
+    }
+
+    /// Records every message the client receives from the SMC listener in the file at
+    /// `path`, to be replayed later. Messages aren't recorded unless this is set.
+    pub fn set_message_log(&mut self, path: PathBuf) {
+        self.message_log = Some(path);
+    }
+
+    /// Puts a message recorder between the SMC listener and the client if a message
+    /// log is set. The client still runs, without recording, if the log can't be created.
+    fn record(&mut self, smc_receiver: mpsc::Receiver<message::Message>) -> mpsc::Receiver<message::Message> {
+        match self.message_log.take() {
+            Some(path) => match MessageRecorder::create(&path) {
+                Ok(recorder) => record_messages(smc_receiver, recorder).0,
+                Err(e) => {
+                    error!("Error creating the message log {:?}: {:?}", path, e);
+                    smc_receiver
+                }
+            },
+            None => smc_receiver
+        }
+    }
+
+    /// Run the thread with the given receiver. A proposer can't run without an SMC
+    /// backend, see `set_smc_backend`.
+    pub fn run(&mut self, smc_receiver: mpsc::Receiver<message::Message>) -> Result<()> {
         match self.mode {
             config::Mode::Notary => {
+                let smc_receiver = self.record(smc_receiver);
                 let (notary_manager_sender, notary_manager_receiver) = mpsc::channel();
-                let mut notary = notary::Notary::new(smc_receiver, notary_manager_receiver);
+                let mut notary = notary::Notary::new(merge_events(smc_receiver, notary_manager_receiver));
+                if let Some(chosen_sender) = self.chosen_sender.take() {
+                    notary.set_chosen_sender(chosen_sender);
+                }
 
                 self.manager = Some(notary_manager_sender);
                 self.handle = Some(thread::Builder::new()
@@ -55,8 +202,23 @@ impl ClientThread {
                                     .expect("Failed to spawn a notary thread"));
             },
             config::Mode::Proposer => {
+                let smc_backend = match self.smc_backend.take() {
+                    Some(smc_backend) => smc_backend,
+                    None => bail!(ErrorKind::MissingSmcBackend)
+                };
+                let smc_receiver = self.record(smc_receiver);
                 let (proposer_manager_sender, proposer_manager_receiver) = mpsc::channel();
-                let mut proposer = proposer::Proposer::new();
+                let mut proposer = proposer::Proposer::new(merge_events(smc_receiver, proposer_manager_receiver),
+                                                           smc_backend);
+                if let Some(proposer_address) = self.proposer_address {
+                    proposer.set_address(proposer_address);
+                }
+                if let Some(blob_pool) = self.blob_pool.take() {
+                    proposer.set_blob_pool(blob_pool);
+                }
+                if let Some(proposal_sender) = self.proposal_sender.take() {
+                    proposer.set_proposal_sender(proposal_sender);
+                }
 
                 self.manager = Some(proposer_manager_sender);
                 self.handle = Some(thread::Builder::new()
@@ -68,5 +230,125 @@ impl ClientThread {
             }
             _ => { panic!("Invalid mode provided to spawn new child thread from client thread instance") }
         }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use modules::collation::blob::Blob;
+    use modules::message_log::open_message_log;
+    use modules::primitives::{ChunkPeriodHash, Wei};
+    use modules::smc::in_memory::InMemorySmc;
+
+    #[test]
+    fn it_merges_messages_and_commands_in_arrival_order() {
+        let (smc_sender, smc_receiver) = mpsc::channel();
+        let (manager_sender, manager_receiver) = mpsc::channel();
+        let events = merge_events(smc_receiver, manager_receiver);
+
+        smc_sender.send(message::Message::Selected { value: true }).unwrap();
+        match events.recv() {
+            Ok(Event::Message(message::Message::Selected { value: true })) => {},
+            result => panic!("Expected the message, got {:?}", result)
+        }
+        smc_sender.send(message::Message::PeriodStart { value: ChunkPeriodHash::zero() }).unwrap();
+        match events.recv() {
+            Ok(Event::Message(message::Message::PeriodStart { .. })) => {},
+            result => panic!("Expected the message, got {:?}", result)
+        }
+        manager_sender.send(Command::Terminate).unwrap();
+        match events.recv() {
+            Ok(Event::Command(Command::Terminate)) => {},
+            result => panic!("Expected the command, got {:?}", result)
+        }
+
+        // Both forwarders stop after the Terminate command, although the SMC listener
+        // and the thread manager are still there
+        match events.recv() {
+            Err(mpsc::RecvError) => {},
+            result => panic!("Expected the channel to hang up, got {:?}", result)
+        }
+        drop(smc_sender);
+        drop(manager_sender);
+    }
+
+    #[test]
+    fn it_hangs_up_once_both_sources_have() {
+        let (smc_sender, smc_receiver) = mpsc::channel();
+        let (manager_sender, manager_receiver) = mpsc::channel::<Command>();
+        let events = merge_events(smc_receiver, manager_receiver);
+
+        drop(smc_sender);
+        drop(manager_sender);
+        match events.recv() {
+            Err(mpsc::RecvError) => {},
+            result => panic!("Expected the channel to hang up, got {:?}", result)
+        }
+    }
+
+    #[test]
+    fn it_records_messages_to_the_message_log() {
+        let path = ::std::env::temp_dir()
+            .join(format!("diamond_drops_client_message_log_{}.jsonl", ::std::process::id()));
+        let mut client = ClientThread::new(&config::Mode::Notary);
+        client.set_message_log(path.clone());
+
+        let (smc_sender, smc_receiver) = mpsc::channel();
+        client.run(smc_receiver).unwrap();
+        smc_sender.send(message::Message::Selected { value: true }).unwrap();
+
+        // The notary stops once the SMC listener and the thread manager hung up, after
+        // the recorder flushed the message and forwarded it
+        drop(smc_sender);
+        drop(client.manager.take());
+        client.handle.take().unwrap().join().unwrap();
+        let recorded = open_message_log(&path).unwrap();
+        ::std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(recorded.len(), 1);
+        assert_eq!(recorded[0].message, message::Message::Selected { value: true });
+    }
+
+    #[test]
+    fn it_sets_up_the_proposer_it_runs() {
+        let address = ProposerAddress::from([7; 20]);
+        let blob_pool = Arc::new(Mutex::new(BlobPool::new()));
+        blob_pool.lock().unwrap().submit(Blob::new(vec![1; 100]), address, Wei::from(10)).unwrap();
+        let backend = InMemorySmc::new();
+        let (proposal_sender, proposal_receiver) = mpsc::channel();
+        let mut client = ClientThread::new(&config::Mode::Proposer);
+        client.set_smc_backend(Box::new(backend.clone()));
+        client.set_proposer_address(address);
+        client.set_blob_pool(blob_pool);
+        client.set_proposal_sender(proposal_sender);
+
+        let (smc_sender, smc_receiver) = mpsc::channel();
+        client.run(smc_receiver).unwrap();
+        smc_sender.send(message::Message::PeriodStart { value: ChunkPeriodHash::from(1) }).unwrap();
+
+        // The proposer stops once the SMC listener and the thread manager hung up, after
+        // it proposed in the period however long building the collation takes
+        drop(smc_sender);
+        drop(client.manager.take());
+        client.handle.take().unwrap().join().unwrap();
+        match proposal_receiver.try_recv() {
+            Ok(message::Message::Commitment { value }) => assert_eq!(value.proposer, address),
+            result => panic!("Expected a commitment, got {:?}", result)
+        }
+        assert!(backend.proposer_deposit(address).unwrap().is_some());
+    }
+
+    #[test]
+    fn it_does_not_run_a_proposer_without_an_smc_backend() {
+        let mut client = ClientThread::new(&config::Mode::Proposer);
+        let (_smc_sender, smc_receiver) = mpsc::channel();
+        match client.run(smc_receiver) {
+            Err(Error(ErrorKind::MissingSmcBackend, _)) => {},
+            result => panic!("Expected missing SMC backend error, got {:?}", result)
+        }
+        assert!(client.manager.is_none());
+        assert!(client.handle.is_none());
     }
 }
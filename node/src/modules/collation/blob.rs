@@ -8,12 +8,13 @@
 use modules::collation::chunk::Chunk;
 use modules::constants::{CHUNK_SIZE, CHUNK_DATA_SIZE,
     COLLATION_SIZE, CHUNKS_PER_COLLATION, MAX_BLOB_SIZE, DATA_BYTES_PER_COLLATION};
-use modules::collation::body::{Body/* , BlobBodies */};
+use modules::collation::body::{Body, BlobBodies};
 use modules::collation::header::Header;
-use modules::primitives::CollationHeaderHash;
+use modules::primitives::{CollationHeaderHash, BlobHash};
 use modules::errors::*;
 use modules::collation::body;
 use std::process;
+use tiny_keccak;
 
 // use std::ops::Try; // for trying to use with structs for error-handling,
 // however this is a nightly feature.
@@ -37,164 +38,68 @@ impl Blob {
 
     }
     /// Create a set of chunks to represent this blob.
+    ///
+    /// Every chunk but the last is non-terminal and holds CHUNK_DATA_SIZE bytes. The
+    /// terminal chunk records the exact number of bytes it holds (1 to CHUNK_DATA_SIZE),
+    /// so trailing zero bytes in the blob are kept. An empty blob has no chunks.
     pub fn to_chunks(self, skip_evm: bool) -> Vec<Chunk>/* Result<Vec<Chunk>> */ {
-        // Since each element is one byte then getting the length (the number of elements)
-        // will also get the number of bytes.
-        let bytes_per_blob: usize = self.data.len();
         // We can only fit 31 (CHUNK_DATA_SIZE) out of 32 (CHUNK_SIZE) bytes
         // per blob into a chunk, due to adding the indicator byte.
-        // self.size is the number of bytes per blob
-        // Dimensional analysis:
-        // chunks_per_blob = [bytes per blob] / [blob bytes that fit into each chunk].
-        let chunks_per_blob: usize = (((bytes_per_blob as f32 / CHUNK_DATA_SIZE as f32)
-            as f32).ceil()) as usize;
         // E.g.: for a 125 byte blob at 31 bytes per chunk, that's 124 bytes that fit
         // into 4 chunks, plus the last chunk for the last byte, i.e. 5 chunks.
-        // 125 / 31 rounded up is 5.
-
-        // This won't work when we have a blob with 0 value bytes on the end of it,
-        // since those will be included in the length of bytes_per_blob.
-        // let terminal_len: u8 = (bytes_per_blob % CHUNK_DATA_SIZE) as u8;
-        // 125 % 31 = 1.
-
-        // Assume initially that the blob has no consecutive final zeros (i.e. not [..., 0, 0, 0])
-        // However, this has the adverse side effect of when we actually want all the data to be zero in the last blob,
-        // the indicator will be set to 0.
-        let mut data_length_last_31_bytes: u8
-            = CHUNK_DATA_SIZE as u8;
-        //let blob_data: &mut Vec<u8> = &mut self.data;
-        //let blob_data = self.data;
-        //let blob_length = self.data.len()?;
-        //assert_eq!{blob_length, 100, "blob_length: {:?}", blob_length}
-        if !(0 < data_length_last_31_bytes
-            && data_length_last_31_bytes
-            <= CHUNK_DATA_SIZE as u8) {
-                error!("{:?} is not more than 0 bytes and less than or equal to {:?}",
-                data_length_last_31_bytes,
-                CHUNK_DATA_SIZE)
-            }
-        // For collecting chunks:
-        let mut chunks: Vec<Chunk> = vec![];
-        // Iterate, collecting the blob into chunks
-        // Remember, for loops don't iterate the last step in a range!
-        // So start from 0 to add 1 (and to avoid doing operations for performance)
-        for i in 0..chunks_per_blob {
-            // 0, 1, 2, ... chunks_per_blob - 1
-            let mut indicator: u8 = 0b0000_0000;
-            let mut ch: Chunk;
-            let i_data_start: usize = (i * CHUNK_DATA_SIZE) as usize;
-            // 0, 31, 62, ... chunks_per_blob * CHUNK_DATA_SIZE
-            let mut chunk_data: [u8; CHUNK_DATA_SIZE] = [0; CHUNK_DATA_SIZE];
-            if i != chunks_per_blob - 1 {
-                // Build non-terminal chunks
-                // i_data_end_plus_1 = i_data_start_of_next_31_bytes
-                let i_data_end_plus_1: usize = ((i + 1) * CHUNK_DATA_SIZE) as usize;
-                // 31, 62, 93, ..., (chunks_per_blob + 1) * CHUNK_DATA_SIZE
-                // Again, it doesn't do the last step.
-                for j in i_data_start..(i_data_end_plus_1) {
-                    // j = (loop 1) 0..30, (loop 2), 31..61,
-                    // 62..92, ..., (chunks_per_blob - 2)*CHUNK_DATA_SIZE
-                    // .. (chunks_per_blob - 1) * CHUNK_DATA_SIZE - 1
-                    // chunks_per_blob won't run in this if loop, it will go up to
-                    // chunks_per_blob - 1, which will then not go into the block.
-                    chunk_data[j - i_data_start] = self.data[j];
-                }
-            } else {
-                // Build the terminal chunk
-
-                // Set the chunk data, saving iterating the last 0 consecutive bits of
-                // the terminal chunk, which are set to 0 anyway above.
-                for j in i_data_start..bytes_per_blob {
-                    chunk_data[j - i_data_start] = self.data[j];
-                }
-
-                //let last_31_bytes_in_blob_in_reverse = blob_data[blob_length-31..].reverse();
-                for i in (0..CHUNK_DATA_SIZE).rev() {
-                    //assert_eq!{self.data[i], 100, "self.data: {:?}\nself.data[i]: {:?}\ni: {:?}", self.data, self.data[i], i}
-                    //assert_eq!{i, 100, "i: {:?}", i}
-                    if chunk_data[i] == 0 {
-                        data_length_last_31_bytes -= 1;
-                        /*
-                        assert_eq!(data_length_last_31_bytes,
-                            3.14,
-                            "\n\ndata_length_last_31_bytes: {:?}\ni:{:?}\nself.data[i]:{:?}\n\n",
-                            data_length_last_31_bytes, i, self.data[i]);
-                        */
-                    } else {
-                        break;
-                    }
-                }
-
-                // Set the 5 least significant bits of the indicator byte
-                //assert_eq![chunk_data, [0; 31], "left = chunk_data"];
-                //assert_eq!(data_length_last_31_bytes, 50, "indicator: {:?}", indicator);
-                indicator = indicator
-                    | data_length_last_31_bytes;
-                // assert_eq!(indicator, 50, "indicator: {:?}", indicator);
-            }
-            // Set the first bit of the indicator byte, the skip_evm flag,
-            // if a skip_evm opcode was called.
-            // This will be false until there are multiple EVMs.
-            // The skip_evm opcode could change the value of skip_evm to true
-            if skip_evm {
-                // Set SKIP_EVM flag to 1
-                indicator = indicator | 0b1000_0000;
-            }
-            ch = Chunk::new(indicator, chunk_data);
-            chunks.push(ch);
-        }
-        chunks/* Ok(chunks) */
+        let chunks_per_blob: usize = self.chunk_count();
+        self.data.chunks(CHUNK_DATA_SIZE)
+            .enumerate()
+            .map(|(i, chunk_data)| Chunk::from_blob_data(chunk_data, skip_evm, i == chunks_per_blob - 1))
+            .collect()
     }
 
-    /// Create a blob from a set of chunks
-    pub fn from_chunks(chunks: Vec<Chunk>) -> Blob {
+    /// Create a blob from a set of chunks. Reads all CHUNK_DATA_SIZE bytes of each
+    /// non-terminal chunk, then only the length bytes of the terminal chunk, so
+    /// `Blob::from_chunks(blob.to_chunks(skip_evm))` gives back `blob` for every blob.
+    /// Chunks after the terminal chunk are not part of the blob.
+    ///
+    /// Returns an error if a chunk has an invalid indicator byte, or if there are
+    /// chunks but none of them is terminal.
+    pub fn from_chunks(chunks: Vec<Chunk>) -> Result<Blob> {
         let mut data = vec![];
+        let chunks_len = chunks.len();
+        let mut terminated = chunks.is_empty();
         for ch in chunks {
-            let mask: u8 = 0b0001_1111;
-            let data_bytes_length_in_terminal_chunk = &ch.indicator & mask;
-            // read the first 3 bits of the indicator and discard the result.
-            //BitReader::new(&[ch.indicator]).read_u8(3).unwrap();
-            // read the remaining 5 bits into length bits
-            //let mut data_bytes_length_in_terminal_chunk
-            //    = BitReader::new(&[ch.indicator]).read_u8(5).unwrap();
-            for i in 0..CHUNK_DATA_SIZE {
-                data.push(ch.data[i as usize]);
+            ch.validate()?;
+            data.extend_from_slice(ch.blob_data());
+            if ch.is_terminal() {
+                terminated = true;
+                break;
             }
-            /* This is commented out because the else block for a terminal chunk is not
-            writing the final 0 bytes, where data above was instantiated as an empty vector.
-            There is a better way to do this. We can instantiate data as vec![0; 31],
-            and just have one for loop, regardless of the value of data_bytes_length_in_terminal_chunk.
-            if data_bytes_length_in_terminal_chunk == 0 {
-                // Chunk is not terminal, read all CHUNK_DATA_SIZE bytes into data.
-                for i in 0..CHUNK_DATA_SIZE {
-                    data.push(ch.data[i as usize]);
-                }
-            } else {
-                // Chunk is terminal, read data_bytes_length_in_terminal_chunk bytes into data
-                for i in 0..data_bytes_length_in_terminal_chunk {
-                    data.push(ch.data[i as usize]);
-                }
-            }
-            */
         }
-        Blob {
-            data
+        if !terminated {
+            bail!(ErrorKind::MissingTerminalChunk(chunks_len));
         }
+        Ok(Blob {
+            data
+        })
     }
 
-    /// Put blob chunks into (a) collation body(ies).
-    /// This is incomplete.
-    /// TODO:  functionality for packing multiple blobs into
-    /// one collation body.
+    /// Hash the blob data with Keccak-256.
+    pub fn hash(&self) -> BlobHash {
+        BlobHash::from(tiny_keccak::keccak256(&self.data))
+    }
+
+    /// The number of chunks needed to serialize this blob.
+    pub fn chunk_count(&self) -> usize {
+        self.data.len().div_ceil(CHUNK_DATA_SIZE)
+    }
+
+    /// Put blob chunks into a new collation body. To pack multiple blobs into
+    /// one collation body, use `body::BodyBuilder`.
     /// Further details, see:
-    /// https://ethresear.ch/t/blob-serialisation/1705/17 */
-    /* if to_chunks(blob).length > CHUNKS_PER_COLLATION {
-        Serialize a blob into multiple collation bodies.
-    } else {
-        Pack the blob chunks into the collation body.
-    }*/
+    /// https://ethresear.ch/t/blob-serialisation/1705/17
+    ///
+    /// Returns an error if the blob is larger than `DATA_BYTES_PER_COLLATION`, use
+    /// `blob_to_collation_bodies` for those.
     pub fn blob_to_collation_body(self,
-        /* collation_header_hash: CollationHeaderHash */) -> Body {
+        /* collation_header_hash: CollationHeaderHash */) -> Result<Body> {
         // We can't just create a collation body, then put a blob into that,
         // since we need to maintain an order of putting blobs into
         // collation bodies, and bodies into collations, and collations
@@ -203,42 +108,11 @@ impl Blob {
         // it's CollationHeaderHash. Instantiate a var. to represent a
         // collation body from the input collation_header_hash.
         let bytes_per_blob: usize = self.data.len();
-        let blob_as_chunks = self.to_chunks(false);
         if bytes_per_blob > DATA_BYTES_PER_COLLATION {
-            //let mut chunk = [0; 31]
-            //let mut body = Body::new(chunk);
-            //let mut blob_bodies = BlobBodies::new(body);
-            /* TODO: this is more of a hack than a proper fix. We still want this to panic to avoid unexpected behaviour,
-            or be able to serialize a blob that is larger than a collation (although perhaps not many will do that due
-            to the expense, and implementing this is not a high priority, it can probably be done in like phase 6 or 
-            later).*/
-            warn!("Sorry, sharding developers and researchers haven't agreed on a way to serialize blobs that are \
-                larger than a DATA_BYTES_PER_COLLATION ({:?} bytes), please split this blob up into blobs that are  \
-                smaller than this and try again, or lobby us to do this.", DATA_BYTES_PER_COLLATION);
-            // TODO @dev fix panic case with blob serialization (tim will do)
-            /*error!("Sorry, sharding developers and researchers haven't agreed on a way to serialize blobs that are \
-                larger than a DATA_BYTES_PER_COLLATION ({:?} bytes), please split this blob up into blobs that are  \
-                smaller than this and try again, or lobby us to do this.", DATA_BYTES_PER_COLLATION);
-            */
-            //process::exit(1);
-            /*
-            for chunk in blob_as_chunks {
-                if chunk % CHUNKS_PER_COLLATION == 0 {
-                    // 0..CHUNKS_PER_COLLATION - 1 = first collation body
-                    // CHUNKS_PER_COLLATION * n = start of a new collation body
-                    // Therefore, start a new collation body.
-                    let mut body = Body::new(chunk);
-                } else {
-                    body.push(chunk);
-                }
-                if chunk % CHUNKS_PER_COLLATION == CHUNKS_PER_COLLATION - 1 {
-                    // first collation of a blob
-                } else  {
-                    BlobBodies.push(body)
-                }
-            }
-            */
+            bail!(ErrorKind::BodyTooLarge(self.chunk_count()));
         }
+        let blob_as_chunks = self.to_chunks(false);
+
         // Find a collation body that has been created.
         // Search the shard binary Merkle trie for a collation that has the input collation_header_hash.
         // TODO
@@ -264,7 +138,40 @@ impl Blob {
             }
         }
         */
-        body
+        Ok(body)
+    }
+
+    /// Serialize a blob of any size into successive collation bodies of up to
+    /// `CHUNKS_PER_COLLATION` chunks each. Only the last body of the blob contains
+    /// the terminal chunk.
+    pub fn blob_to_collation_bodies(self) -> BlobBodies {
+        let blob_hash = self.hash();
+        let blob_as_chunks = self.to_chunks(false);
+        let bodies: Vec<Body> = blob_as_chunks
+            .chunks(CHUNKS_PER_COLLATION)
+            .map(|body_chunks| Body::new(body_chunks.to_vec()))
+            .collect();
+        BlobBodies::new(blob_hash, bodies)
+    }
+
+    /// Reassemble a blob from the bodies it was serialized into, in any order.
+    /// Returns an error if any of the bodies are missing or duplicated, if their
+    /// chunks are malformed, or if the reassembled blob doesn't match the blob hash.
+    pub fn from_collation_bodies(blob_bodies: BlobBodies) -> Result<Blob> {
+        let blob_hash = blob_bodies.blob_hash;
+        if !blob_bodies.is_complete() {
+            bail!(ErrorKind::IncompleteBlobBodies(blob_hash, blob_bodies.total));
+        }
+        let mut bodies = blob_bodies.bodies;
+        bodies.sort_by_key(|blob_body| blob_body.index);
+        let chunks: Vec<Chunk> = bodies.into_iter()
+            .flat_map(|blob_body| blob_body.body.chunks)
+            .collect();
+        let blob = Blob::from_chunks(chunks)?;
+        if blob.hash() != blob_hash {
+            bail!(ErrorKind::BlobHashMismatch(blob_hash, blob.hash()));
+        }
+        Ok(blob)
     }
 }
 
@@ -278,15 +185,15 @@ mod tests {
         // run as cargo test mb_blob_zeros_to_collation_body -- --ignored -> mb_blob_zeros_to_collation_body.log
         let blob = Blob::new(vec![0; DATA_BYTES_PER_COLLATION]);
         // let mut blob_as_chunks = blob.to_chunks();
-        let sample_body = blob.blob_to_collation_body();
+        let sample_body = blob.blob_to_collation_body().unwrap();
 
 /*         let sample_chunk = Chunk::new(0, [0; 31]);
         let sample_chunks = vec![sample_chunk; CHUNKS_PER_COLLATION];
         let blob2 = Blob::new(vec![1; DATA_BYTES_PER_COLLATION]);
         let blob_chunks = blob2.to_chunks(false);*/
         let non_terminal_chunk_indicator = Chunk::build_indicator(false, false, 0);
-        // Since all bytes are 0, the length is 0.
-        let terminal_chunk_indicator = Chunk::build_indicator(false, true, 0);
+        // The zero bytes are part of the blob, so the length is still 31.
+        let terminal_chunk_indicator = Chunk::build_indicator(false, true, 31);
         let mut correct_blob_chunks = vec![Chunk::new(non_terminal_chunk_indicator,
             [0; CHUNK_DATA_SIZE]); CHUNKS_PER_COLLATION - 1];
         correct_blob_chunks.push(Chunk::new(terminal_chunk_indicator, [0; 31]));
@@ -300,7 +207,7 @@ mod tests {
         // run as cargo test mb_blob_to_collation_body -- --ignored -> mb_blob_to_collation_body.log
         let blob = Blob::new(vec![1; DATA_BYTES_PER_COLLATION]);
         // let mut blob_as_chunks = blob.to_chunks();
-        let sample_body = blob.blob_to_collation_body();
+        let sample_body = blob.blob_to_collation_body().unwrap();
 
 /*         let sample_chunk = Chunk::new(0, [0; 31]);
         let sample_chunks = vec![sample_chunk; CHUNKS_PER_COLLATION];
@@ -317,24 +224,112 @@ mod tests {
     }
 
     #[test]
-    // TODO @dev fix this
-    //#[should_panic]
+    fn mb_1_b_blob_to_colltn_body_fails() {
+        let blob = Blob::new(vec![0; DATA_BYTES_PER_COLLATION + 1]);
+        match blob.blob_to_collation_body() {
+            Err(Error(ErrorKind::BodyTooLarge(chunks), _)) => assert_eq!(chunks, CHUNKS_PER_COLLATION + 1),
+            result => panic!("Expected body too large error, got {:?}", result)
+        }
+    }
+
+    #[test]
     fn mb_1_b_blob_to_2_colltn_bodies() {
-        let blob = Blob::new(vec![0; COLLATION_SIZE + 1]);
-        // assert_eq!(blob.data.len(), COLLATION_SIZE + 1); // this actually passes
-        // assert!(blob.data.len() > COLLATION_SIZE); // this passes if you uncomment this, but fails otherwise.
-        // let mut blob_as_chunks = to_chunks(blob);
-        let body = blob.blob_to_collation_body();
+        let blob = Blob::new(vec![1; DATA_BYTES_PER_COLLATION + 1]);
+        let blob_hash = blob.hash();
+        let blob_bodies = blob.blob_to_collation_bodies();
+
+        assert_eq!(blob_bodies.blob_hash, blob_hash);
+        assert_eq!(blob_bodies.total, 2);
+        assert_eq!(blob_bodies.bodies[0].index, 0);
+        assert_eq!(blob_bodies.bodies[1].index, 1);
+
+        // The first body is full of non-terminal chunks.
+        let non_terminal_chunk_indicator = Chunk::build_indicator(false, false, 0);
+        let first_body = Body::new(vec![Chunk::new(non_terminal_chunk_indicator,
+            [1; CHUNK_DATA_SIZE]); CHUNKS_PER_COLLATION]);
+        assert_eq!(blob_bodies.bodies[0].body, first_body);
+
+        // The second body only has the terminal chunk with the last byte.
+        let terminal_chunk_indicator = Chunk::build_indicator(false, true, 1);
+        let mut terminal_chunk_data = [0; CHUNK_DATA_SIZE];
+        terminal_chunk_data[0] = 1;
+        let second_body = Body::new(vec![Chunk::new(terminal_chunk_indicator, terminal_chunk_data)]);
+        assert_eq!(blob_bodies.bodies[1].body, second_body);
     }
 
     #[test]
-    fn to_chunks_skip_evm_0_4_b_blob() {
-        let mut blob = Blob::new(vec![0; 31]);
-        for i in 0..4 {
-            blob.data[i] = 0xff;
+    fn small_blob_to_1_colltn_body() {
+        let blob = Blob::new(vec![0xff; CHUNK_DATA_SIZE * 4]);
+        let expected_body = blob.clone().blob_to_collation_body().unwrap();
+        let blob_bodies = blob.blob_to_collation_bodies();
+
+        assert_eq!(blob_bodies.total, 1);
+        assert_eq!(blob_bodies.bodies[0].body, expected_body);
+    }
+
+    #[test]
+    fn it_reassembles_blob_from_colltn_bodies_in_any_order() {
+        let blob = Blob::new(vec![0xff; DATA_BYTES_PER_COLLATION * 2 + CHUNK_DATA_SIZE]);
+        let mut blob_bodies = blob.clone().blob_to_collation_bodies();
+        assert_eq!(blob_bodies.total, 3);
+
+        blob_bodies.bodies.reverse();
+        let reassembled_blob = Blob::from_collation_bodies(blob_bodies).unwrap();
+        assert_eq!(reassembled_blob, blob);
+    }
+
+    #[test]
+    fn it_does_not_reassemble_blob_with_missing_colltn_body() {
+        let blob = Blob::new(vec![0xff; DATA_BYTES_PER_COLLATION * 2 + CHUNK_DATA_SIZE]);
+        let mut blob_bodies = blob.blob_to_collation_bodies();
+        blob_bodies.bodies.remove(1);
+        assert!(!blob_bodies.is_complete());
+        match Blob::from_collation_bodies(blob_bodies.clone()) {
+            Err(Error(ErrorKind::IncompleteBlobBodies(_, total), _)) => assert_eq!(total, 3),
+            result => panic!("Expected incomplete blob bodies error, got {:?}", result)
+        }
+
+        // Duplicating a body doesn't make up for the missing one.
+        let duplicate = blob_bodies.bodies[0].clone();
+        blob_bodies.bodies.push(duplicate);
+        assert!(Blob::from_collation_bodies(blob_bodies).is_err());
+    }
+
+    #[test]
+    fn it_does_not_reassemble_blob_with_wrong_hash() {
+        let blob = Blob::new(vec![0xff; DATA_BYTES_PER_COLLATION + 1]);
+        let mut blob_bodies = blob.blob_to_collation_bodies();
+        blob_bodies.bodies[0].body.chunks[0].data[0] = 0;
+        match Blob::from_collation_bodies(blob_bodies) {
+            Err(Error(ErrorKind::BlobHashMismatch(..), _)) => {},
+            result => panic!("Expected blob hash mismatch error, got {:?}", result)
+        }
+    }
+
+    #[test]
+    fn it_rejects_chunks_without_terminal_chunk() {
+        let chunks = vec![Chunk::new(Chunk::build_indicator(false, false, 0), [1; CHUNK_DATA_SIZE]); 2];
+        match Blob::from_chunks(chunks) {
+            Err(Error(ErrorKind::MissingTerminalChunk(chunks), _)) => assert_eq!(chunks, 2),
+            result => panic!("Expected missing terminal chunk error, got {:?}", result)
         }
+    }
+
+    #[test]
+    fn it_rejects_chunks_with_reserved_indicator_bits() {
+        let mut chunks = Blob::new(vec![1; CHUNK_DATA_SIZE * 2]).to_chunks(false);
+        chunks[0].indicator |= 0b0100_0000;
+        match Blob::from_chunks(chunks) {
+            Err(Error(ErrorKind::ReservedIndicatorBitsSet(indicator), _)) => assert_eq!(indicator, 0b0100_0000),
+            result => panic!("Expected reserved indicator bits error, got {:?}", result)
+        }
+    }
+
+    #[test]
+    fn to_chunks_skip_evm_0_4_b_blob() {
+        let blob = Blob::new(vec![0xff; 4]);
         let blob_chunks = blob.to_chunks(false);
-        // If you set the length to 4, it will not include the zeros in blob.data.
+        // The length is 4, the rest of the terminal chunk is padded with zeros.
         let terminal_chunk_indicator = Chunk::build_indicator(false, true, 4);
         let correct_blob_chunks = vec![Chunk::new(terminal_chunk_indicator,
                                                                 [0xff, 0xff, 0xff, 0xff,
@@ -427,8 +422,8 @@ mod tests {
         // Alternative: https://play.rust-lang.org/?gist=53969b6c3cad694d219091e8550c8ccc&version=undefined&mode=undefined
         // Less efficient: https://play.rust-lang.org/?gist=8ca17ca652ab781b5c4e1fe14ef4f919&version=stable&mode=debug
         let chunks = vec![Chunk::new(terminal_chunk_indicator, chunk_1_data)];
-        let blob_from_chunks = Blob::from_chunks(chunks);
-        let blob = Blob::new(chunk_1_data.to_vec());
+        let blob_from_chunks = Blob::from_chunks(chunks).unwrap();
+        let blob = Blob::new(chunk_1_data[..4].to_vec());
         assert_eq!(blob, blob_from_chunks,
             "\nblob.data.len(): {:?}, blob_from_chunks.data.len(): {:?}\
             \ndifference = {:?}\n",
@@ -448,11 +443,8 @@ mod tests {
         // 2nd terminal chunk, with one byte as 255.
         let mut chunk_2_data = [0; 31]; chunk_2_data[0] = 255;
         chunks.push(Chunk::new(terminal_chunk_indicator, chunk_2_data));
-        let blob_from_chunks = Blob::from_chunks(chunks);
-        let mut blob = Blob::new(vec![0; 62]);
-        for i in 0..32 {
-            blob.data[i] = 255;
-        }
+        let blob_from_chunks = Blob::from_chunks(chunks).unwrap();
+        let blob = Blob::new(vec![255; 32]);
         assert_eq!(blob, blob_from_chunks,
             "\nblob.data.len(): {:?}, blob_from_chunks.data.len(): {:?}\
             \ndifference = {:?}\n",
@@ -472,7 +464,7 @@ mod tests {
             [255; CHUNK_DATA_SIZE]); 4];
         // 5th terminal chunk, also with every non-indicator byte as 255
         chunks.push(Chunk::new(terminal_chunk_indicator, [255; CHUNK_DATA_SIZE]));
-        let blob_from_chunks = Blob::from_chunks(chunks);
+        let blob_from_chunks = Blob::from_chunks(chunks).unwrap();
         let blob = Blob::new(vec![255; CHUNK_DATA_SIZE*5]);//155
         assert_eq!(blob, blob_from_chunks,
             "\nblob.data.len(): {:?}, blob_from_chunks.data.len(): {:?}\
@@ -493,7 +485,7 @@ mod tests {
             [255; CHUNK_DATA_SIZE]); 4];
         // ditto: 5th terminal chunk, also with every non-indicator byte as 255
         chunks.push(Chunk::new(terminal_chunk_indicator, [255; CHUNK_DATA_SIZE]));
-        let blob_from_chunks = Blob::from_chunks(chunks);
+        let blob_from_chunks = Blob::from_chunks(chunks).unwrap();
         let blob = Blob::new(vec![255; CHUNK_DATA_SIZE*5]);
         assert_eq!(blob, blob_from_chunks,
             "\nblob.data.len(): {:?}, blob_from_chunks.data.len(): {:?}\
@@ -501,6 +493,65 @@ mod tests {
             blob.data.len(), blob_from_chunks.data.len(),
             blob.data.len() - blob_from_chunks.data.len());
     }
+
+    /// Blobs of every length from 0 to 4 chunks, with data that is all zeros, has no
+    /// zeros, or ends in zeros.
+    fn sample_blobs() -> Vec<Blob> {
+        let mut blobs = vec![];
+        for length in 0..(CHUNK_DATA_SIZE * 4 + 1) {
+            blobs.push(Blob::new(vec![0; length]));
+            blobs.push(Blob::new(vec![0xff; length]));
+            blobs.push(Blob::new((0..length).map(|i| if i < length / 2 { 1 } else { 0 }).collect()));
+            blobs.push(Blob::new((0..length).map(|i| (i % 3) as u8).collect()));
+        }
+        blobs
+    }
+
+    #[test]
+    fn it_round_trips_every_blob_through_chunks() {
+        for blob in sample_blobs() {
+            for &skip_evm in &[false, true] {
+                let chunks = blob.clone().to_chunks(skip_evm);
+                assert_eq!(chunks.len(), blob.chunk_count());
+                assert_eq!(Blob::from_chunks(chunks).unwrap(), blob,
+                    "Blob of {:?} bytes with skip_evm {:?} didn't round-trip", blob.data.len(), skip_evm);
+            }
+        }
+    }
+
+    #[test]
+    fn it_round_trips_every_blob_through_bytes() {
+        for blob in sample_blobs() {
+            let chunks: Vec<Chunk> = blob.clone().to_chunks(false)
+                .into_iter()
+                .map(|chunk| Chunk::bytes_to_chunk(chunk.chunk_to_bytes()).unwrap())
+                .collect();
+            assert_eq!(Blob::from_chunks(chunks).unwrap(), blob);
+        }
+    }
+
+    #[test]
+    fn it_tells_apart_blobs_with_trailing_zeros() {
+        let short_blob = Blob::new(vec![1, 2, 3]);
+        let long_blob = Blob::new(vec![1, 2, 3, 0, 0]);
+        assert_ne!(short_blob.clone().to_chunks(false), long_blob.clone().to_chunks(false));
+        assert_eq!(Blob::from_chunks(short_blob.clone().to_chunks(false)).unwrap(), short_blob);
+        assert_eq!(Blob::from_chunks(long_blob.clone().to_chunks(false)).unwrap(), long_blob);
+    }
+
+    #[test]
+    fn empty_blob_has_no_chunks() {
+        let blob = Blob::new(vec![]);
+        assert_eq!(blob.clone().to_chunks(false), vec![]);
+        assert_eq!(Blob::from_chunks(vec![]).unwrap(), blob);
+    }
+
+    #[test]
+    fn it_round_trips_zero_blob_through_colltn_bodies() {
+        let blob = Blob::new(vec![0; DATA_BYTES_PER_COLLATION + 1]);
+        let blob_bodies = blob.clone().blob_to_collation_bodies();
+        assert_eq!(Blob::from_collation_bodies(blob_bodies).unwrap(), blob);
+    }
 }
 
 // Not used
@@ -0,0 +1,251 @@
+use modules::collation::chunk::Chunk;
+use modules::constants::{CHUNK_SIZE, CHUNK_DATA_SIZE};
+use modules::errors::*;
+
+use std::io;
+use std::io::{Read, Write};
+
+/// Serializes a blob into chunks as its bytes are written, without holding the whole
+/// blob in memory. Produces the same chunks as `Blob::to_chunks`, so the output is a
+/// valid (part of a) collation body.
+///
+/// The last chunk can only be written once it is known to be terminal, so `finish`
+/// must be called after writing the whole blob.
+pub struct BlobWriter<W: Write> {
+    inner: W,
+    skip_evm: bool,
+    // Blob data that hasn't been written to a chunk yet, at most CHUNK_DATA_SIZE bytes.
+    pending: Vec<u8>
+}
+
+impl<W: Write> BlobWriter<W> {
+    pub fn new(inner: W, skip_evm: bool) -> BlobWriter<W> {
+        BlobWriter {
+            inner,
+            skip_evm,
+            pending: Vec::with_capacity(CHUNK_DATA_SIZE)
+        }
+    }
+
+    /// Write the terminal chunk, flush and return the inner writer.
+    /// An empty blob has no chunks, so nothing is written for it.
+    pub fn finish(mut self) -> io::Result<W> {
+        if !self.pending.is_empty() {
+            let chunk = Chunk::from_blob_data(&self.pending, self.skip_evm, true);
+            self.inner.write_all(&chunk.chunk_to_bytes())?;
+        }
+        self.inner.flush()?;
+        Ok(self.inner)
+    }
+}
+
+impl<W: Write> Write for BlobWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut remaining = buf;
+        while !remaining.is_empty() {
+            // A full pending chunk is only written once more data arrives, since it is
+            // terminal if the blob ends here.
+            if self.pending.len() == CHUNK_DATA_SIZE {
+                let chunk = Chunk::from_blob_data(&self.pending, self.skip_evm, false);
+                self.inner.write_all(&chunk.chunk_to_bytes())?;
+                self.pending.clear();
+            }
+            let take = (CHUNK_DATA_SIZE - self.pending.len()).min(remaining.len());
+            self.pending.extend_from_slice(&remaining[..take]);
+            remaining = &remaining[take..];
+        }
+        Ok(buf.len())
+    }
+
+    /// Flush the chunks written so far. The pending data of the last chunk is kept
+    /// until more data is written or `finish` is called.
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Reads the chunks of a blob from the inner reader and yields the blob data, one
+/// chunk at a time. Reading stops after the terminal chunk, so the inner reader is
+/// left at the start of the next blob in a body.
+///
+/// Malformed chunks give an `io::ErrorKind::InvalidData` error.
+pub struct BlobReader<R: Read> {
+    inner: R,
+    chunk: Chunk,
+    // Position of the next byte to return from the current chunk's blob data.
+    position: usize,
+    chunks_read: usize,
+    // Whether the terminal chunk has been read, or the blob is empty.
+    done: bool
+}
+
+impl<R: Read> BlobReader<R> {
+    pub fn new(inner: R) -> BlobReader<R> {
+        BlobReader {
+            inner,
+            chunk: Chunk::new(0, [0; CHUNK_DATA_SIZE]),
+            position: CHUNK_DATA_SIZE,
+            chunks_read: 0,
+            done: false
+        }
+    }
+
+    /// Return the inner reader, e.g. to read the next blob packed into a body.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    /// Read the next chunk. Returns false if the reader ended cleanly before the
+    /// first chunk, i.e. the blob is empty.
+    fn read_chunk(&mut self) -> io::Result<bool> {
+        let mut chunk_bytes: [u8; CHUNK_SIZE] = [0; CHUNK_SIZE];
+        let mut filled = 0;
+        while filled < CHUNK_SIZE {
+            match self.inner.read(&mut chunk_bytes[filled..]) {
+                Ok(0) => break,
+                Ok(n) => filled += n,
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {},
+                Err(e) => return Err(e)
+            }
+        }
+        if filled == 0 && self.chunks_read == 0 {
+            return Ok(false);
+        }
+        if filled == 0 {
+            return Err(invalid_data(ErrorKind::MissingTerminalChunk(self.chunks_read).into()));
+        }
+        if filled < CHUNK_SIZE {
+            let length = self.chunks_read * CHUNK_SIZE + filled;
+            return Err(invalid_data(ErrorKind::BodyNotChunkAligned(length).into()));
+        }
+        self.chunk = Chunk::bytes_to_chunk(chunk_bytes).map_err(invalid_data)?;
+        self.chunks_read += 1;
+        self.position = 0;
+        // Don't read past the end of the blob.
+        self.done = self.chunk.is_terminal();
+        Ok(true)
+    }
+}
+
+impl<R: Read> Read for BlobReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        // Move on to the next chunk once all of the current chunk's data has been read.
+        while self.position == self.chunk.blob_data().len() {
+            if self.done || !self.read_chunk()? {
+                self.done = true;
+                return Ok(0);
+            }
+        }
+        let blob_data = self.chunk.blob_data();
+        let n = (blob_data.len() - self.position).min(buf.len());
+        buf[..n].copy_from_slice(&blob_data[self.position..self.position + n]);
+        self.position += n;
+        Ok(n)
+    }
+}
+
+fn invalid_data(e: Error) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use modules::collation::blob::Blob;
+    use modules::collation::body::{Body, BodyBuilder};
+
+    fn write_blob_in_pieces(data: &[u8], piece_size: usize, skip_evm: bool) -> Vec<u8> {
+        let mut writer = BlobWriter::new(vec![], skip_evm);
+        for piece in data.chunks(piece_size) {
+            writer.write_all(piece).unwrap();
+        }
+        writer.finish().unwrap()
+    }
+
+    fn read_blob_in_pieces(bytes: &[u8], piece_size: usize) -> Vec<u8> {
+        let mut reader = BlobReader::new(bytes);
+        let mut data = vec![];
+        let mut piece = vec![0; piece_size];
+        loop {
+            let n = reader.read(&mut piece).unwrap();
+            if n == 0 {
+                break;
+            }
+            data.extend_from_slice(&piece[..n]);
+        }
+        data
+    }
+
+    #[test]
+    fn it_writes_same_chunks_as_to_chunks() {
+        for &length in &[0, 1, CHUNK_DATA_SIZE - 1, CHUNK_DATA_SIZE, CHUNK_DATA_SIZE + 1, 1000] {
+            let data: Vec<u8> = (0..length).map(|i| (i % 7) as u8).collect();
+            let expected = Body::new(Blob::new(data.clone()).to_chunks(true)).to_bytes();
+            for &piece_size in &[1, 5, CHUNK_DATA_SIZE, 64, 4096] {
+                assert_eq!(write_blob_in_pieces(&data, piece_size, true), expected,
+                    "Blob of {:?} bytes written {:?} bytes at a time", length, piece_size);
+            }
+        }
+    }
+
+    #[test]
+    fn it_reads_back_written_blob() {
+        for &length in &[0, 1, CHUNK_DATA_SIZE, CHUNK_DATA_SIZE * 3 + 4, 10000] {
+            let data: Vec<u8> = (0..length).map(|i| if i % 2 == 0 { 0 } else { 0xab }).collect();
+            let bytes = write_blob_in_pieces(&data, 100, false);
+            for &piece_size in &[1, 7, CHUNK_DATA_SIZE, 8192] {
+                assert_eq!(read_blob_in_pieces(&bytes, piece_size), data);
+            }
+        }
+    }
+
+    #[test]
+    fn it_reads_blobs_packed_into_a_body() {
+        let first_blob = Blob::new(vec![1; CHUNK_DATA_SIZE * 2 + 3]);
+        let second_blob = Blob::new(vec![2, 0, 0]);
+        let mut builder = BodyBuilder::new();
+        builder.add_blob(first_blob.clone()).unwrap();
+        builder.add_blob(second_blob.clone()).unwrap();
+        let bytes = builder.build().to_bytes();
+
+        let mut reader = BlobReader::new(&bytes[..]);
+        let mut data = vec![];
+        reader.read_to_end(&mut data).unwrap();
+        assert_eq!(data, first_blob.data);
+
+        let mut reader = BlobReader::new(reader.into_inner());
+        let mut data = vec![];
+        reader.read_to_end(&mut data).unwrap();
+        assert_eq!(data, second_blob.data);
+    }
+
+    #[test]
+    fn it_rejects_stream_without_terminal_chunk() {
+        let chunk = Chunk::new(0, [1; CHUNK_DATA_SIZE]);
+        let bytes = Body::new(vec![chunk; 2]).to_bytes();
+        let mut data = vec![];
+        let error = BlobReader::new(&bytes[..]).read_to_end(&mut data).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn it_rejects_stream_not_chunk_aligned() {
+        let mut bytes = Body::new(Blob::new(vec![1; 40]).to_chunks(false)).to_bytes();
+        bytes.truncate(CHUNK_SIZE + 10);
+        let mut data = vec![];
+        let error = BlobReader::new(&bytes[..]).read_to_end(&mut data).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn it_rejects_stream_with_reserved_indicator_bits() {
+        let mut bytes = Body::new(Blob::new(vec![1; 40]).to_chunks(false)).to_bytes();
+        bytes[CHUNK_SIZE] |= 0b0100_0000;
+        let mut data = vec![];
+        let error = BlobReader::new(&bytes[..]).read_to_end(&mut data).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+}
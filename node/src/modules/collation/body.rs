@@ -1,7 +1,14 @@
 use modules::collation::chunk::Chunk;
 use modules::collation::blob::Blob;
-use modules::constants::COLLATION_SIZE;
+use modules::collation::merkle;
+use modules::collation::merkle::{MerkleProof, MerkleTree};
+use modules::constants::{CHUNK_SIZE, CHUNK_DATA_SIZE, CHUNKS_PER_COLLATION, DATA_BYTES_PER_COLLATION,
+    CHUNK_TREE_DEPTH};
+use modules::primitives::{BlobHash, ChunkRootHash};
 use modules::errors::*;
+use modules::hex::{from_hex, to_hex};
+
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
 
 /// Collation body storing arbitrary data or blobs, serialized into 32-byte chunks.
 #[derive(PartialEq, Debug, Clone)]
@@ -15,28 +22,419 @@ impl Body {
             chunks
         }
     }
+
+    /// The size of the body in bytes, CHUNK_SIZE bytes per chunk
+    pub fn size(&self) -> usize {
+        self.chunks.len() * CHUNK_SIZE
+    }
+
+    /// Serialize the body into bytes, CHUNK_SIZE bytes per chunk.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.chunks.len() * CHUNK_SIZE);
+        for chunk in &self.chunks {
+            bytes.extend_from_slice(&chunk.chunk_to_bytes());
+        }
+        bytes
+    }
+
+    /// Deserialize a body from bytes, e.g. received from a peer. Returns an error if
+    /// the length is not a multiple of CHUNK_SIZE or if any chunk is malformed.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Body> {
+        if !bytes.len().is_multiple_of(CHUNK_SIZE) {
+            bail!(ErrorKind::BodyNotChunkAligned(bytes.len()));
+        }
+        let mut chunks = Vec::with_capacity(bytes.len() / CHUNK_SIZE);
+        for chunk_bytes in bytes.chunks(CHUNK_SIZE) {
+            let mut array: [u8; CHUNK_SIZE] = [0; CHUNK_SIZE];
+            array.copy_from_slice(chunk_bytes);
+            chunks.push(Chunk::bytes_to_chunk(array)?);
+        }
+        Ok(Body::new(chunks))
+    }
+
+    /// The root of the binary Merkle tree over the body's 32-byte chunks, which a
+    /// collation header commits to. The tree has CHUNKS_PER_COLLATION leaves, so a
+    /// shorter body has the same root as the body padded with zero chunks.
+    ///
+    /// Panics if the body has more than CHUNKS_PER_COLLATION chunks.
+    pub fn chunk_root(&self) -> ChunkRootHash {
+        self.chunk_tree().root()
+    }
+
+    /// Build a proof that the chunk at `index` is part of the body with this
+    /// chunk root, to be checked with `merkle::verify_chunk_proof`. The zero chunks
+    /// padding a short body to CHUNKS_PER_COLLATION chunks can be proven too.
+    /// Returns `None` if the index is outside the tree.
+    pub fn prove_chunk(&self, index: usize) -> Option<MerkleProof> {
+        self.chunk_tree().proof(index)
+    }
+
+    /// The binary Merkle tree over the body's chunks, for proving many chunks
+    /// without rebuilding the tree.
+    pub fn chunk_tree(&self) -> MerkleTree {
+        let leaves: Vec<ChunkRootHash> = self.chunks.iter()
+            .map(|chunk| ChunkRootHash::from(chunk.chunk_to_bytes()))
+            .collect();
+        MerkleTree::new(&leaves, CHUNK_TREE_DEPTH)
+    }
+
+    /// The chunk at `index`, where a short body is padded with zero chunks to
+    /// CHUNKS_PER_COLLATION chunks. Returns `None` if the index is outside the body.
+    pub fn chunk(&self, index: usize) -> Option<Chunk> {
+        match self.chunks.get(index) {
+            Some(chunk) => Some(*chunk),
+            None if index < CHUNKS_PER_COLLATION => Some(Chunk::new(0, [0; CHUNK_DATA_SIZE])),
+            None => None
+        }
+    }
+
+    /// Split the body back into the blobs that were packed into it, using the
+    /// terminal chunk of each blob to find where it ends. Zero chunks after the last
+    /// terminal chunk are padding and are ignored, but any other chunks there are
+    /// part of a blob without a terminal chunk, which is an error.
+    pub fn to_blobs(&self) -> Result<Vec<Blob>> {
+        let mut blobs: Vec<Blob> = vec![];
+        let mut blob_chunks: Vec<Chunk> = vec![];
+        for chunk in &self.chunks {
+            blob_chunks.push(*chunk);
+            if chunk.is_terminal() {
+                blobs.push(Blob::from_chunks(blob_chunks)?);
+                blob_chunks = vec![];
+            }
+        }
+        let zero_chunk = Chunk::new(0, [0; CHUNK_DATA_SIZE]);
+        if blob_chunks.iter().any(|chunk| *chunk != zero_chunk) {
+            bail!(ErrorKind::MissingTerminalChunk(blob_chunks.len()));
+        }
+        Ok(blobs)
+    }
+}
+
+/// A body is serialized as the hex of its bytes, e.g. in a message log, and its
+/// chunks are checked when it is deserialized.
+impl Serialize for Body {
+    fn serialize<S: Serializer>(&self, serializer: S) -> ::std::result::Result<S::Ok, S::Error> {
+        serializer.serialize_str(&to_hex(&self.to_bytes()))
+    }
+}
+
+impl<'de> Deserialize<'de> for Body {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> ::std::result::Result<Body, D::Error> {
+        let hex = String::deserialize(deserializer)?;
+        let bytes = from_hex(&hex).map_err(de::Error::custom)?;
+        Body::from_bytes(&bytes).map_err(de::Error::custom)
+    }
+}
+
+/// Packs several blobs back to back into one collation body, as sequences of chunks.
+/// See https://ethresear.ch/t/blob-serialisation/1705/17.
+#[derive(PartialEq, Debug, Clone, Default)]
+pub struct BodyBuilder {
+    chunks: Vec<Chunk>
+}
+
+impl BodyBuilder {
+    pub fn new() -> BodyBuilder {
+        BodyBuilder {
+            chunks: vec![]
+        }
+    }
+
+    /// The number of chunks that can still be added to the body.
+    pub fn free_chunks(&self) -> usize {
+        CHUNKS_PER_COLLATION - self.chunks.len()
+    }
+
+    /// Whether the blob fits into the remaining space in the body.
+    pub fn fits(&self, blob: &Blob) -> bool {
+        blob.chunk_count() <= self.free_chunks()
+    }
+
+    /// Append a blob after the blobs already in the body. If the blob doesn't fit,
+    /// it is handed back unchanged.
+    pub fn add_blob(&mut self, blob: Blob) -> ::std::result::Result<(), Blob> {
+        if !self.fits(&blob) {
+            debug!("A blob of {:?} chunks doesn't fit into a body with {:?} free chunks",
+                blob.chunk_count(), self.free_chunks());
+            return Err(blob);
+        }
+        self.chunks.extend(blob.to_chunks(false));
+        Ok(())
+    }
+
+    pub fn build(self) -> Body {
+        Body::new(self.chunks)
+    }
+
+    /// Build the body padded with zero chunks to CHUNKS_PER_COLLATION chunks, the
+    /// size of a collation body.
+    pub fn build_padded(mut self) -> Body {
+        self.chunks.resize(CHUNKS_PER_COLLATION, Chunk::new(0, [0; CHUNK_DATA_SIZE]));
+        Body::new(self.chunks)
+    }
 }
 
 // This must be outside the impl Body, since you can't create a 
 // specific instance in an impl, AFAIK.
 pub fn create_sample_collation_body() -> Body {
-        let blob = Blob::new(vec![4; COLLATION_SIZE]);
-        let sample_body = blob.blob_to_collation_body();
+        let blob = Blob::new(vec![4; DATA_BYTES_PER_COLLATION]);
+        let sample_body = blob.blob_to_collation_body()
+            .expect("The sample blob fits into one collation body");
         //println!("{:?}", body);
         sample_body
 }
 
-/// Serialize collation bodies that correspond to the same blob. In practice a blob should contain
-///  a hash and this struct should then contain that hash as a field.
+/// Serialize collation bodies that correspond to the same blob, for blobs that are larger than
+/// `DATA_BYTES_PER_COLLATION`. Each body is tagged with its position in the blob, so that the
+/// bodies can be put into collations in any order and reassembled later with
+/// `Blob::from_collation_bodies`.
+#[derive(PartialEq, Debug, Clone)]
 pub struct BlobBodies {
-    body: Body
+    /// Hash of the blob data, identifying which blob the bodies belong to.
+    pub blob_hash: BlobHash,
+    /// The number of bodies the blob was serialized into.
+    pub total: usize,
+    pub bodies: Vec<BlobBody>
+}
+
+/// A collation body holding one part of a blob.
+#[derive(PartialEq, Debug, Clone)]
+pub struct BlobBody {
+    /// Position of this body in the blob, starting from 0.
+    pub index: usize,
+    pub body: Body
 }
 
 impl BlobBodies {
-    pub fn new(body: Body) -> BlobBodies {
+    /// Create a set of blob bodies, numbering the bodies in the order they are given.
+    pub fn new(blob_hash: BlobHash, bodies: Vec<Body>) -> BlobBodies {
+        let total = bodies.len();
+        let bodies = bodies.into_iter()
+            .enumerate()
+            .map(|(index, body)| BlobBody { index, body })
+            .collect();
         BlobBodies {
-            body
+            blob_hash,
+            total,
+            bodies
         }
     }
+
+    /// Check that every body from 0 to `total - 1` is present exactly once.
+    pub fn is_complete(&self) -> bool {
+        if self.bodies.len() != self.total {
+            return false;
+        }
+        let mut seen = vec![false; self.total];
+        for blob_body in &self.bodies {
+            if blob_body.index >= self.total || seen[blob_body.index] {
+                return false;
+            }
+            seen[blob_body.index] = true;
+        }
+        true
+    }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use modules::collation::chunk;
+    use serde_json;
+
+    #[test]
+    fn it_packs_blobs_into_one_body() {
+        let first_blob = Blob::new(vec![1; CHUNK_DATA_SIZE * 2]);
+        let second_blob = Blob::new(vec![2; 5]);
+        let mut builder = BodyBuilder::new();
+        assert_eq!(builder.free_chunks(), CHUNKS_PER_COLLATION);
+
+        builder.add_blob(first_blob.clone()).unwrap();
+        assert_eq!(builder.free_chunks(), CHUNKS_PER_COLLATION - 2);
+        builder.add_blob(second_blob.clone()).unwrap();
+        assert_eq!(builder.free_chunks(), CHUNKS_PER_COLLATION - 3);
+
+        let mut expected_chunks = first_blob.to_chunks(false);
+        expected_chunks.extend(second_blob.to_chunks(false));
+        assert_eq!(builder.build(), Body::new(expected_chunks));
+    }
+
+    #[test]
+    fn it_refuses_blob_that_does_not_fit() {
+        let mut builder = BodyBuilder::new();
+        builder.add_blob(Blob::new(vec![1; DATA_BYTES_PER_COLLATION - CHUNK_DATA_SIZE])).unwrap();
+        assert_eq!(builder.free_chunks(), 1);
+
+        let too_big_blob = Blob::new(vec![2; CHUNK_DATA_SIZE + 1]);
+        assert!(!builder.fits(&too_big_blob));
+        assert_eq!(builder.add_blob(too_big_blob.clone()), Err(too_big_blob));
+        assert_eq!(builder.free_chunks(), 1);
+
+        // A blob that exactly fills the remaining space is accepted.
+        builder.add_blob(Blob::new(vec![3; CHUNK_DATA_SIZE])).unwrap();
+        assert_eq!(builder.free_chunks(), 0);
+        assert_eq!(builder.build().chunks.len(), CHUNKS_PER_COLLATION);
+    }
+
+    #[test]
+    fn it_pads_body_to_collation_size() {
+        let blob = Blob::new(vec![1; CHUNK_DATA_SIZE * 2]);
+        let mut builder = BodyBuilder::new();
+        builder.add_blob(blob.clone()).unwrap();
+        let unpadded = builder.clone().build();
+        let body = builder.build_padded();
+
+        assert_eq!(body.chunks.len(), CHUNKS_PER_COLLATION);
+        assert_eq!(&body.chunks[..2], &unpadded.chunks[..]);
+        assert!(body.chunks[2..].iter().all(|chunk| *chunk == Chunk::new(0, [0; CHUNK_DATA_SIZE])));
+        assert_eq!(body.chunk_root(), unpadded.chunk_root());
+        assert_eq!(body.to_blobs().unwrap(), vec![blob]);
+    }
+
+    #[test]
+    fn it_splits_body_into_blobs() {
+        let blobs = vec![Blob::new(vec![1; CHUNK_DATA_SIZE * 3]),
+                         Blob::new(vec![0; 1]),
+                         Blob::new(vec![3, 3, 0, 0]),
+                         Blob::new(vec![4; CHUNK_DATA_SIZE + 7])];
+        let mut builder = BodyBuilder::new();
+        for blob in blobs.clone() {
+            builder.add_blob(blob).unwrap();
+        }
+        let mut body = builder.build();
+
+        // Padding after the last blob is ignored.
+        body.chunks.push(Chunk::new(0, [0; CHUNK_DATA_SIZE]));
+        assert_eq!(body.to_blobs().unwrap(), blobs);
+    }
+
+    #[test]
+    fn it_computes_chunk_root_over_chunks() {
+        let body = Blob::new(vec![5; CHUNK_DATA_SIZE * 2]).blob_to_collation_body().unwrap();
+        let first_leaf = ChunkRootHash::from(body.chunks[0].chunk_to_bytes());
+        let second_leaf = ChunkRootHash::from(body.chunks[1].chunk_to_bytes());
+        let mut expected_root = merkle::hash_pair(&first_leaf, &second_leaf);
+        // The rest of the tree is zero subtrees.
+        let mut zero_subtree = ChunkRootHash::zero();
+        for _ in 0..CHUNK_TREE_DEPTH - 1 {
+            zero_subtree = merkle::hash_pair(&zero_subtree, &zero_subtree);
+            expected_root = merkle::hash_pair(&expected_root, &zero_subtree);
+        }
+        assert_eq!(body.chunk_root(), expected_root);
+    }
+
+    #[test]
+    fn padding_does_not_change_chunk_root() {
+        let mut body = Blob::new(vec![5; 100]).blob_to_collation_body().unwrap();
+        let chunk_root = body.chunk_root();
+        body.chunks.resize(CHUNKS_PER_COLLATION, Chunk::new(0, [0; CHUNK_DATA_SIZE]));
+        assert_eq!(body.chunk_root(), chunk_root);
+    }
+
+    #[test]
+    fn different_bodies_have_different_chunk_roots() {
+        let body = Blob::new(vec![5; 100]).blob_to_collation_body().unwrap();
+        let other_body = Blob::new(vec![5; 101]).blob_to_collation_body().unwrap();
+        assert_ne!(body.chunk_root(), other_body.chunk_root());
+    }
+
+    #[test]
+    fn it_proves_chunks_of_body() {
+        let body = Blob::new((0..5000).map(|i| (i % 251) as u8).collect()).blob_to_collation_body().unwrap();
+        let chunk_root = body.chunk_root();
+        for index in 0..body.chunks.len() {
+            let proof = body.prove_chunk(index).unwrap();
+            assert!(merkle::verify_chunk_proof(&chunk_root, index, &body.chunks[index], &proof));
+            // The proof doesn't hold for another chunk, or for another index.
+            let other_index = (index + 1) % body.chunks.len();
+            assert!(!merkle::verify_chunk_proof(&chunk_root, index, &body.chunks[other_index], &proof));
+            assert!(!merkle::verify_chunk_proof(&chunk_root, other_index, &body.chunks[index], &proof));
+        }
+        assert_eq!(body.prove_chunk(CHUNKS_PER_COLLATION), None);
+    }
+
+    #[test]
+    fn it_proves_padding_chunks_of_body() {
+        let body = Blob::new(vec![1; 100]).blob_to_collation_body().unwrap();
+        let chunk_root = body.chunk_root();
+        let tree = body.chunk_tree();
+        for &index in &[body.chunks.len(), 1000, CHUNKS_PER_COLLATION - 1] {
+            let chunk = body.chunk(index).unwrap();
+            assert_eq!(chunk, Chunk::new(0, [0; CHUNK_DATA_SIZE]));
+            let proof = tree.proof(index).unwrap();
+            assert_eq!(Some(proof.clone()), body.prove_chunk(index));
+            assert!(merkle::verify_chunk_proof(&chunk_root, index, &chunk, &proof));
+        }
+        assert_eq!(body.chunk(CHUNKS_PER_COLLATION), None);
+    }
+
+    #[test]
+    fn it_proves_chunk_of_full_body() {
+        let body = create_sample_collation_body();
+        assert_eq!(body.chunks.len(), CHUNKS_PER_COLLATION);
+        let chunk_root = body.chunk_root();
+        let index = CHUNKS_PER_COLLATION - 1;
+        let proof = body.prove_chunk(index).unwrap();
+        assert!(merkle::verify_chunk_proof(&chunk_root, index, &body.chunks[index], &proof));
+    }
+
+    #[test]
+    fn it_rejects_chunk_proof_against_other_root() {
+        let body = Blob::new(vec![1; 100]).blob_to_collation_body().unwrap();
+        let other_body = Blob::new(vec![2; 100]).blob_to_collation_body().unwrap();
+        let proof = body.prove_chunk(0).unwrap();
+        assert!(!merkle::verify_chunk_proof(&other_body.chunk_root(), 0, &body.chunks[0], &proof));
+    }
+
+    #[test]
+    fn it_rejects_unterminated_blob_in_body() {
+        let mut body = Blob::new(vec![1; 10]).blob_to_collation_body().unwrap();
+        body.chunks.push(Chunk::new(0, [1; CHUNK_DATA_SIZE]));
+        body.chunks.push(Chunk::new(0, [0; CHUNK_DATA_SIZE]));
+        match body.to_blobs() {
+            Err(Error(ErrorKind::MissingTerminalChunk(chunks), _)) => assert_eq!(chunks, 2),
+            result => panic!("Expected missing terminal chunk error, got {:?}", result)
+        }
+    }
+
+    #[test]
+    fn it_round_trips_body_through_bytes() {
+        let body = Blob::new(vec![9; CHUNK_DATA_SIZE * 3 + 2]).blob_to_collation_body().unwrap();
+        let bytes = body.to_bytes();
+        assert_eq!(bytes.len(), CHUNK_SIZE * 4);
+        assert_eq!(Body::from_bytes(&bytes).unwrap(), body);
+    }
+
+    #[test]
+    fn it_rejects_body_bytes_not_chunk_aligned() {
+        let bytes = vec![0; CHUNK_SIZE * 2 + 1];
+        match Body::from_bytes(&bytes) {
+            Err(Error(ErrorKind::BodyNotChunkAligned(length), _)) => assert_eq!(length, CHUNK_SIZE * 2 + 1),
+            result => panic!("Expected body not chunk aligned error, got {:?}", result)
+        }
+    }
+
+    #[test]
+    fn it_rejects_body_bytes_with_malformed_chunk() {
+        let mut bytes = vec![0; CHUNK_SIZE * 2];
+        bytes[CHUNK_SIZE] = 0b0010_0001;
+        match Body::from_bytes(&bytes) {
+            Err(Error(ErrorKind::ReservedIndicatorBitsSet(indicator), _)) => assert_eq!(indicator, 0b0010_0001),
+            result => panic!("Expected reserved indicator bits error, got {:?}", result)
+        }
+    }
+
+    #[test]
+    fn it_serializes_body_as_hex() {
+        let body = Body::new(vec![Chunk::new(0, [1; CHUNK_DATA_SIZE]), Chunk::new(5, [2; CHUNK_DATA_SIZE])]);
+        let serialized = serde_json::to_string(&body).unwrap();
+        assert_eq!(serialized, format!("\"{}\"", to_hex(&body.to_bytes())));
+        assert_eq!(serde_json::from_str::<Body>(&serialized).unwrap(), body);
+
+        // Chunks with reserved indicator bits set are rejected
+        let mut bytes = body.to_bytes();
+        bytes[0] = chunk::INDICATOR_RESERVED_BITS;
+        assert!(serde_json::from_str::<Body>(&format!("\"{}\"", to_hex(&bytes))).is_err());
+        assert!(serde_json::from_str::<Body>("\"0x0102\"").is_err());
+    }
+}
@@ -5,7 +5,7 @@ use modules::constants::{CHUNK_SIZE,
     CHUNKS_PER_COLLATION,
     MAX_BLOB_SIZE
 };
-// use modules::errors::*;
+use modules::errors::*;
 
 // Not used:
 // use modules::primitives::{BinaryU8};
@@ -13,6 +13,13 @@ use modules::constants::{CHUNK_SIZE,
 // not used: use ::std::fmt::{Binary, Formatter, Result};
 // nightly, not used: use ::std::slice::SliceIndex;
 
+/// The most significant bit of the indicator byte, the SKIP_EVM flag.
+pub const INDICATOR_SKIP_EVM_BIT: u8 = 0b1000_0000;
+/// The two bits of the indicator byte after SKIP_EVM, which are reserved and must be 0.
+pub const INDICATOR_RESERVED_BITS: u8 = 0b0110_0000;
+/// The 5 least significant bits of the indicator byte, holding the length of a terminal chunk.
+pub const INDICATOR_LENGTH_BITS: u8 = 0b0001_1111;
+
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub struct Chunk {
     pub indicator: u8,
@@ -37,39 +44,85 @@ impl Chunk {
         bytes
     }
 
-    /// Convert CHUNK_SIZE bytes into a chunk
-    pub fn bytes_to_chunk(chunk_bytes: [u8; CHUNK_SIZE]) -> Chunk {
+    /// Convert CHUNK_SIZE bytes into a chunk, checking that the indicator byte is valid.
+    pub fn bytes_to_chunk(chunk_bytes: [u8; CHUNK_SIZE]) -> Result<Chunk> {
         let indicator = chunk_bytes[0];
-        let mut data: [u8; 31] = [0; 31];
-        /* UNNECESSARY
-        This was from trying this alternative and trying to work around Chunk and it's fields being immutable.
-        let mut chunk = Chunk {
-            indicator: chunk_bytes[0],
-            data: [0x00; CHUNK_DATA_SIZE]
+        let mut data: [u8; CHUNK_DATA_SIZE] = [0; CHUNK_DATA_SIZE];
+        data.copy_from_slice(&chunk_bytes[1..]);
+        let chunk = Chunk {
+            indicator,
+            data
         };
-        let indicator_ref = &[chunk_bytes[0]];
-        let mut indicator_reader = BitReader::new(indicator_ref);
+        chunk.validate()?;
+        Ok(chunk)
+    }
 
-        let skip_evm = indicator_reader.read_u8(1)?;//.chain_err(|| "Failed to read the 
-            // first three bits of the indicator");
-        */
-        for i in 0..CHUNK_DATA_SIZE {
-            data[i] = chunk_bytes[i+1];
+    /// Check that the indicator byte has no reserved bits set. The 5 length bits can't
+    /// hold more than CHUNK_DATA_SIZE, so any terminal length is valid.
+    pub fn validate(&self) -> Result<()> {
+        if self.indicator & INDICATOR_RESERVED_BITS != 0 {
+            bail!(ErrorKind::ReservedIndicatorBitsSet(self.indicator));
         }
+        Ok(())
+    }
+
+    /// Build a chunk holding a piece of blob data. A non-terminal chunk must hold
+    /// exactly CHUNK_DATA_SIZE bytes, while a terminal chunk holds 1 to CHUNK_DATA_SIZE
+    /// bytes, with its length stored in the indicator and the rest of the data zeroed.
+    pub fn from_blob_data(blob_data: &[u8], skip_evm: bool, terminal: bool) -> Chunk {
+        let length = blob_data.len();
+        if terminal {
+            assert!(0 < length && length <= CHUNK_DATA_SIZE,
+                "A terminal chunk must hold 1 to {:?} bytes, not {:?}", CHUNK_DATA_SIZE, length);
+        } else {
+            assert_eq!(length, CHUNK_DATA_SIZE, "A non-terminal chunk must be full");
+        }
+        let mut data: [u8; CHUNK_DATA_SIZE] = [0; CHUNK_DATA_SIZE];
+        data[..length].copy_from_slice(blob_data);
         Chunk {
-            indicator,
+            indicator: Chunk::build_indicator(skip_evm, terminal, length as u8),
             data
         }
     }
+
+    /// The blob data held by this chunk: all of the data for a non-terminal chunk,
+    /// or the first `terminal_length` bytes for a terminal chunk.
+    pub fn blob_data(&self) -> &[u8] {
+        if self.is_terminal() {
+            &self.data[..self.terminal_length() as usize]
+        } else {
+            &self.data[..]
+        }
+    }
+
+    /// The number of data bytes in a terminal chunk, read from the 5 least
+    /// significant bits of the indicator byte. This is 0 for non-terminal chunks.
+    pub fn terminal_length(&self) -> u8 {
+        self.indicator & INDICATOR_LENGTH_BITS
+    }
+
+    /// Whether this is the last chunk of a blob.
+    pub fn is_terminal(&self) -> bool {
+        self.terminal_length() != 0
+    }
+
     /// Build an indicator byte with the supplied parameters:
     /// `skip_evm`, `terminal` and `terminal_length` .  Length can be
     /// any value if the chunk is not terminal (the value is ignored).
-    /// Only used in tests.
+    /// Used by `from_blob_data` and in tests. Panics if `terminal_length`
+    /// is larger than CHUNK_DATA_SIZE.
     pub fn build_indicator(skip_evm: bool, terminal: bool, terminal_length: u8) -> u8 {
+        Chunk::try_build_indicator(skip_evm, terminal, terminal_length)
+            .expect("Failed to build indicator")
+    }
+
+    /// Build an indicator byte as in `build_indicator`, returning an error instead
+    /// of panicking if `terminal_length` doesn't fit into a chunk.
+    pub fn try_build_indicator(skip_evm: bool, terminal: bool, terminal_length: u8) -> Result<u8> {
         let mut indicator: u8 = 0b0000_0000;
         if skip_evm {
             // Set SKIP_EVM flag to 1
-            indicator += 0b1000_0000;
+            indicator |= INDICATOR_SKIP_EVM_BIT;
         }
         /* Could use this if we want to build an indicator byte from a chunk,
         then we wouldn't need terminal and terminal_length.
@@ -79,11 +132,12 @@ impl Chunk {
         }
         */
         if terminal {
-            // This needs to be <= for when we have a the last 32 bytes of a blob as all 0s.
-            assert!(0 as u8 <= terminal_length && terminal_length < CHUNK_SIZE as u8);
-            indicator += terminal_length;
+            if terminal_length as usize > CHUNK_DATA_SIZE {
+                bail!(ErrorKind::TerminalLengthTooLarge(terminal_length));
+            }
+            indicator |= terminal_length;
         }
-        indicator
+        Ok(indicator)
     }
 }
 
@@ -129,6 +183,46 @@ mod tests {
         assert_eq!(run_evm_indicator, correct_run_evm_indicator);
     }
 
+    #[test]
+    fn it_reads_terminal_length() {
+        let terminal_chunk = Chunk::new(Chunk::build_indicator(true, true, 4), [1; CHUNK_DATA_SIZE]);
+        assert_eq!(terminal_chunk.terminal_length(), 4);
+        assert!(terminal_chunk.is_terminal());
+
+        let non_terminal_chunk = Chunk::new(Chunk::build_indicator(true, false, 4), [1; CHUNK_DATA_SIZE]);
+        assert_eq!(non_terminal_chunk.terminal_length(), 0);
+        assert!(!non_terminal_chunk.is_terminal());
+    }
+
+    #[test]
+    fn it_builds_chunks_from_blob_data() {
+        let non_terminal_chunk = Chunk::from_blob_data(&[7; CHUNK_DATA_SIZE], false, false);
+        assert_eq!(non_terminal_chunk, Chunk::new(0b0000_0000, [7; CHUNK_DATA_SIZE]));
+        assert_eq!(non_terminal_chunk.blob_data(), &[7; CHUNK_DATA_SIZE][..]);
+
+        let terminal_chunk = Chunk::from_blob_data(&[0, 7, 0], true, true);
+        let mut data = [0; CHUNK_DATA_SIZE];
+        data[1] = 7;
+        assert_eq!(terminal_chunk, Chunk::new(0b1000_0011, data));
+        assert_eq!(terminal_chunk.blob_data(), &[0, 7, 0][..]);
+
+        let full_terminal_chunk = Chunk::from_blob_data(&[0; CHUNK_DATA_SIZE], false, true);
+        assert_eq!(full_terminal_chunk.terminal_length(), CHUNK_DATA_SIZE as u8);
+        assert_eq!(full_terminal_chunk.blob_data(), &[0; CHUNK_DATA_SIZE][..]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn it_does_not_build_empty_terminal_chunk() {
+        Chunk::from_blob_data(&[], false, true);
+    }
+
+    #[test]
+    #[should_panic]
+    fn it_does_not_build_partial_non_terminal_chunk() {
+        Chunk::from_blob_data(&[1; CHUNK_DATA_SIZE - 1], false, false);
+    }
+
     #[test]
     fn it_converts_to_bytes() {
         let chunk = Chunk::new(0b1000_0000, [1; CHUNK_DATA_SIZE as usize]);
@@ -147,10 +241,34 @@ mod tests {
     fn it_converts_from_bytes() {
         let chunk = Chunk::new(0b1000_0000, [1; CHUNK_DATA_SIZE]);
         let chunk_bytes = chunk.clone().chunk_to_bytes();
-        let same_chunk = Chunk::bytes_to_chunk(chunk_bytes);
+        let same_chunk = Chunk::bytes_to_chunk(chunk_bytes).unwrap();
         assert_eq!(chunk, same_chunk);
     }
 
+    #[test]
+    fn it_rejects_reserved_indicator_bits() {
+        for &indicator in &[0b0010_0000, 0b0100_0000, 0b1110_0001] {
+            let mut chunk_bytes = [0; CHUNK_SIZE];
+            chunk_bytes[0] = indicator;
+            match Chunk::bytes_to_chunk(chunk_bytes) {
+                Err(Error(ErrorKind::ReservedIndicatorBitsSet(bits), _)) => assert_eq!(bits, indicator),
+                result => panic!("Expected reserved indicator bits error, got {:?}", result)
+            }
+        }
+    }
+
+    #[test]
+    fn it_rejects_terminal_length_above_chunk_data_size() {
+        match Chunk::try_build_indicator(false, true, CHUNK_DATA_SIZE as u8 + 1) {
+            Err(Error(ErrorKind::TerminalLengthTooLarge(length), _)) => assert_eq!(length, 32),
+            result => panic!("Expected terminal length error, got {:?}", result)
+        }
+        // The length is ignored for non-terminal chunks.
+        assert_eq!(Chunk::try_build_indicator(false, false, CHUNK_DATA_SIZE as u8 + 1).unwrap(), 0);
+        // Every length the indicator bits can hold fits into a chunk.
+        Chunk::new(INDICATOR_LENGTH_BITS, [1; CHUNK_DATA_SIZE]).validate().unwrap();
+    }
+
 }
 
 /* Everything below is commented out because trying to use this causes errors e.g. on slices,
@@ -1,7 +1,10 @@
 use modules::collation::body::{Body/* , BlobBodies */};
 use modules::collation::header::Header;
+use modules::constants::{CHUNKS_PER_COLLATION, SHARD_COUNT};
+use modules::errors::*;
+use modules::primitives::ShardIdHash;
 
-#[derive(PartialEq, Clone, Debug)]
+#[derive(PartialEq, Clone, Debug, Serialize, Deserialize)]
 pub struct Collation {
     pub header: Header,
     pub body: Body
@@ -14,4 +17,129 @@ impl Collation {
             body
         }
     }
+
+    /// Check that the chunk root in the header is the chunk root of the body,
+    /// i.e. that the body is the one the header commits to.
+    pub fn verify_chunk_root(&self) -> bool {
+        self.header.chunk_root() == self.body.chunk_root()
+    }
+
+    /// Check that the collation is complete and consistent: its shard exists, its body
+    /// is padded to CHUNKS_PER_COLLATION well-formed chunks that split into blobs, and
+    /// the header commits to the body.
+    pub fn validate(&self) -> Result<()> {
+        if self.header.shard_id >= ShardIdHash::from(SHARD_COUNT) {
+            bail!(ErrorKind::ShardIdOutOfRange(self.header.shard_id));
+        }
+        let chunks = self.body.chunks.len();
+        if chunks > CHUNKS_PER_COLLATION {
+            bail!(ErrorKind::BodyTooLarge(chunks));
+        }
+        if chunks < CHUNKS_PER_COLLATION {
+            bail!(ErrorKind::BodyNotPadded(chunks));
+        }
+        self.body.to_blobs()?;
+        let body_root = self.body.chunk_root();
+        if self.header.chunk_root() != body_root {
+            bail!(ErrorKind::ChunkRootMismatch(self.header.chunk_root(), body_root));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use modules::collation::blob::Blob;
+    use modules::collation::body;
+    use modules::collation::chunk::Chunk;
+    use modules::constants::CHUNK_DATA_SIZE;
+    use modules::primitives::{ChunkPeriodHash, ChunkRootHash, ProposerAddress, ProposerBidHash};
+
+    fn generate_collation(body: Body) -> Collation {
+        let header = Header::new(ShardIdHash::from_dec_str("1").unwrap(),
+                                 body.chunk_root(),
+                                 ChunkPeriodHash::from_dec_str("1").unwrap(),
+                                 ProposerAddress::zero(), ProposerBidHash::zero());
+        Collation::new(header, body)
+    }
+
+    #[test]
+    fn it_verifies_chunk_root() {
+        let collation = generate_collation(Blob::new(vec![1; 1000]).blob_to_collation_body().unwrap());
+        assert!(collation.verify_chunk_root());
+    }
+
+    #[test]
+    fn it_rejects_body_not_matching_chunk_root() {
+        let mut collation = generate_collation(Blob::new(vec![1; 1000]).blob_to_collation_body().unwrap());
+        collation.body.chunks[3].data[0] = 2;
+        assert!(!collation.verify_chunk_root());
+    }
+
+    fn generate_padded_collation() -> Collation {
+        let mut builder = body::BodyBuilder::new();
+        builder.add_blob(Blob::new(vec![1; 1000])).unwrap();
+        generate_collation(builder.build_padded())
+    }
+
+    #[test]
+    fn it_validates_padded_collation() {
+        generate_padded_collation().validate().unwrap();
+    }
+
+    #[test]
+    fn it_rejects_invalid_collations() {
+        // The chunk root of a padded body is slow to compute in a debug build, so the
+        // cases share one padded collation
+        let padded = generate_padded_collation();
+
+        let mut collation = padded.clone();
+        collation.header = Header::new(ShardIdHash::from(SHARD_COUNT), padded.header.chunk_root(),
+                                       padded.header.period(), ProposerAddress::zero(), ProposerBidHash::zero());
+        match collation.validate() {
+            Err(Error(ErrorKind::ShardIdOutOfRange(_), _)) => {},
+            result => panic!("Expected shard id out of range error, got {:?}", result)
+        }
+
+        let collation = generate_collation(Blob::new(vec![1; 1000]).blob_to_collation_body().unwrap());
+        match collation.validate() {
+            Err(Error(ErrorKind::BodyNotPadded(chunks), _)) => assert_eq!(chunks, collation.body.chunks.len()),
+            result => panic!("Expected body not padded error, got {:?}", result)
+        }
+
+        let mut collation = padded.clone();
+        collation.body.chunks.push(Chunk::new(0, [0; CHUNK_DATA_SIZE]));
+        match collation.validate() {
+            Err(Error(ErrorKind::BodyTooLarge(chunks), _)) => assert_eq!(chunks, CHUNKS_PER_COLLATION + 1),
+            result => panic!("Expected body too large error, got {:?}", result)
+        }
+
+        // A chunk after the last blob that isn't padding. The body is checked before the
+        // chunk root, so the header can keep the old root.
+        let mut collation = padded.clone();
+        collation.body.chunks[CHUNKS_PER_COLLATION - 1].data[0] = 1;
+        match collation.validate() {
+            Err(Error(ErrorKind::MissingTerminalChunk(_), _)) => {},
+            result => panic!("Expected missing terminal chunk error, got {:?}", result)
+        }
+
+        let mut collation = padded.clone();
+        collation.header = Header::new(padded.header.shard_id, ChunkRootHash::zero(), padded.header.period(),
+                                       ProposerAddress::zero(), ProposerBidHash::zero());
+        match collation.validate() {
+            Err(Error(ErrorKind::ChunkRootMismatch(header_root, body_root), _)) => {
+                assert_eq!((header_root, body_root), (ChunkRootHash::zero(), padded.header.chunk_root()));
+            },
+            result => panic!("Expected chunk root mismatch error, got {:?}", result)
+        }
+    }
+
+    #[test]
+    fn it_rejects_sample_collation() {
+        // The sample header's chunk root is made up, so it doesn't match the sample body.
+        let collation = Collation::new(Header::create_sample_collation_header(),
+                                       body::create_sample_collation_body());
+        assert!(!collation.verify_chunk_root());
+    }
 }
@@ -0,0 +1,213 @@
+use modules::collation::body::Body;
+use modules::collation::chunk::Chunk;
+use modules::collation::merkle;
+use modules::collation::merkle::{MerkleProof, MerkleTree};
+use modules::constants::{CHUNK_SIZE, CHUNK_DATA_SIZE, CHUNKS_PER_COLLATION, CHUNK_TREE_DEPTH};
+use modules::errors::*;
+use modules::primitives::ChunkRootHash;
+
+use reed_solomon_simd;
+use std::collections::BTreeMap;
+
+/// The number of chunks in an extended body.
+pub const EXTENDED_CHUNKS_PER_COLLATION: usize = CHUNKS_PER_COLLATION * 2;
+/// Depth of the binary Merkle tree over the chunks of an extended body.
+pub const EXTENDED_CHUNK_TREE_DEPTH: usize = CHUNK_TREE_DEPTH + 1;
+
+/// A collation body extended with Reed-Solomon erasure coding over chunk-sized symbols,
+/// for data availability. The first CHUNKS_PER_COLLATION chunks are the chunks of the
+/// body, padded with zero chunks, and the rest are parity chunks, which don't have an
+/// indicator byte. The body can be rebuilt from any CHUNKS_PER_COLLATION of the extended
+/// chunks, so it is only unavailable if more than half of them are withheld, which
+/// sampling random chunks detects with high probability.
+///
+/// Only available with the `erasure-coding` feature.
+#[derive(PartialEq, Debug, Clone)]
+pub struct ExtendedBody {
+    pub chunks: Vec<[u8; CHUNK_SIZE]>
+}
+
+impl ExtendedBody {
+    /// Extend a body of up to CHUNKS_PER_COLLATION chunks to EXTENDED_CHUNKS_PER_COLLATION chunks.
+    pub fn from_body(body: &Body) -> Result<ExtendedBody> {
+        if body.chunks.len() > CHUNKS_PER_COLLATION {
+            bail!(ErrorKind::BodyTooLarge(body.chunks.len()));
+        }
+        let mut chunks: Vec<[u8; CHUNK_SIZE]> = body.chunks.iter()
+            .map(|chunk| chunk.chunk_to_bytes())
+            .collect();
+        chunks.resize(CHUNKS_PER_COLLATION, [0; CHUNK_SIZE]);
+
+        let parity_chunks = reed_solomon_simd::encode(CHUNKS_PER_COLLATION, CHUNKS_PER_COLLATION, &chunks)
+            .map_err(|e| ErrorKind::ErasureCodingFailed(e.to_string()))?;
+        chunks.extend(parity_chunks.iter().map(|parity_chunk| to_chunk_bytes(parity_chunk)));
+        Ok(ExtendedBody {
+            chunks
+        })
+    }
+
+    /// The root of the binary Merkle tree over the extended chunks, which availability
+    /// samples are checked against.
+    pub fn chunk_root(&self) -> ChunkRootHash {
+        self.chunk_tree().root()
+    }
+
+    /// Build a proof that the extended chunk at `index` is part of the extended body,
+    /// to be checked with `verify_extended_chunk_proof`.
+    pub fn prove_chunk(&self, index: usize) -> Option<MerkleProof> {
+        self.chunk_tree().proof(index)
+    }
+
+    /// The binary Merkle tree over the extended chunks, for proving many chunks
+    /// without rebuilding the tree.
+    pub fn chunk_tree(&self) -> MerkleTree {
+        let leaves: Vec<ChunkRootHash> = self.chunks.iter()
+            .map(|chunk_bytes| ChunkRootHash::from(*chunk_bytes))
+            .collect();
+        MerkleTree::new(&leaves, EXTENDED_CHUNK_TREE_DEPTH)
+    }
+
+    /// The original body, padded with zero chunks to CHUNKS_PER_COLLATION chunks.
+    pub fn body(&self) -> Result<Body> {
+        let chunks = self.chunks[..CHUNKS_PER_COLLATION].iter()
+            .map(|chunk_bytes| Chunk::bytes_to_chunk(*chunk_bytes))
+            .collect::<Result<Vec<Chunk>>>()?;
+        Ok(Body::new(chunks))
+    }
+
+    /// Rebuild the original body, padded with zero chunks to CHUNKS_PER_COLLATION chunks,
+    /// from any CHUNKS_PER_COLLATION distinct extended chunks, given with their indices.
+    pub fn recover_body(available: &[(usize, [u8; CHUNK_SIZE])]) -> Result<Body> {
+        let mut original: BTreeMap<usize, [u8; CHUNK_SIZE]> = BTreeMap::new();
+        let mut parity: BTreeMap<usize, [u8; CHUNK_SIZE]> = BTreeMap::new();
+        for &(index, chunk_bytes) in available {
+            if index < CHUNKS_PER_COLLATION {
+                original.insert(index, chunk_bytes);
+            } else if index < EXTENDED_CHUNKS_PER_COLLATION {
+                parity.insert(index - CHUNKS_PER_COLLATION, chunk_bytes);
+            } else {
+                bail!(ErrorKind::ChunkIndexOutOfRange(index, EXTENDED_CHUNKS_PER_COLLATION));
+            }
+        }
+        if original.len() + parity.len() < CHUNKS_PER_COLLATION {
+            bail!(ErrorKind::NotEnoughChunks(original.len() + parity.len(), CHUNKS_PER_COLLATION));
+        }
+
+        if original.len() < CHUNKS_PER_COLLATION {
+            let restored = reed_solomon_simd::decode(CHUNKS_PER_COLLATION, CHUNKS_PER_COLLATION,
+                                                     original.iter().map(|(&index, chunk)| (index, &chunk[..])),
+                                                     parity.iter().map(|(&index, chunk)| (index, &chunk[..])))
+                .map_err(|e| ErrorKind::ErasureCodingFailed(e.to_string()))?;
+            for (index, chunk_bytes) in restored {
+                original.insert(index, to_chunk_bytes(&chunk_bytes));
+            }
+        }
+        let chunks = original.values()
+            .map(|chunk_bytes| Chunk::bytes_to_chunk(*chunk_bytes))
+            .collect::<Result<Vec<Chunk>>>()?;
+        Ok(Body::new(chunks))
+    }
+}
+
+/// Check that `chunk_bytes` is the extended chunk at `index` of the extended body with
+/// the given chunk root, without needing the extended body.
+pub fn verify_extended_chunk_proof(root: &ChunkRootHash, index: usize, chunk_bytes: &[u8; CHUNK_SIZE],
+                                   proof: &MerkleProof) -> bool {
+    proof.index == index
+        && proof.siblings.len() == EXTENDED_CHUNK_TREE_DEPTH
+        && merkle::verify_merkle_proof(root, &ChunkRootHash::from(*chunk_bytes), proof)
+}
+
+fn to_chunk_bytes(shard: &[u8]) -> [u8; CHUNK_SIZE] {
+    let mut chunk_bytes: [u8; CHUNK_SIZE] = [0; CHUNK_SIZE];
+    chunk_bytes.copy_from_slice(shard);
+    chunk_bytes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use modules::collation::blob::Blob;
+
+    fn sample_body() -> Body {
+        let data: Vec<u8> = (0..CHUNK_DATA_SIZE * 1000).map(|i| (i % 253) as u8).collect();
+        Blob::new(data).blob_to_collation_body().unwrap()
+    }
+
+    fn padded(body: &Body) -> Body {
+        let mut chunks = body.chunks.clone();
+        chunks.resize(CHUNKS_PER_COLLATION, Chunk::new(0, [0; CHUNK_DATA_SIZE]));
+        Body::new(chunks)
+    }
+
+    #[test]
+    fn it_extends_body_to_twice_the_chunks() {
+        let body = sample_body();
+        let extended_body = ExtendedBody::from_body(&body).unwrap();
+        assert_eq!(extended_body.chunks.len(), EXTENDED_CHUNKS_PER_COLLATION);
+        assert_eq!(extended_body.body().unwrap(), padded(&body));
+    }
+
+    #[test]
+    fn it_recovers_body_from_any_half_of_extended_chunks() {
+        let body = sample_body();
+        let extended_body = ExtendedBody::from_body(&body).unwrap();
+
+        // Only parity chunks
+        let parity_half: Vec<(usize, [u8; CHUNK_SIZE])> = (CHUNKS_PER_COLLATION..EXTENDED_CHUNKS_PER_COLLATION)
+            .map(|index| (index, extended_body.chunks[index]))
+            .collect();
+        assert_eq!(ExtendedBody::recover_body(&parity_half).unwrap(), padded(&body));
+
+        // Every other chunk
+        let mixed_half: Vec<(usize, [u8; CHUNK_SIZE])> = (0..EXTENDED_CHUNKS_PER_COLLATION)
+            .filter(|index| index % 2 == 1)
+            .map(|index| (index, extended_body.chunks[index]))
+            .collect();
+        assert_eq!(ExtendedBody::recover_body(&mixed_half).unwrap(), padded(&body));
+
+        // Only original chunks
+        let original_half: Vec<(usize, [u8; CHUNK_SIZE])> = (0..CHUNKS_PER_COLLATION)
+            .map(|index| (index, extended_body.chunks[index]))
+            .collect();
+        assert_eq!(ExtendedBody::recover_body(&original_half).unwrap(), padded(&body));
+    }
+
+    #[test]
+    fn it_does_not_recover_body_from_less_than_half() {
+        let body = sample_body();
+        let extended_body = ExtendedBody::from_body(&body).unwrap();
+        let mut available: Vec<(usize, [u8; CHUNK_SIZE])> = (1..CHUNKS_PER_COLLATION + 1)
+            .map(|index| (index, extended_body.chunks[index]))
+            .collect();
+        // A duplicate doesn't count as another chunk.
+        available[0] = available[1];
+        match ExtendedBody::recover_body(&available) {
+            Err(Error(ErrorKind::NotEnoughChunks(available, needed), _)) => {
+                assert_eq!(available, CHUNKS_PER_COLLATION - 1);
+                assert_eq!(needed, CHUNKS_PER_COLLATION);
+            },
+            result => panic!("Expected not enough chunks error, got {:?}", result.map(|body| body.chunks.len()))
+        }
+    }
+
+    #[test]
+    fn it_rejects_body_larger_than_a_collation() {
+        let body = Body::new(vec![Chunk::new(0, [0; CHUNK_DATA_SIZE]); CHUNKS_PER_COLLATION + 1]);
+        assert!(ExtendedBody::from_body(&body).is_err());
+    }
+
+    #[test]
+    fn it_proves_extended_chunks() {
+        let extended_body = ExtendedBody::from_body(&sample_body()).unwrap();
+        // Building the tree over all extended chunks is slow in a debug build, so it is built once.
+        let tree = extended_body.chunk_tree();
+        let chunk_root = tree.root();
+        for &index in &[0, 999, CHUNKS_PER_COLLATION, EXTENDED_CHUNKS_PER_COLLATION - 1] {
+            let proof = tree.proof(index).unwrap();
+            assert!(verify_extended_chunk_proof(&chunk_root, index, &extended_body.chunks[index], &proof));
+            assert!(!verify_extended_chunk_proof(&chunk_root, index, &[0xff; CHUNK_SIZE], &proof));
+        }
+        assert_eq!(tree.proof(EXTENDED_CHUNKS_PER_COLLATION), None);
+    }
+}
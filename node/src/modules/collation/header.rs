@@ -7,7 +7,7 @@ use modules::primitives::{
     ProposerAddress,
     CollationHeaderHash,
     //ParentCollationHeaderHash,
-    //ProposerBidHash,
+    ProposerBidHash,
     //ProposerSignature
 };
 
@@ -19,17 +19,19 @@ use modules::constants::{
 
 use tiny_keccak;
 
-#[derive(PartialEq, Debug, Clone)]
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
 pub struct Header {
 
     pub shard_id: ShardIdHash,
     proposer_address: ProposerAddress,
     chunk_root: ChunkRootHash,
     period: ChunkPeriodHash,
+    // What the proposer pays to have the collation included, for notaries to choose
+    // between proposals
+    proposer_bid: ProposerBidHash,
 
     // The following fields are pending updates to the sharding spec and are currently ignored
     //parent_hash: ParentCollationHeaderHash,
-    //proposer_bid: ProposerBidHash,
     //proposer_signature: ProposerSignature
 }
 
@@ -39,7 +41,7 @@ impl Header {
                chunk_root: ChunkRootHash,
                period: ChunkPeriodHash,
                proposer_address: ProposerAddress,
-               //proposer_bid: ProposerBidHash,
+               proposer_bid: ProposerBidHash,
                /*proposer_signature: ProposerSignature*/) -> Header{
 
         Header {
@@ -48,11 +50,28 @@ impl Header {
             chunk_root,
             period,
             proposer_address,
-            //proposer_bid,
+            proposer_bid,
             //proposer_signature
         }
     }
 
+    pub fn chunk_root(&self) -> ChunkRootHash {
+        self.chunk_root
+    }
+
+    pub fn period(&self) -> ChunkPeriodHash {
+        self.period
+    }
+
+    pub fn proposer_address(&self) -> ProposerAddress {
+        self.proposer_address
+    }
+
+    /// The proposer's bid
+    pub fn proposer_bid(&self) -> ProposerBidHash {
+        self.proposer_bid
+    }
+
     pub fn hash(&self) -> CollationHeaderHash {
         let mut sha3 = tiny_keccak::Keccak::new_sha3_256();
 
@@ -81,6 +100,10 @@ impl Header {
         self.proposer_address.copy_to(pa);
         sha3.update(pa);
 
+        // Add the proposer's bid
+        let pb = u256_to_bytes32(self.proposer_bid);
+        sha3.update(&pb);
+
         // Finalize hash and return as H256
         let mut result_bytes: [u8; 32] = [0; 32];
         sha3.finalize(&mut result_bytes);
@@ -95,7 +118,7 @@ impl Header {
         let chunk_root = ChunkRootHash::from_slice(&SAMPLE_COLLATION_CHUNK_ROOT_BYTES[..]);
         let period = ChunkPeriodHash::from_dec_str("1").unwrap();
         let proposer_address = ProposerAddress::from_slice(&SAMPLE_COLLATION_PROPOSER_ADDRESS_BYTES[..]);
-        let header = Header::new(shard_id, /*parent_hash,*/ chunk_root, period, proposer_address);
+        let header = Header::new(shard_id, /*parent_hash,*/ chunk_root, period, proposer_address, ProposerBidHash::zero());
         return header;
     }
 }
@@ -117,7 +140,7 @@ pub fn create_sample_collation_header() -> Header {
     let chunk_root = ChunkRootHash::from_slice(&SAMPLE_COLLATION_CHUNK_ROOT_BYTES[..]);
     let period = ChunkPeriodHash::from_dec_str("1").unwrap();
     let proposer_address = ProposerAddress::from_slice(&SAMPLE_COLLATION_PROPOSER_ADDRESS_BYTES[..]);
-    let header = Header::new(shard_id, /*parent_hash,*/ chunk_root, period, proposer_address);
+    let header = Header::new(shard_id, /*parent_hash,*/ chunk_root, period, proposer_address, ProposerBidHash::zero());
     return header;
 }
 
@@ -133,6 +156,7 @@ mod tests {
         let header_hash: CollationHeaderHash = header.hash();
         let shard_id_bytes = u256_to_bytes32(header.shard_id);
         let period_bytes = u256_to_bytes32(header.period);
+        let bid_bytes = u256_to_bytes32(header.proposer_bid);
 
         // Calculate the expected hash
         let mut sha3 = tiny_keccak::Keccak::new_sha3_256();
@@ -141,6 +165,7 @@ mod tests {
         sha3.update(&SAMPLE_COLLATION_CHUNK_ROOT_BYTES[..]);
         sha3.update(&period_bytes[..]);
         sha3.update(&SAMPLE_COLLATION_PROPOSER_ADDRESS_BYTES[..]);
+        sha3.update(&bid_bytes[..]);
 
         let mut expected_bytes: [u8; 32] = [0; 32];
         sha3.finalize(&mut expected_bytes);
@@ -149,6 +174,11 @@ mod tests {
 
         // Ensure manually calculated hash matches the generated hash
         assert_eq!(expected, header_hash);
+
+        // Headers only differing in their bid have different hashes
+        let mut other_bid = header.clone();
+        other_bid.proposer_bid = ProposerBidHash::from(1);
+        assert_ne!(other_bid.hash(), header_hash);
     }
 
 }
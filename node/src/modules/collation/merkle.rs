@@ -0,0 +1,216 @@
+use modules::collation::chunk::Chunk;
+use modules::constants::CHUNK_TREE_DEPTH;
+use modules::primitives::ChunkRootHash;
+
+use std::mem;
+use tiny_keccak;
+
+/// Hash two sibling nodes of the tree into their parent node.
+pub fn hash_pair(left: &ChunkRootHash, right: &ChunkRootHash) -> ChunkRootHash {
+    let mut keccak = tiny_keccak::Keccak::new_keccak256();
+    keccak.update(&left[..]);
+    keccak.update(&right[..]);
+    let mut result_bytes: [u8; 32] = [0; 32];
+    keccak.finalize(&mut result_bytes);
+    ChunkRootHash::from(result_bytes)
+}
+
+/// The roots of subtrees of zero leaves, from a single zero leaf up to a subtree of
+/// `2^depth` zero leaves.
+fn zero_hashes(depth: usize) -> Vec<ChunkRootHash> {
+    let mut zero_hashes = vec![ChunkRootHash::zero()];
+    for level in 0..depth {
+        let parent = hash_pair(&zero_hashes[level], &zero_hashes[level]);
+        zero_hashes.push(parent);
+    }
+    zero_hashes
+}
+
+/// A binary Merkle tree with `2^depth` leaves, where leaves after the given ones are
+/// zero. Each parent node is the Keccak-256 hash of its two children. Only the nodes
+/// with some of the given leaves below them are kept, the rest of each level is made
+/// up of zero subtrees.
+#[derive(PartialEq, Debug, Clone)]
+pub struct MerkleTree {
+    depth: usize,
+    // The nodes of each level, from the leaves up to the root.
+    levels: Vec<Vec<ChunkRootHash>>,
+    zero_hashes: Vec<ChunkRootHash>
+}
+
+impl MerkleTree {
+    /// Build the tree. Panics if there are more than `2^depth` leaves.
+    pub fn new(leaves: &[ChunkRootHash], depth: usize) -> MerkleTree {
+        assert!(leaves.len() <= 1 << depth,
+            "{:?} leaves don't fit into a tree of depth {:?}", leaves.len(), depth);
+        let zero_hashes = zero_hashes(depth);
+        let mut levels: Vec<Vec<ChunkRootHash>> = vec![leaves.to_vec()];
+        for zero_hash in zero_hashes.iter().take(depth) {
+            let mut level_nodes = levels.last().unwrap().clone();
+            if level_nodes.len() % 2 == 1 {
+                level_nodes.push(*zero_hash);
+            }
+            levels.push(level_nodes.chunks(2)
+                .map(|pair| hash_pair(&pair[0], &pair[1]))
+                .collect());
+        }
+        MerkleTree {
+            depth,
+            levels,
+            zero_hashes
+        }
+    }
+
+    /// The size of the kept nodes in bytes
+    pub fn size(&self) -> usize {
+        let nodes: usize = self.levels.iter().map(Vec::len).sum::<usize>() + self.zero_hashes.len();
+        nodes * mem::size_of::<ChunkRootHash>()
+    }
+
+    pub fn root(&self) -> ChunkRootHash {
+        match self.levels[self.depth].first() {
+            Some(root) => *root,
+            None => self.zero_hashes[self.depth]
+        }
+    }
+
+    /// Build a proof for the leaf at `index`. Returns `None` if the index is outside the tree.
+    pub fn proof(&self, index: usize) -> Option<MerkleProof> {
+        if index >= 1 << self.depth {
+            return None;
+        }
+        let siblings = (0..self.depth)
+            .map(|level| {
+                let sibling_index = (index >> level) ^ 1;
+                match self.levels[level].get(sibling_index) {
+                    Some(sibling) => *sibling,
+                    None => self.zero_hashes[level]
+                }
+            })
+            .collect();
+        Some(MerkleProof {
+            index,
+            siblings
+        })
+    }
+}
+
+/// The root of the tree from `MerkleTree::new`, without keeping the tree.
+///
+/// Panics if there are more than `2^depth` leaves.
+pub fn merkle_root(leaves: &[ChunkRootHash], depth: usize) -> ChunkRootHash {
+    MerkleTree::new(leaves, depth).root()
+}
+
+/// A proof that a leaf is in a binary Merkle tree: the leaf's index, and the sibling
+/// of each node on the path from the leaf up to the root.
+#[derive(PartialEq, Debug, Clone)]
+pub struct MerkleProof {
+    pub index: usize,
+    pub siblings: Vec<ChunkRootHash>
+}
+
+/// Build a proof for the leaf at `index` in the tree from `MerkleTree::new`, without
+/// keeping the tree. Returns `None` if the index is outside the tree.
+pub fn merkle_proof(leaves: &[ChunkRootHash], depth: usize, index: usize) -> Option<MerkleProof> {
+    MerkleTree::new(leaves, depth).proof(index)
+}
+
+/// Check that `leaf` is at the proof's index in the tree with the given root. The
+/// depth of the tree is the number of siblings in the proof.
+pub fn verify_merkle_proof(root: &ChunkRootHash, leaf: &ChunkRootHash, proof: &MerkleProof) -> bool {
+    if proof.index.checked_shr(proof.siblings.len() as u32).unwrap_or(0) != 0 {
+        return false;
+    }
+    let mut node = *leaf;
+    for (level, sibling) in proof.siblings.iter().enumerate() {
+        node = if (proof.index >> level) & 1 == 0 {
+            hash_pair(&node, sibling)
+        } else {
+            hash_pair(sibling, &node)
+        };
+    }
+    node == *root
+}
+
+/// Check that `chunk` is the chunk at `index` of the collation body with the given
+/// chunk root, e.g. `Header::chunk_root`, without needing the body.
+pub fn verify_chunk_proof(root: &ChunkRootHash, index: usize, chunk: &Chunk, proof: &MerkleProof) -> bool {
+    proof.index == index
+        && proof.siblings.len() == CHUNK_TREE_DEPTH
+        && verify_merkle_proof(root, &ChunkRootHash::from(chunk.chunk_to_bytes()), proof)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn leaf(byte: u8) -> ChunkRootHash {
+        ChunkRootHash::from([byte; 32])
+    }
+
+    #[test]
+    fn it_computes_root_of_full_tree() {
+        let leaves = vec![leaf(1), leaf(2), leaf(3), leaf(4)];
+        let expected = hash_pair(&hash_pair(&leaf(1), &leaf(2)), &hash_pair(&leaf(3), &leaf(4)));
+        assert_eq!(merkle_root(&leaves, 2), expected);
+    }
+
+    #[test]
+    fn it_pads_with_zero_leaves() {
+        let leaves = vec![leaf(1), leaf(2), leaf(3)];
+        let padded_leaves = vec![leaf(1), leaf(2), leaf(3), ChunkRootHash::zero()];
+        assert_eq!(merkle_root(&leaves, 2), merkle_root(&padded_leaves, 2));
+        assert_eq!(merkle_root(&leaves, 3),
+                   hash_pair(&merkle_root(&leaves, 2), &merkle_root(&[], 2)));
+    }
+
+    #[test]
+    fn single_leaf_is_its_own_root() {
+        assert_eq!(merkle_root(&[leaf(7)], 0), leaf(7));
+    }
+
+    #[test]
+    fn it_computes_root_of_zero_leaves() {
+        let zero_leaves = vec![ChunkRootHash::zero(); 8];
+        assert_eq!(merkle_root(&[], 3), merkle_root(&zero_leaves, 3));
+    }
+
+    #[test]
+    fn it_proves_every_leaf() {
+        let leaves = vec![leaf(1), leaf(2), leaf(3), leaf(4), leaf(5)];
+        let root = merkle_root(&leaves, 3);
+        for index in 0..8 {
+            let proof = merkle_proof(&leaves, 3, index).unwrap();
+            assert_eq!(proof.siblings.len(), 3);
+            let proven_leaf = if index < leaves.len() { leaves[index] } else { ChunkRootHash::zero() };
+            assert!(verify_merkle_proof(&root, &proven_leaf, &proof), "Leaf {:?} wasn't proven", index);
+        }
+        assert_eq!(merkle_proof(&leaves, 3, 8), None);
+    }
+
+    #[test]
+    fn it_rejects_wrong_leaf_or_index() {
+        let leaves = vec![leaf(1), leaf(2), leaf(3), leaf(4)];
+        let root = merkle_root(&leaves, 2);
+        let proof = merkle_proof(&leaves, 2, 1).unwrap();
+        assert!(verify_merkle_proof(&root, &leaf(2), &proof));
+        assert!(!verify_merkle_proof(&root, &leaf(3), &proof));
+
+        let mut wrong_index_proof = proof.clone();
+        wrong_index_proof.index = 2;
+        assert!(!verify_merkle_proof(&root, &leaf(2), &wrong_index_proof));
+        wrong_index_proof.index = 5;
+        assert!(!verify_merkle_proof(&root, &leaf(2), &wrong_index_proof));
+
+        let mut wrong_sibling_proof = proof;
+        wrong_sibling_proof.siblings[1] = leaf(9);
+        assert!(!verify_merkle_proof(&root, &leaf(2), &wrong_sibling_proof));
+    }
+
+    #[test]
+    #[should_panic]
+    fn it_does_not_fit_too_many_leaves() {
+        merkle_root(&[leaf(1), leaf(2), leaf(3)], 1);
+    }
+}
@@ -3,4 +3,9 @@ pub mod header;
 pub mod body;
 pub mod chunk;
 pub mod blob;
+pub mod blob_stream;
 pub mod collation_index;
+#[cfg(feature = "erasure-coding")]
+pub mod erasure;
+pub mod merkle;
+pub mod store;
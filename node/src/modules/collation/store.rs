@@ -0,0 +1,216 @@
+use modules::collation::collation::Collation;
+use modules::primitives::{ShardIdHash, ChunkPeriodHash, CollationHeaderHash};
+
+use std::collections::BTreeMap;
+
+/// Collations of one shard, by the period in their header
+type CollationsByPeriod = BTreeMap<ChunkPeriodHash, Vec<Collation>>;
+
+/// Collations stored by the shard and period in their header, keeping track of the
+/// size of their bodies so that old collations can be dropped.
+///
+/// Shards and periods are ordered, so iterating over the store always gives the
+/// collations in the same order.
+#[derive(PartialEq, Debug, Clone, Default)]
+pub struct CollationStore {
+    collations: BTreeMap<ShardIdHash, CollationsByPeriod>,
+    // The total size of the stored bodies in bytes
+    size: usize
+}
+
+/// The size of a collation's body in bytes
+fn body_size(collation: &Collation) -> usize {
+    collation.body.size()
+}
+
+impl CollationStore {
+    pub fn new() -> CollationStore {
+        CollationStore::default()
+    }
+
+    pub fn insert(&mut self, collation: Collation) {
+        self.size += body_size(&collation);
+        self.collations.entry(collation.header.shard_id)
+            .or_default()
+            .entry(collation.header.period())
+            .or_default()
+            .push(collation);
+    }
+
+    /// The collations of a shard in a period, in the order they were inserted
+    pub fn get(&self, shard_id: ShardIdHash, period: ChunkPeriodHash) -> &[Collation] {
+        self.collations.get(&shard_id)
+            .and_then(|by_period| by_period.get(&period))
+            .map_or(&[], |collations| &collations[..])
+    }
+
+    /// Every collation, by shard and then by period
+    pub fn iter(&self) -> impl Iterator<Item = &Collation> {
+        self.collations.values()
+            .flat_map(|by_period| by_period.values())
+            .flat_map(|collations| collations.iter())
+    }
+
+    pub fn len(&self) -> usize {
+        self.iter().count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.collations.is_empty()
+    }
+
+    /// The total size of the stored bodies in bytes
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// The earliest period of any stored collation
+    pub fn oldest_period(&self) -> Option<ChunkPeriodHash> {
+        self.collations.values()
+            .filter_map(|by_period| by_period.keys().next())
+            .min()
+            .cloned()
+    }
+
+    /// Remove and return the collations of periods before `period`
+    pub fn remove_before(&mut self, period: ChunkPeriodHash) -> Vec<Collation> {
+        let mut removed = vec![];
+        for by_period in self.collations.values_mut() {
+            let kept = by_period.split_off(&period);
+            for (_, collations) in ::std::mem::replace(by_period, kept) {
+                removed.extend(collations);
+            }
+        }
+        self.collations.retain(|_, by_period| !by_period.is_empty());
+        self.size -= removed.iter().map(body_size).sum::<usize>();
+        removed
+    }
+
+    /// Remove and return the collation with the given header hash from a shard and period
+    pub fn remove(&mut self, shard_id: ShardIdHash, period: ChunkPeriodHash,
+                  header_hash: &CollationHeaderHash) -> Option<Collation> {
+        let collation = {
+            let collations = self.collations.get_mut(&shard_id)?.get_mut(&period)?;
+            let index = collations.iter().position(|collation| collation.header.hash() == *header_hash)?;
+            collations.remove(index)
+        };
+        self.remove_empty(shard_id, period);
+        self.size -= body_size(&collation);
+        Some(collation)
+    }
+
+    /// Remove and return the first inserted collation of the oldest period, from the
+    /// lowest shard with a collation in that period
+    pub fn remove_oldest(&mut self) -> Option<Collation> {
+        let period = self.oldest_period()?;
+        let shard_id = *self.collations.iter()
+            .find(|(_, by_period)| by_period.contains_key(&period))?
+            .0;
+        let collation = self.collations.get_mut(&shard_id)?.get_mut(&period)?.remove(0);
+        self.remove_empty(shard_id, period);
+        self.size -= body_size(&collation);
+        Some(collation)
+    }
+
+    // Remove the entries of a shard and period once they hold no collations
+    fn remove_empty(&mut self, shard_id: ShardIdHash, period: ChunkPeriodHash) {
+        let shard_is_empty = match self.collations.get_mut(&shard_id) {
+            Some(by_period) => {
+                if by_period.get(&period).is_some_and(|collations| collations.is_empty()) {
+                    by_period.remove(&period);
+                }
+                by_period.is_empty()
+            },
+            None => false
+        };
+        if shard_is_empty {
+            self.collations.remove(&shard_id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use modules::collation::body::Body;
+    use modules::collation::chunk::Chunk;
+    use modules::collation::header::Header;
+    use modules::constants::{CHUNK_DATA_SIZE, CHUNK_SIZE};
+    use modules::primitives::{ChunkRootHash, ProposerAddress, ProposerBidHash};
+
+    fn generate_collation(shard_id: u64, period: u64, chunks: usize) -> Collation {
+        let header = Header::new(ShardIdHash::from(shard_id), ChunkRootHash::from(chunks as u64),
+                                 ChunkPeriodHash::from(period), ProposerAddress::zero(),
+                                 ProposerBidHash::zero());
+        Collation::new(header, Body::new(vec![Chunk::new(0, [1; CHUNK_DATA_SIZE]); chunks]))
+    }
+
+    #[test]
+    fn it_stores_collations_by_shard_and_period() {
+        let mut store = CollationStore::new();
+        assert!(store.is_empty());
+        store.insert(generate_collation(2, 1, 1));
+        store.insert(generate_collation(1, 1, 2));
+        store.insert(generate_collation(1, 0, 3));
+        store.insert(generate_collation(1, 1, 4));
+
+        assert_eq!(store.get(ShardIdHash::from(1), ChunkPeriodHash::from(1)),
+                   &[generate_collation(1, 1, 2), generate_collation(1, 1, 4)]);
+        assert!(store.get(ShardIdHash::from(3), ChunkPeriodHash::from(1)).is_empty());
+        assert_eq!(store.iter().cloned().collect::<Vec<Collation>>(),
+                   vec![generate_collation(1, 0, 3), generate_collation(1, 1, 2),
+                        generate_collation(1, 1, 4), generate_collation(2, 1, 1)]);
+        assert_eq!(store.len(), 4);
+        assert_eq!(store.size(), 10 * CHUNK_SIZE);
+        assert_eq!(store.oldest_period(), Some(ChunkPeriodHash::from(0)));
+    }
+
+    #[test]
+    fn it_removes_collations_before_a_period() {
+        let mut store = CollationStore::new();
+        store.insert(generate_collation(1, 0, 1));
+        store.insert(generate_collation(2, 1, 2));
+        store.insert(generate_collation(1, 2, 3));
+
+        assert_eq!(store.remove_before(ChunkPeriodHash::from(2)),
+                   vec![generate_collation(1, 0, 1), generate_collation(2, 1, 2)]);
+        assert_eq!(store.iter().cloned().collect::<Vec<Collation>>(), vec![generate_collation(1, 2, 3)]);
+        assert_eq!(store.size(), 3 * CHUNK_SIZE);
+        assert!(store.remove_before(ChunkPeriodHash::from(2)).is_empty());
+    }
+
+    #[test]
+    fn it_removes_collations_by_header_hash() {
+        let mut store = CollationStore::new();
+        store.insert(generate_collation(1, 2, 1));
+        store.insert(generate_collation(1, 2, 2));
+        let header_hash = generate_collation(1, 2, 1).header.hash();
+
+        assert_eq!(store.remove(ShardIdHash::from(2), ChunkPeriodHash::from(2), &header_hash), None);
+        assert_eq!(store.remove(ShardIdHash::from(1), ChunkPeriodHash::from(2), &header_hash),
+                   Some(generate_collation(1, 2, 1)));
+        assert_eq!(store.remove(ShardIdHash::from(1), ChunkPeriodHash::from(2), &header_hash), None);
+        assert_eq!(store.size(), 2 * CHUNK_SIZE);
+        let header_hash = generate_collation(1, 2, 2).header.hash();
+        assert!(store.remove(ShardIdHash::from(1), ChunkPeriodHash::from(2), &header_hash).is_some());
+        assert!(store.is_empty());
+    }
+
+    #[test]
+    fn it_removes_oldest_collations_first() {
+        let mut store = CollationStore::new();
+        store.insert(generate_collation(1, 3, 1));
+        store.insert(generate_collation(2, 2, 2));
+        store.insert(generate_collation(1, 2, 3));
+        store.insert(generate_collation(1, 2, 4));
+
+        assert_eq!(store.remove_oldest(), Some(generate_collation(1, 2, 3)));
+        assert_eq!(store.remove_oldest(), Some(generate_collation(1, 2, 4)));
+        assert_eq!(store.remove_oldest(), Some(generate_collation(2, 2, 2)));
+        assert_eq!(store.size(), CHUNK_SIZE);
+        assert_eq!(store.remove_oldest(), Some(generate_collation(1, 3, 1)));
+        assert_eq!(store.remove_oldest(), None);
+        assert!(store.is_empty());
+        assert_eq!(store.size(), 0);
+    }
+}
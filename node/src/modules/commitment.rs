@@ -0,0 +1,170 @@
+use modules::collation::collation::Collation;
+use modules::collation::header::Header;
+use modules::errors::*;
+use modules::primitives::{
+    ShardIdHash,
+    ChunkPeriodHash,
+    CollationHeaderHash,
+    ProposerAddress,
+    ProposerBidHash
+};
+
+use std::collections::HashMap;
+
+/// A proposer's commitment to a collation, published before its body: the hash of the
+/// collation's header and the proposer's bid. Notaries choose between commitments, and
+/// only the chosen proposer reveals the collation.
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
+pub struct Commitment {
+    pub shard_id: ShardIdHash,
+    pub period: ChunkPeriodHash,
+    pub proposer: ProposerAddress,
+    pub header_hash: CollationHeaderHash,
+    pub bid: ProposerBidHash
+}
+
+impl Commitment {
+    pub fn new(shard_id: ShardIdHash,
+               period: ChunkPeriodHash,
+               proposer: ProposerAddress,
+               header_hash: CollationHeaderHash,
+               bid: ProposerBidHash) -> Commitment {
+        Commitment {
+            shard_id,
+            period,
+            proposer,
+            header_hash,
+            bid
+        }
+    }
+
+    /// The commitment to a collation with the given header.
+    pub fn for_header(header: &Header) -> Commitment {
+        Commitment::new(header.shard_id, header.period(), header.proposer_address(), header.hash(),
+                        header.proposer_bid())
+    }
+
+    /// Check that a revealed collation is the one committed to, and that it is valid.
+    pub fn check_reveal(&self, collation: &Collation) -> Result<()> {
+        if Commitment::for_header(&collation.header) != *self {
+            bail!(ErrorKind::CommitmentMismatch(self.header_hash, collation.header.hash()));
+        }
+        collation.validate()
+    }
+}
+
+/// Choose between the commitments of a shard and period: the one with the highest bid,
+/// and of equal bids the one with the lowest header hash.
+pub fn select_commitment(commitments: &[Commitment]) -> Option<&Commitment> {
+    commitments.iter()
+        .max_by(|a, b| a.bid.cmp(&b.bid).then_with(|| b.header_hash.cmp(&a.header_hash)))
+}
+
+/// The commitments of proposers that were chosen but never revealed their collation,
+/// for penalizing them.
+#[derive(PartialEq, Debug, Clone, Default)]
+pub struct PenaltyRecord {
+    missed_reveals: HashMap<ProposerAddress, Vec<Commitment>>
+}
+
+impl PenaltyRecord {
+    pub fn new() -> PenaltyRecord {
+        PenaltyRecord::default()
+    }
+
+    /// Record that the proposer of the commitment didn't reveal its collation
+    pub fn record(&mut self, commitment: Commitment) {
+        warn!("Proposer {:?} didn't reveal the collation {:?} it committed to in shard id {} and period {}",
+              commitment.proposer, commitment.header_hash, commitment.shard_id, commitment.period);
+        self.missed_reveals.entry(commitment.proposer).or_default().push(commitment);
+    }
+
+    /// The commitments the proposer didn't reveal, in the order they were recorded
+    pub fn missed_reveals(&self, proposer: &ProposerAddress) -> &[Commitment] {
+        self.missed_reveals.get(proposer).map_or(&[], |commitments| &commitments[..])
+    }
+
+    /// The proposers with missed reveals
+    pub fn proposers(&self) -> impl Iterator<Item = &ProposerAddress> {
+        self.missed_reveals.keys()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use modules::collation::blob::Blob;
+    use modules::collation::body::BodyBuilder;
+    use modules::primitives::ChunkRootHash;
+
+    fn generate_collation(proposer: u8, bid: u64) -> Collation {
+        let mut builder = BodyBuilder::new();
+        builder.add_blob(Blob::new(vec![proposer; 100])).unwrap();
+        let body = builder.build_padded();
+        let header = Header::new(ShardIdHash::from(1), body.chunk_root(), ChunkPeriodHash::from(2),
+                                 ProposerAddress::from([proposer; 20]), ProposerBidHash::from(bid));
+        Collation::new(header, body)
+    }
+
+    #[test]
+    fn it_commits_to_header_and_bid() {
+        let collation = generate_collation(1, 5);
+        let commitment = Commitment::for_header(&collation.header);
+        assert_eq!(commitment, Commitment::new(ShardIdHash::from(1), ChunkPeriodHash::from(2),
+                                               ProposerAddress::from([1; 20]), collation.header.hash(),
+                                               ProposerBidHash::from(5)));
+        commitment.check_reveal(&collation).unwrap();
+    }
+
+    #[test]
+    fn it_rejects_reveals_not_matching_commitment() {
+        let collation = generate_collation(1, 5);
+        let commitment = Commitment::for_header(&collation.header);
+
+        // Another collation, or the same header with another bid
+        for revealed in &[generate_collation(2, 5), generate_collation(1, 6)] {
+            match commitment.check_reveal(revealed) {
+                Err(Error(ErrorKind::CommitmentMismatch(committed, revealed_hash), _)) => {
+                    assert_eq!((committed, revealed_hash), (commitment.header_hash, revealed.header.hash()));
+                },
+                result => panic!("Expected commitment mismatch error, got {:?}", result)
+            }
+        }
+
+        // A header committing to a body other than the revealed one
+        let mut revealed = collation.clone();
+        revealed.header = Header::new(ShardIdHash::from(1), ChunkRootHash::zero(), ChunkPeriodHash::from(2),
+                                      ProposerAddress::from([1; 20]), ProposerBidHash::from(5));
+        match Commitment::for_header(&revealed.header).check_reveal(&revealed) {
+            Err(Error(ErrorKind::ChunkRootMismatch(..), _)) => {},
+            result => panic!("Expected chunk root mismatch error, got {:?}", result)
+        }
+    }
+
+    #[test]
+    fn it_selects_the_commitment_with_the_highest_bid() {
+        let commitments: Vec<Commitment> = vec![(1, 5), (2, 9), (3, 9), (4, 7)].into_iter()
+            .map(|(proposer, bid)| Commitment::for_header(&generate_collation(proposer, bid).header))
+            .collect();
+        let lowest_hash_of_highest_bid = if commitments[1].header_hash < commitments[2].header_hash {
+            &commitments[1]
+        } else {
+            &commitments[2]
+        };
+        assert_eq!(select_commitment(&commitments), Some(lowest_hash_of_highest_bid));
+        assert_eq!(select_commitment(&[]), None);
+    }
+
+    #[test]
+    fn it_records_missed_reveals_by_proposer() {
+        let mut penalties = PenaltyRecord::new();
+        let first = Commitment::for_header(&generate_collation(1, 5).header);
+        let second = Commitment { period: ChunkPeriodHash::from(3), ..first.clone() };
+        penalties.record(first.clone());
+        penalties.record(second.clone());
+
+        assert_eq!(penalties.missed_reveals(&ProposerAddress::from([1; 20])), &[first, second]);
+        assert!(penalties.missed_reveals(&ProposerAddress::from([2; 20])).is_empty());
+        assert_eq!(penalties.proposers().collect::<Vec<_>>(), vec![&ProposerAddress::from([1; 20])]);
+    }
+}
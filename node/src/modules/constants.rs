@@ -1,4 +1,6 @@
 //use ethereum_types;
+use ethereum_types::U256;
+use modules::primitives::Wei;
 
 //  Blobs and Chunks
 
@@ -6,10 +8,51 @@ pub const CHUNK_SIZE: usize = 32; // bytes
 pub const CHUNK_DATA_SIZE: usize = CHUNK_SIZE - 1;  // size of chunk excluding the indicator byte
 pub const COLLATION_SIZE: usize = 1048576; // 2^20 bytes, .pow() can't be used in a constant expression. 
 pub const CHUNKS_PER_COLLATION: usize = COLLATION_SIZE / CHUNK_SIZE; // 2**15
+/// depth of the binary Merkle tree over the chunks of a collation body, log2(CHUNKS_PER_COLLATION)
+pub const CHUNK_TREE_DEPTH: usize = 15;
+/// default number of random chunks a notary samples from each collation body to check its availability
+pub const AVAILABILITY_SAMPLES: usize = 20;
 pub const DATA_BYTES_PER_COLLATION: usize = COLLATION_SIZE * CHUNK_DATA_SIZE / CHUNK_SIZE;
 /// size of a blob filling a full collation
 pub const MAX_BLOB_SIZE: usize = CHUNKS_PER_COLLATION * CHUNK_DATA_SIZE;
 
+//  Notarization
+
+/// number of notaries sampled to vote on each shard in a period
+pub const COMMITTEE_SIZE: usize = 135;
+/// number of votes for a collation header needed to notarize it
+pub const QUORUM_SIZE: usize = 90;
+
+//  Sharding manager contract
+
+/// number of shards
+pub const SHARD_COUNT: usize = 100;
+/// number of main chain blocks in a period
+pub const PERIOD_LENGTH: u64 = 5;
+/// expected time between main chain blocks, for estimating block numbers from the wall clock
+pub const BLOCK_TIME_MILLIS: u64 = 15_000;
+/// time the SMC listener waits between polls of the contract
+pub const SMC_POLL_INTERVAL_MILLIS: u64 = 500;
+/// time between the notary's checks of its collations when no messages arrive
+pub const NOTARY_TICK_MILLIS: u64 = 1000;
+/// time an event forwarder waits for an SMC listener message before checking whether
+/// its client thread was terminated
+pub const EVENT_FORWARDER_POLL_MILLIS: u64 = 100;
+/// number of periods before the current one that a notary keeps collations for
+pub const COLLATION_LOOKBACK_PERIODS: u64 = 16;
+/// most bytes of collation and proposal bodies a notary keeps, 256 MiB
+pub const NOTARY_MAX_STORED_BYTES: usize = 256 * 1024 * 1024;
+/// most bytes of blob data a proposer's blob pool holds, 64 MiB
+pub const BLOB_POOL_MAX_BYTES: usize = 64 * 1024 * 1024;
+/// most bytes of blob data one sender can have in a blob pool, 4 MiB
+pub const BLOB_POOL_MAX_SENDER_BYTES: usize = 4 * 1024 * 1024;
+/// number of periods a blob waits in a blob pool before it is dropped
+pub const BLOB_POOL_EXPIRY_PERIODS: u64 = 10;
+/// deposit a notary locks when registering, 1000 ether
+pub const NOTARY_DEPOSIT: Wei = U256([0x35c9_adc5_dea0_0000, 0x36, 0, 0]);
+/// deposit a proposer locks when registering, 1 ether
+pub const PROPOSER_DEPOSIT: Wei = U256([0x0de0_b6b3_a764_0000, 0, 0, 0]);
+
 pub const SAMPLE_COLLATION_PARENT_HASH_BYTES: [u8; 32] = [0x50, 0xa1, 0xb3, 0xd5, 0x14, 0xd4, 0x99, 0x63,
                                                           0x54, 0x14, 0x7a, 0xd2, 0x89, 0x61, 0x75, 0xb0,
                                                           0x7d, 0x43, 0x7f, 0x9e, 0x58, 0xfa, 0x3c, 0x44,
@@ -32,4 +75,15 @@ mod tests {
         assert_eq!(COLLATION_SIZE % CHUNK_SIZE, 0, "Test: COLLATION_SIZE {} % 
             CHUNK_SIZE {} == 0", COLLATION_SIZE, CHUNK_SIZE);
     }
+
+    #[test]
+    fn chunk_tree_depth_fits_chunks_per_collation() {
+        assert_eq!(1 << CHUNK_TREE_DEPTH, CHUNKS_PER_COLLATION);
+    }
+
+    #[test]
+    fn deposits_are_in_wei() {
+        assert_eq!(NOTARY_DEPOSIT, Wei::from_dec_str("1000000000000000000000").unwrap());
+        assert_eq!(PROPOSER_DEPOSIT, Wei::from_dec_str("1000000000000000000").unwrap());
+    }
 }
@@ -0,0 +1,229 @@
+// Create the Error, ErrorKind, ResultExt, and Result types.
+// Other modules in this crate `use modules::errors::*;` to get access to them.
+// error_chain implements the deprecated `description` and `cause` of `std::error::Error`.
+#![allow(deprecated)]
+
+use modules::constants::{CHUNK_SIZE, CHUNK_DATA_SIZE, CHUNKS_PER_COLLATION};
+use ethereum_types::Address;
+use modules::primitives::{
+    BlobHash,
+    ChunkRootHash,
+    ShardIdHash,
+    ChunkPeriodHash,
+    CollationHeaderHash,
+    NotaryAddress,
+    ProposerAddress,
+    ProposerBidHash,
+    Wei
+};
+
+error_chain! {
+    foreign_links {
+        Io(::std::io::Error) #[doc = "An I/O error, e.g. of a connection to an Ethereum node."];
+        Json(::serde_json::Error) #[doc = "Invalid JSON, e.g. in a JSON-RPC response."];
+    }
+
+    errors {
+        /// The length bits of a terminal chunk's indicator claim more data
+        /// bytes than fit into a chunk.
+        TerminalLengthTooLarge(length: u8) {
+            description("terminal chunk length is larger than the chunk data size")
+            display("Terminal chunk length {} is larger than the chunk data size of {} bytes",
+                length, CHUNK_DATA_SIZE)
+        }
+        /// One of the two reserved bits of an indicator byte is set.
+        ReservedIndicatorBitsSet(indicator: u8) {
+            description("reserved indicator bits are set")
+            display("Reserved bits are set in indicator byte {:#010b}", indicator)
+        }
+        /// A sequence of chunks ends without a terminal chunk.
+        MissingTerminalChunk(chunks: usize) {
+            description("chunk sequence has no terminal chunk")
+            display("Sequence of {} chunks has no terminal chunk", chunks)
+        }
+        /// The length of a serialized collation body is not a multiple of `CHUNK_SIZE`.
+        BodyNotChunkAligned(length: usize) {
+            description("body length is not a multiple of the chunk size")
+            display("Body of {} bytes is not a multiple of the chunk size of {} bytes",
+                length, CHUNK_SIZE)
+        }
+        /// A collation body has more than `CHUNKS_PER_COLLATION` chunks.
+        BodyTooLarge(chunks: usize) {
+            description("body has too many chunks")
+            display("Body of {} chunks is larger than a collation of {} chunks", chunks, CHUNKS_PER_COLLATION)
+        }
+        /// A collation body has fewer than `CHUNKS_PER_COLLATION` chunks, so it isn't
+        /// padded to the size of a collation.
+        BodyNotPadded(chunks: usize) {
+            description("body is not padded to the collation size")
+            display("Body of {} chunks is not padded to a collation of {} chunks", chunks, CHUNKS_PER_COLLATION)
+        }
+        /// The chunk root in a collation header isn't the root of the collation's body.
+        ChunkRootMismatch(header_root: ChunkRootHash, body_root: ChunkRootHash) {
+            description("chunk root doesn't match the body")
+            display("Header has chunk root {:?}, but the body has chunk root {:?}", header_root, body_root)
+        }
+        /// A chunk index is outside of a (possibly extended) collation body.
+        ChunkIndexOutOfRange(index: usize, chunks: usize) {
+            description("chunk index out of range")
+            display("Chunk index {} is out of range for {} chunks", index, chunks)
+        }
+        /// Too few distinct chunks are available to rebuild an erasure-coded body.
+        NotEnoughChunks(available: usize, needed: usize) {
+            description("not enough chunks to rebuild the body")
+            display("{} distinct chunks are available, {} are needed to rebuild the body", available, needed)
+        }
+        /// Reed-Solomon encoding or decoding failed.
+        ErasureCodingFailed(message: String) {
+            description("erasure coding failed")
+            display("Erasure coding failed: {}", message)
+        }
+        /// Some of the bodies a blob was serialized into are missing or duplicated.
+        IncompleteBlobBodies(blob_hash: BlobHash, total: usize) {
+            description("blob bodies are missing or duplicated")
+            display("Bodies of blob {:?} are missing or duplicated, expected {} distinct bodies",
+                blob_hash, total)
+        }
+        /// A reassembled blob doesn't match the hash it was serialized with.
+        BlobHashMismatch(expected: BlobHash, actual: BlobHash) {
+            description("reassembled blob hash mismatch")
+            display("Reassembled blob has hash {:?}, expected {:?}", actual, expected)
+        }
+        /// A blob with the same data is in the blob pool already.
+        BlobAlreadyPooled(blob_hash: BlobHash) {
+            description("blob is pooled already")
+            display("Blob {:?} is in the blob pool already", blob_hash)
+        }
+        /// A blob has more chunks than fit into a collation body.
+        BlobTooLarge(chunks: usize) {
+            description("blob doesn't fit into a collation")
+            display("Blob of {} chunks is larger than a collation of {} chunks", chunks, CHUNKS_PER_COLLATION)
+        }
+        /// A sender's pooled blobs would take more than the pool allows each sender.
+        SenderBlobLimit(sender: Address, size: usize, limit: usize) {
+            description("sender's pooled blobs exceed the limit")
+            display("Pooled blobs of sender {:?} would take {} bytes, more than the limit of {} bytes",
+                sender, size, limit)
+        }
+        /// The blob pool is full of blobs paying at least as much per chunk.
+        BlobPoolFull(size: usize, limit: usize) {
+            description("blob pool is full")
+            display("Blob pool would take {} bytes, more than the limit of {} bytes", size, limit)
+        }
+        /// A shard id is not below `SHARD_COUNT`.
+        ShardIdOutOfRange(shard_id: ShardIdHash) {
+            description("shard id out of range")
+            display("Shard id {} is out of range", shard_id)
+        }
+        /// A notary address is registered in the notary registry already.
+        NotaryAlreadyRegistered(notary: NotaryAddress) {
+            description("notary is already registered")
+            display("Notary {:?} is already registered", notary)
+        }
+        /// A notary address is not in the notary registry.
+        NotaryNotRegistered(notary: NotaryAddress) {
+            description("notary is not registered")
+            display("Notary {:?} is not registered", notary)
+        }
+        /// A proposer address is registered in the proposer registry already.
+        ProposerAlreadyRegistered(proposer: ProposerAddress) {
+            description("proposer is already registered")
+            display("Proposer {:?} is already registered", proposer)
+        }
+        /// A proposer address is not in the proposer registry.
+        ProposerNotRegistered(proposer: ProposerAddress) {
+            description("proposer is not registered")
+            display("Proposer {:?} is not registered", proposer)
+        }
+        /// A deposit is less than the deposit required to register.
+        DepositTooLow(deposit: Wei, required: Wei) {
+            description("deposit is too low")
+            display("Deposit of {} wei is less than the required {} wei", deposit, required)
+        }
+        /// A proposer's balance doesn't cover its bid.
+        InsufficientBalance(proposer: ProposerAddress, bid: ProposerBidHash, balance: Wei) {
+            description("proposer balance doesn't cover its bid")
+            display("Balance of {} wei of proposer {:?} doesn't cover its bid of {} wei", balance, proposer, bid)
+        }
+        /// A header or vote is for another period than the current one.
+        WrongPeriod(period: ChunkPeriodHash, current: ChunkPeriodHash) {
+            description("not the current period")
+            display("Period {} is not the current period {}", period, current)
+        }
+        /// A header was added for the shard and period already.
+        HeaderAlreadyAdded(shard_id: ShardIdHash, period: ChunkPeriodHash) {
+            description("header already added")
+            display("A header was added for shard id {} and period {} already", shard_id, period)
+        }
+        /// A vote is for a header that wasn't added for its shard and period.
+        UnknownHeader(header_hash: CollationHeaderHash) {
+            description("unknown header")
+            display("Header {:?} was not added for its shard and period", header_hash)
+        }
+        /// A notary received a collation of a shard it isn't assigned to.
+        ShardNotAssigned(shard_id: ShardIdHash) {
+            description("shard is not assigned to the notary")
+            display("Shard id {} is not assigned to the notary", shard_id)
+        }
+        /// A notary voted in a shard without being in the shard's committee.
+        NotaryNotInCommittee(notary: NotaryAddress, shard_id: ShardIdHash) {
+            description("notary is not in the committee")
+            display("Notary {:?} is not in the committee of shard id {}", notary, shard_id)
+        }
+        /// A notary voted twice in the same shard and period.
+        AlreadyVoted(notary: NotaryAddress, shard_id: ShardIdHash, period: ChunkPeriodHash) {
+            description("notary already voted")
+            display("Notary {:?} already voted in shard id {} and period {}", notary, shard_id, period)
+        }
+        /// A revealed collation isn't the one a notary chose for its shard and period.
+        UnknownCommitment(header_hash: CollationHeaderHash) {
+            description("no chosen commitment to the revealed collation")
+            display("Collation {:?} was revealed without being chosen", header_hash)
+        }
+        /// A revealed collation's header or bid differs from the commitment.
+        CommitmentMismatch(committed: CollationHeaderHash, revealed: CollationHeaderHash) {
+            description("revealed collation doesn't match the commitment")
+            display("Revealed collation {:?} doesn't match the commitment to {:?}", revealed, committed)
+        }
+        /// An Ethereum node returned a JSON-RPC error.
+        RpcError(code: i64, message: String) {
+            description("JSON-RPC error")
+            display("JSON-RPC error {}: {}", code, message)
+        }
+        /// An Ethereum node returned a response that isn't valid HTTP or JSON-RPC.
+        InvalidRpcResponse(reason: String) {
+            description("invalid JSON-RPC response")
+            display("Invalid JSON-RPC response: {}", reason)
+        }
+        /// A string isn't valid hex.
+        InvalidHex(hex: String) {
+            description("invalid hex")
+            display("Invalid hex {}", hex)
+        }
+        /// The URL of an Ethereum node isn't a plain `http://` URL.
+        InvalidRpcUrl(url: String) {
+            description("unsupported JSON-RPC URL")
+            display("Only http:// URLs are supported, got {}", url)
+        }
+        /// A proposer was run without an SMC backend to add its collations to.
+        MissingSmcBackend {
+            description("no SMC backend")
+            display("A proposer needs an SMC backend to add collations")
+        }
+        /// A transaction would be sent from an account that the signer doesn't have the key of.
+        UnknownAccount(account: Address) {
+            description("no key for the account")
+            display("No key to sign transactions from account {:?}", account)
+        }
+        /// A secret key is not a valid secp256k1 secret key.
+        InvalidSecretKey {
+            description("invalid secret key")
+            display("Invalid secret key")
+        }
+        /// Return data or an event log doesn't decode as the expected ABI types.
+        InvalidAbiData(reason: String) {
+            description("invalid ABI data")
+            display("Invalid ABI data: {}", reason)
+        }
+    }
+}
@@ -0,0 +1,43 @@
+use modules::errors::*;
+
+/// Encodes bytes as a `0x` prefixed hex string
+pub fn to_hex(bytes: &[u8]) -> String {
+    let mut hex = String::with_capacity(2 + bytes.len() * 2);
+    hex.push_str("0x");
+    for byte in bytes {
+        hex.push_str(&format!("{:02x}", byte));
+    }
+    hex
+}
+
+/// Decodes a hex string, with or without a `0x` prefix. An odd number of digits is
+/// read as if it had a leading zero, as in JSON-RPC quantities.
+pub fn from_hex(hex: &str) -> Result<Vec<u8>> {
+    let digits = hex.strip_prefix("0x").unwrap_or(hex);
+    let padded = if digits.len() % 2 == 1 { format!("0{}", digits) } else { digits.to_string() };
+    (0..padded.len())
+        .step_by(2)
+        .map(|index| match u8::from_str_radix(&padded[index..index + 2], 16) {
+            Ok(byte) => Ok(byte),
+            Err(_) => bail!(ErrorKind::InvalidHex(hex.to_string()))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_encodes_and_decodes_hex() {
+        assert_eq!(to_hex(&[0x00, 0xab, 0x10]), "0x00ab10");
+        assert_eq!(from_hex("0x00ab10").unwrap(), vec![0x00, 0xab, 0x10]);
+        assert_eq!(from_hex("ab").unwrap(), vec![0xab]);
+        assert_eq!(from_hex("0x1").unwrap(), vec![0x01]);
+        assert_eq!(from_hex("0x").unwrap(), Vec::<u8>::new());
+        match from_hex("0xzz") {
+            Err(Error(ErrorKind::InvalidHex(hex), _)) => assert_eq!(hex, "0xzz"),
+            result => panic!("Expected invalid hex error, got {:?}", result)
+        }
+    }
+}
@@ -1,11 +1,23 @@
 use modules::collation::collation;
-use modules::primitives::{ShardIdHash};
+use modules::collation::header;
+use modules::commitment::Commitment;
+use modules::primitives::{ShardIdHash, ChunkPeriodHash};
+use modules::vote::Vote;
 
-#[derive(Debug)]
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
 /// A message from the SMC Listener
 pub enum Message {
+    PeriodStart{value: ChunkPeriodHash},
     Selected{value: bool},
     ShardId{value: ShardIdHash},
     Collation{value: collation::Collation},
-    Proposal{value: collation::Collation}
+    Header{value: header::Header},
+    Proposal{value: collation::Collation},
+    Vote{value: Vote},
+    /// A proposer's commitment to a collation, before revealing it
+    Commitment{value: Commitment},
+    /// A notary chose the commitment, so its proposer should reveal the collation
+    Chosen{value: Commitment},
+    /// A collation revealed by the proposer of a chosen commitment
+    Reveal{value: collation::Collation}
 }
\ No newline at end of file
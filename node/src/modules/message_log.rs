@@ -0,0 +1,178 @@
+use modules::errors::*;
+use modules::message::Message;
+
+use serde_json;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+use std::sync::mpsc;
+use std::thread;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// A message received by a notary or proposer, with the time it was received in
+/// milliseconds since the Unix epoch.
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
+pub struct RecordedMessage {
+    pub timestamp_millis: u64,
+    pub message: Message
+}
+
+/// Writes received messages to a message log, one JSON object per line, so that the
+/// stream can be replayed, e.g. with `Notary::replay`.
+pub struct MessageRecorder<W: Write> {
+    writer: W
+}
+
+impl MessageRecorder<BufWriter<File>> {
+    /// Creates a recorder writing to a new log file, replacing any existing file
+    pub fn create<P: AsRef<Path>>(path: P) -> Result<MessageRecorder<BufWriter<File>>> {
+        Ok(MessageRecorder::new(BufWriter::new(File::create(path)?)))
+    }
+}
+
+impl<W: Write> MessageRecorder<W> {
+    pub fn new(writer: W) -> MessageRecorder<W> {
+        MessageRecorder {
+            writer
+        }
+    }
+
+    /// Records a message received now
+    pub fn record(&mut self, message: &Message) -> Result<()> {
+        let timestamp_millis = match SystemTime::now().duration_since(UNIX_EPOCH) {
+            Ok(elapsed) => elapsed.as_millis() as u64,
+            Err(_) => 0
+        };
+        self.record_at(timestamp_millis, message)
+    }
+
+    /// Records a message received at a time in milliseconds since the Unix epoch
+    pub fn record_at(&mut self, timestamp_millis: u64, message: &Message) -> Result<()> {
+        let recorded = RecordedMessage {
+            timestamp_millis,
+            message: message.clone()
+        };
+        serde_json::to_writer(&mut self.writer, &recorded)?;
+        // Each line is flushed, so the log is complete up to the last message if the
+        // node crashes.
+        self.writer.write_all(b"\n")?;
+        self.writer.flush()?;
+        Ok(())
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+/// Reads the messages of a message log in the order they were recorded
+pub fn read_message_log<R: BufRead>(reader: R) -> Result<Vec<RecordedMessage>> {
+    let mut messages = vec![];
+    for line in reader.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        messages.push(serde_json::from_str(&line)?);
+    }
+    Ok(messages)
+}
+
+/// Reads the messages of a message log file
+pub fn open_message_log<P: AsRef<Path>>(path: P) -> Result<Vec<RecordedMessage>> {
+    read_message_log(BufReader::new(File::open(path)?))
+}
+
+/// Records every message from `source` in its own thread and forwards it to the
+/// returned receiver, e.g. to put between the SMC listener and a notary or proposer.
+/// The thread stops when `source` hangs up or the returned receiver is dropped.
+pub fn record_messages<W: Write + Send + 'static>(source: mpsc::Receiver<Message>,
+                                                  mut recorder: MessageRecorder<W>)
+                                                  -> (mpsc::Receiver<Message>, thread::JoinHandle<()>) {
+    let (sender, receiver) = mpsc::channel();
+    let handle = thread::Builder::new()
+        .name(String::from("message_recorder"))
+        .spawn(move || {
+            for message in source.iter() {
+                if let Err(e) = recorder.record(&message) {
+                    error!("Error recording message {:?}: {:?}", message, e);
+                }
+                if sender.send(message).is_err() {
+                    debug!("Receiver of recorded messages hung up");
+                    break;
+                }
+            }
+        })
+        .expect("Failed to spawn a message recorder thread");
+    (receiver, handle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use modules::collation::body::Body;
+    use modules::collation::chunk::Chunk;
+    use modules::collation::collation::Collation;
+    use modules::collation::header::Header;
+    use modules::constants::CHUNK_DATA_SIZE;
+    use modules::primitives::{ShardIdHash, ChunkPeriodHash, NotaryIdHash, ProposerAddress,
+                              ProposerBidHash};
+    use modules::vote::Vote;
+
+    fn generate_messages() -> Vec<Message> {
+        let body = Body::new(vec![Chunk::new(3, [7; CHUNK_DATA_SIZE])]);
+        let header = Header::new(ShardIdHash::from(2), body.chunk_root(), ChunkPeriodHash::from(1),
+                                 ProposerAddress::from([9; 20]), ProposerBidHash::zero());
+        vec![
+            Message::PeriodStart { value: ChunkPeriodHash::from(1) },
+            Message::ShardId { value: ShardIdHash::from(2) },
+            Message::Selected { value: true },
+            Message::Header { value: header.clone() },
+            Message::Collation { value: Collation::new(header.clone(), body) },
+            Message::Vote { value: Vote::for_header(&header, NotaryIdHash::from(4), true) }
+        ]
+    }
+
+    #[test]
+    fn it_records_and_reads_messages() {
+        let mut recorder = MessageRecorder::new(vec![]);
+        for (timestamp, message) in generate_messages().iter().enumerate() {
+            recorder.record_at(timestamp as u64, message).unwrap();
+        }
+        let log = recorder.into_inner();
+        assert_eq!(log.iter().filter(|byte| **byte == b'\n').count(), generate_messages().len());
+
+        let recorded = read_message_log(&log[..]).unwrap();
+        let expected: Vec<RecordedMessage> = generate_messages().into_iter()
+            .enumerate()
+            .map(|(timestamp, message)| RecordedMessage { timestamp_millis: timestamp as u64, message })
+            .collect();
+        assert_eq!(recorded, expected);
+    }
+
+    #[test]
+    fn it_rejects_malformed_logs() {
+        match read_message_log(&b"{\"timestamp_millis\": 1}\n"[..]) {
+            Err(Error(ErrorKind::Json(_), _)) => {},
+            result => panic!("Expected JSON error, got {:?}", result)
+        }
+    }
+
+    #[test]
+    fn it_records_messages_passing_through() {
+        let (source_sender, source) = mpsc::channel();
+        let path = ::std::env::temp_dir().join(format!("diamond_drops_message_log_{}.jsonl", ::std::process::id()));
+        let (receiver, handle) = record_messages(source, MessageRecorder::create(&path).unwrap());
+        for message in generate_messages() {
+            source_sender.send(message).unwrap();
+        }
+        drop(source_sender);
+
+        assert_eq!(receiver.iter().collect::<Vec<Message>>(), generate_messages());
+        handle.join().unwrap();
+        let recorded = open_message_log(&path).unwrap();
+        ::std::fs::remove_file(&path).unwrap();
+        assert_eq!(recorded.into_iter().map(|recorded| recorded.message).collect::<Vec<Message>>(),
+                   generate_messages());
+    }
+}
@@ -1,9 +1,18 @@
+pub mod availability;
+pub mod bidding;
+pub mod blob_pool;
 pub mod client_thread;
 pub mod collation;
+pub mod commitment;
 pub mod constants;
 pub mod errors;
+pub mod hex;
 pub mod message;
+pub mod message_log;
 pub mod notary;
+pub mod period_clock;
 pub mod proposer;
 pub mod smc_listener;
-pub mod primitives;
\ No newline at end of file
+pub mod primitives;
+pub mod smc;
+pub mod vote;
\ No newline at end of file
@@ -1,28 +1,72 @@
+use modules::availability;
+use modules::availability::{ChunkSource, ReceivedBodies};
 use modules::collation::body::Body;
 use modules::collation::chunk::Chunk;
 use modules::collation::collation::Collation;
+use modules::collation::store::CollationStore;
+use modules::commitment::{self, Commitment, PenaltyRecord};
 use modules::message::Message;
-use modules::client_thread::Command;
+use modules::message_log::RecordedMessage;
+use modules::vote::{Vote, VoteTally};
+use modules::client_thread::{Command, Event};
+use modules::errors::*;
+use modules::constants::{AVAILABILITY_SAMPLES, COLLATION_LOOKBACK_PERIODS, NOTARY_MAX_STORED_BYTES,
+    NOTARY_TICK_MILLIS, QUORUM_SIZE};
 use modules::primitives::{
     ShardIdHash,
     ChunkRootHash,
     ChunkPeriodHash,
     NotaryIdHash,
-    ProposerAddress
+    ProposerAddress,
+    ProposerBidHash,
+    CollationHeaderHash
 };
 
+use rand;
+use rand::{SeedableRng, XorShiftRng};
 use std::thread;
 use std::sync::mpsc;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::time::{Duration, Instant};
 
 pub struct Notary {
     id: NotaryIdHash,
     selected: bool,
-    shard_id: ShardIdHash,
-    collation_vectors: HashMap<ShardIdHash, Vec<Collation>>,
-    proposal_vectors: HashMap<ShardIdHash, Vec<Collation>>,
-    smc_listener: mpsc::Receiver<Message>,
-    manager_listener: mpsc::Receiver<Command>
+    // The period the SMC listener last announced
+    period: ChunkPeriodHash,
+    // Shards the notary is assigned to in the current period
+    assigned_shards: HashSet<ShardIdHash>,
+    // Stored by the shard in their header. Ordered, so that collations are sampled in
+    // the same order when a message log is replayed.
+    collations: CollationStore,
+    proposals: CollationStore,
+    // Number of periods before the current one that collations are kept for
+    collation_lookback: u64,
+    // Most bytes of collation and proposal bodies kept, before the oldest are evicted
+    max_stored_bytes: usize,
+    // Whether the body of each stored collation was found available by sampling it
+    availability: HashMap<CollationHeaderHash, bool>,
+    availability_samples: usize,
+    chunk_source: Box<dyn ChunkSource + Send>,
+    // Collations this notary has voted on
+    voted: HashSet<CollationHeaderHash>,
+    tally: VoteTally,
+    // Picks the chunks sampled for availability
+    rng: XorShiftRng,
+    vote_sender: Option<mpsc::Sender<Message>>,
+    // Commitments of proposers by shard and period, ordered like the collations
+    commitments: BTreeMap<(ShardIdHash, ChunkPeriodHash), Vec<Commitment>>,
+    // The commitment chosen in each shard and period. It is never replaced, as the
+    // chosen proposer adds its header to the SMC as soon as it is chosen.
+    chosen: BTreeMap<(ShardIdHash, ChunkPeriodHash), Commitment>,
+    revealed: HashSet<CollationHeaderHash>,
+    // The hash of the header added to the SMC in each shard and period
+    added_headers: HashMap<(ShardIdHash, ChunkPeriodHash), CollationHeaderHash>,
+    penalties: PenaltyRecord,
+    chosen_sender: Option<mpsc::Sender<Message>>,
+    // Messages from the SMC listener and commands from the thread manager
+    events: mpsc::Receiver<Event>,
+    tick_interval: Duration
 }
 
 impl Notary {
@@ -30,98 +74,540 @@ impl Notary {
     /// 
     /// #Inputs
     /// 
-    /// smc_listener: mpsc::Receiver<Message>
-    /// manager_listener: mpsc::Receiver<Command>
+    /// events: mpsc::Receiver<Event>
     /// 
-    /// The smc_listener allows the Notary to receive messages from the SMC Listener, 
-    /// and the manager_listener allows the thread to receive commands from outside the thread.
-    pub fn new(smc_listener: mpsc::Receiver<Message>, 
-               manager_listener: mpsc::Receiver<Command>) -> Notary {
+    /// The events are the messages from the SMC Listener and the commands from outside
+    /// the thread, e.g. merged by `client_thread::merge_events`.
+    pub fn new(events: mpsc::Receiver<Event>) -> Notary {
         Notary {
             id: NotaryIdHash::from_dec_str("0").unwrap(),
             selected: false,
-            shard_id: ShardIdHash::from_dec_str("0").unwrap(),
-            collation_vectors: HashMap::new(),
-            proposal_vectors: HashMap::new(),
-            smc_listener,
-            manager_listener
+            period: ChunkPeriodHash::zero(),
+            assigned_shards: HashSet::new(),
+            collations: CollationStore::new(),
+            proposals: CollationStore::new(),
+            collation_lookback: COLLATION_LOOKBACK_PERIODS,
+            max_stored_bytes: NOTARY_MAX_STORED_BYTES,
+            availability: HashMap::new(),
+            availability_samples: AVAILABILITY_SAMPLES,
+            chunk_source: Box::new(ReceivedBodies::new()),
+            voted: HashSet::new(),
+            tally: VoteTally::new(QUORUM_SIZE),
+            rng: rand::weak_rng(),
+            vote_sender: None,
+            commitments: BTreeMap::new(),
+            chosen: BTreeMap::new(),
+            revealed: HashSet::new(),
+            added_headers: HashMap::new(),
+            penalties: PenaltyRecord::new(),
+            chosen_sender: None,
+            events,
+            tick_interval: Duration::from_millis(NOTARY_TICK_MILLIS)
         }
     }
 
+    /// Sets the number of random chunks sampled from each collation body to check its
+    /// availability, AVAILABILITY_SAMPLES by default.
+    pub fn set_availability_samples(&mut self, samples: usize) {
+        self.availability_samples = samples;
+    }
+
+    /// Sets where chunks are fetched from when sampling collation bodies, the bodies
+    /// of the received collations by default.
+    pub fn set_chunk_source(&mut self, chunk_source: Box<dyn ChunkSource + Send>) {
+        self.chunk_source = chunk_source;
+    }
+
+    /// Sets where the notary's votes are sent to. Without a sender, votes are only
+    /// counted in the notary's own tally.
+    pub fn set_vote_sender(&mut self, vote_sender: mpsc::Sender<Message>) {
+        self.vote_sender = Some(vote_sender);
+    }
+
+    /// Sets how often the running notary checks its collations when no events arrive,
+    /// NOTARY_TICK_MILLIS by default.
+    pub fn set_tick_interval(&mut self, tick_interval: Duration) {
+        self.tick_interval = tick_interval;
+    }
+
+    /// Sends the commitments the notary chooses, for their proposers to reveal them
+    pub fn set_chosen_sender(&mut self, chosen_sender: mpsc::Sender<Message>) {
+        self.chosen_sender = Some(chosen_sender);
+    }
+
+    /// Sets for how many periods before the current one collations are kept,
+    /// COLLATION_LOOKBACK_PERIODS by default. Proposals are only kept during their period,
+    /// while they can be voted on.
+    pub fn set_collation_lookback(&mut self, periods: u64) {
+        self.collation_lookback = periods;
+    }
+
+    /// Sets how many bytes of collation and proposal bodies are kept at most, counting
+    /// what the chunk source keeps of them, evicting the oldest first,
+    /// NOTARY_MAX_STORED_BYTES by default.
+    pub fn set_max_stored_bytes(&mut self, max_stored_bytes: usize) {
+        self.max_stored_bytes = max_stored_bytes;
+    }
+
+    /// Seeds the choice of chunks sampled for availability, e.g. to replay a message
+    /// log deterministically. The seed must not be all zeros.
+    pub fn set_rng_seed(&mut self, seed: [u32; 4]) {
+        self.rng = XorShiftRng::from_seed(seed);
+    }
+
+    /// The tally of the votes submitted and received by the notary.
+    pub fn tally(&self) -> &VoteTally {
+        &self.tally
+    }
+
+    /// Assigns the notary to a shard until the next period starts, so that it stores
+    /// the shard's collations. The SMC listener assigns the shards the notary is
+    /// selected in with `ShardId` messages.
+    pub fn assign_shard(&mut self, shard_id: ShardIdHash) {
+        self.assigned_shards.insert(shard_id);
+    }
+
+    /// The stored collations of a shard in a period, in the order they were received
+    pub fn collations(&self, shard_id: ShardIdHash, period: ChunkPeriodHash) -> &[Collation] {
+        self.collations.get(shard_id, period)
+    }
+
+    /// The stored proposals of a shard in a period, in the order they were received
+    pub fn proposals(&self, shard_id: ShardIdHash, period: ChunkPeriodHash) -> &[Collation] {
+        self.proposals.get(shard_id, period)
+    }
+
+    /// The commitment chosen in the shard and period, if any
+    pub fn chosen(&self, shard_id: ShardIdHash, period: ChunkPeriodHash) -> Option<&Commitment> {
+        self.chosen.get(&(shard_id, period))
+    }
+
+    /// The proposers that were chosen but didn't reveal their collations
+    pub fn penalties(&self) -> &PenaltyRecord {
+        &self.penalties
+    }
+
+    /// Whether the body of the collation with the given header hash was found
+    /// available, or `None` if it hasn't been sampled yet.
+    pub fn availability(&self, header_hash: &CollationHeaderHash) -> Option<bool> {
+        self.availability.get(header_hash).cloned()
+    }
+
     /// Runs the notary
+    ///
+    /// The notary blocks until a message or command arrives or its timer is due, so it
+    /// doesn't use the CPU while idle. Events are handled in the order they arrive, and
+    /// a due timer fires before the next event is received, so a stream of messages
+    /// can't delay it. The notary stops on `Command::Terminate`, or when both the SMC
+    /// listener and the thread manager hung up.
     pub fn run(&mut self) {
+        let mut next_tick = Instant::now() + self.tick_interval;
         loop {
-            // Asynchronously get message from the thread manager
-            let manager_msg = self.manager_listener.try_iter().next();
-
-            // Respond to the thread manager message
-            match manager_msg {
-                Some(msg) => {
-                    debug!("Received pending message {:?} in thread {:?} from another thread", msg, thread::current());
-                    match msg {
+            let now = Instant::now();
+            if now >= next_tick {
+                trace!("Timer of notary id {} fired in thread {:?}", self.id, thread::current());
+                self.act();
+                next_tick = now + self.tick_interval;
+            }
+
+            match self.events.recv_timeout(next_tick - now) {
+                Ok(Event::Command(command)) => {
+                    debug!("Received command {:?} in thread {:?} from the thread manager", command, thread::current());
+                    match command {
                         Command::Terminate => { break }
                     }
                 },
-                None => {
-                     trace!("No more pending messages from other threads to thread {:?} or channel hung up", thread::current());
+                Ok(Event::Message(msg)) => {
+                    debug!("Received message {:?} in thread {:?} from SMC Listener", msg, thread::current());
+                    self.handle_message(msg);
+                    self.act();
+                },
+                Err(mpsc::RecvTimeoutError::Timeout) => {},
+                Err(mpsc::RecvTimeoutError::Disconnected) => {
+                    debug!("SMC Listener and thread manager of thread {:?} hung up", thread::current());
+                    break;
+                }
+            }
+        }
+    }
+
+    /// Feeds the messages of a message log to the notary in the order they were
+    /// recorded, acting on each as `run` would. Together with `set_rng_seed`, this
+    /// makes the notary's behaviour on a recorded stream reproducible.
+    pub fn replay(&mut self, messages: &[RecordedMessage]) {
+        for recorded in messages {
+            debug!("Notary id {} replaying message {:?} received at {}", self.id, recorded.message,
+                   recorded.timestamp_millis);
+            self.handle_message(recorded.message.clone());
+            self.act();
+        }
+    }
+
+    fn handle_message(&mut self, msg: Message) {
+        match msg {
+            Message::PeriodStart { value } => {
+                debug!("Period {} started", value);
+                self.period = value;
+                self.assigned_shards.clear();
+                self.close_commitments(value);
+                self.drop_old_collations(value);
+            },
+            Message::Selected { value } => { self.selected = value; },
+            Message::ShardId { value } => { self.assign_shard(value); },
+            Message::Header { value } => {
+                debug!("Header {:?} was added to the SMC", value.hash());
+                self.added_headers.insert((value.shard_id, value.period()), value.hash());
+            },
+            Message::Collation { value } => {
+                if let Err(e) = self.store_collation(value) {
+                    warn!("Notary id {} rejected collation: {}", self.id, e);
+                }
+            },
+            Message::Proposal { value } => {
+                if let Err(e) = self.store_proposal(value) {
+                    warn!("Notary id {} rejected proposal: {}", self.id, e);
+                }
+            },
+            Message::Vote { value } => { self.receive_vote(value); },
+            Message::Commitment { value } => {
+                if let Err(e) = self.store_commitment(value) {
+                    warn!("Notary id {} rejected commitment: {}", self.id, e);
+                }
+            },
+            Message::Chosen { value } => { debug!("Commitment {:?} was chosen", value.header_hash); },
+            Message::Reveal { value } => {
+                if let Err(e) = self.receive_reveal(value) {
+                    warn!("Notary id {} rejected revealed collation: {}", self.id, e);
                 }
             }
+        }
+    }
 
-            // Asynchronously get message from the SMC listener
-            let smc_msg = self.smc_listener.try_iter().next();
-
-            // Respond to SMC listener message
-            match smc_msg {
-                Some(msg) => {
-                    debug!("Received pending message {:?} in thread {:?} from SMC Listener", msg, thread::current());
-                    match msg {
-                        Message::Selected { value } => { self.selected = value; }
-                        Message::ShardId { value } => { self.shard_id = value; },
-                        Message::Collation { value } => { self.store_collation(value); },
-                        Message::Proposal { value } => { self.store_proposal(value); }
-                    }
-                },
-                None => {
-                     trace!("No more pending messages from SMC Listener to thread {:?} or channel hung", thread::current());
+    /// Check availability, promote proposals, choose commitments and vote while selected
+    fn act(&mut self) {
+        if self.selected {
+            self.get_availability();
+            self.promote_proposals();
+            self.choose_commitments();
+            self.submit_vote();
+        }
+    }
+
+
+    /// Store a collation under the shard and period in its header. Collations of
+    /// shards the notary isn't assigned to, or of other periods, are rejected.
+    fn store_collation(&mut self, collation: Collation) -> Result<()> {
+        self.check_assigned(collation.header.shard_id, collation.header.period())?;
+        debug!("Storing in notary id {} a new collation mapped to shard id {}", self.id, collation.header.shard_id);
+        self.chunk_source.receive_body(collation.header.hash(), &collation.body);
+        self.collations.insert(collation);
+        self.evict_over_ceiling();
+        Ok(())
+    }
+
+
+    /// Store a proposal under the shard and period in its header. Proposals of
+    /// shards the notary isn't assigned to, or of other periods, are rejected.
+    fn store_proposal(&mut self, proposal: Collation) -> Result<()> {
+        self.check_assigned(proposal.header.shard_id, proposal.header.period())?;
+        debug!("Storing in notary id {} a new proposal collation mapped to shard id {}", self.id, proposal.header.shard_id);
+        self.chunk_source.receive_body(proposal.header.hash(), &proposal.body);
+        self.proposals.insert(proposal);
+        self.evict_over_ceiling();
+        Ok(())
+    }
+
+
+    /// Store a proposer's commitment under its shard and period. Commitments of shards
+    /// the notary isn't assigned to, or of other periods, are rejected.
+    fn store_commitment(&mut self, commitment: Commitment) -> Result<()> {
+        self.check_assigned(commitment.shard_id, commitment.period)?;
+        debug!("Storing in notary id {} commitment {:?} with bid {}", self.id, commitment.header_hash, commitment.bid);
+        let commitments = self.commitments.entry((commitment.shard_id, commitment.period)).or_default();
+        if !commitments.contains(&commitment) {
+            commitments.push(commitment);
+        }
+        Ok(())
+    }
+
+
+    /// Choose the commitment picked by `commitment::select_commitment` in each shard and
+    /// period, and send it to the chosen sender. The chosen proposer adds its header to
+    /// the SMC right away, so a choice is never replaced, and no commitment is chosen
+    /// once a header was added for the shard and period.
+    fn choose_commitments(&mut self) {
+        for (key, commitments) in &self.commitments {
+            if self.chosen.contains_key(key) || self.added_headers.contains_key(key) {
+                continue;
+            }
+            let best = match commitment::select_commitment(commitments) {
+                Some(best) => best,
+                None => continue
+            };
+            debug!("Notary id {} chooses commitment {:?} with bid {}", self.id, best.header_hash, best.bid);
+            self.chosen.insert(*key, best.clone());
+            if let Some(ref chosen_sender) = self.chosen_sender {
+                if chosen_sender.send(Message::Chosen { value: best.clone() }).is_err() {
+                    warn!("Notary id {} failed to send chosen commitment, receiver hung up", self.id);
+                }
+            }
+        }
+    }
+
+
+    /// Store a revealed collation, if it is the valid collation of the commitment chosen
+    /// in its shard and period.
+    fn receive_reveal(&mut self, collation: Collation) -> Result<()> {
+        let header_hash = collation.header.hash();
+        let chosen = match self.chosen.get(&(collation.header.shard_id, collation.header.period())) {
+            Some(chosen) if chosen.header_hash == header_hash => chosen.clone(),
+            _ => bail!(ErrorKind::UnknownCommitment(header_hash))
+        };
+        chosen.check_reveal(&collation)?;
+        self.store_collation(collation)?;
+        self.revealed.insert(header_hash);
+        Ok(())
+    }
+
+
+    /// Record a penalty for each commitment chosen in a period before the current one
+    /// that wasn't revealed, and drop the commitments of those periods. A chosen proposer
+    /// isn't penalized if another proposer's header filled the SMC slot first.
+    fn close_commitments(&mut self, period: ChunkPeriodHash) {
+        let closed: Vec<(ShardIdHash, ChunkPeriodHash)> = self.chosen.keys()
+            .filter(|(_, chosen_period)| *chosen_period < period)
+            .cloned()
+            .collect();
+        for key in closed {
+            if let Some(chosen) = self.chosen.remove(&key) {
+                if self.revealed.remove(&chosen.header_hash) {
+                    continue;
+                }
+                match self.added_headers.get(&key) {
+                    Some(header_hash) if *header_hash != chosen.header_hash => {
+                        debug!("Notary id {} doesn't penalize proposer {:?}, header {:?} was added instead",
+                               self.id, chosen.proposer, header_hash);
+                    },
+                    _ => { self.penalties.record(chosen); }
+                }
+            }
+        }
+        self.commitments.retain(|(_, commitment_period), _| *commitment_period >= period);
+        self.added_headers.retain(|(_, header_period), _| *header_period >= period);
+    }
+
+
+    /// The notary is assigned to shards for the current period only
+    fn check_assigned(&self, shard_id: ShardIdHash, period: ChunkPeriodHash) -> Result<()> {
+        if !self.assigned_shards.contains(&shard_id) {
+            bail!(ErrorKind::ShardNotAssigned(shard_id));
+        }
+        if period != self.period {
+            bail!(ErrorKind::WrongPeriod(period, self.period));
+        }
+        Ok(())
+    }
+
+
+    /// Drop the proposals of periods before the current one, whose voting window has
+    /// closed, and the collations of periods before the lookback.
+    fn drop_old_collations(&mut self, period: ChunkPeriodHash) {
+        let proposals = self.proposals.remove_before(period);
+        let lookback_start = period.saturating_sub(ChunkPeriodHash::from(self.collation_lookback));
+        let collations = self.collations.remove_before(lookback_start);
+        if !proposals.is_empty() || !collations.is_empty() {
+            debug!("Notary id {} dropped {} proposals and {} collations of past periods",
+                   self.id, proposals.len(), collations.len());
+        }
+        for collation in proposals.iter().chain(collations.iter()) {
+            self.forget(collation);
+        }
+    }
+
+
+    /// The bytes kept of collation and proposal bodies, in the stores and in the chunk
+    /// source, e.g. the copies and chunk trees of `ReceivedBodies`
+    fn stored_bytes(&self) -> usize {
+        self.collations.size() + self.proposals.size() + self.chunk_source.stored_bytes()
+    }
+
+
+    /// Evict the oldest proposals and collations until what is kept of their bodies
+    /// fits in the memory ceiling. Of the same period, proposals are evicted first.
+    fn evict_over_ceiling(&mut self) {
+        while self.stored_bytes() > self.max_stored_bytes {
+            let evict_proposal = match (self.proposals.oldest_period(), self.collations.oldest_period()) {
+                (Some(proposal_period), Some(collation_period)) => proposal_period <= collation_period,
+                (Some(_), None) => true,
+                (None, Some(_)) => false,
+                (None, None) => break
+            };
+            if evict_proposal {
+                if let Some(proposal) = self.proposals.remove_oldest() {
+                    warn!("Notary id {} evicted proposal {:?} over the memory ceiling",
+                          self.id, proposal.header.hash());
+                    self.forget(&proposal);
                 }
+            } else if let Some(collation) = self.collations.remove_oldest() {
+                warn!("Notary id {} evicted collation {:?} over the memory ceiling",
+                      self.id, collation.header.hash());
+                self.forget(&collation);
+            }
+        }
+    }
+
+
+    /// Forget everything kept about a dropped collation or proposal, unless it is still
+    /// stored as the other, e.g. a promoted proposal.
+    fn forget(&mut self, collation: &Collation) {
+        let header = &collation.header;
+        let still_stored = self.collations.get(header.shard_id, header.period()).iter()
+            .chain(self.proposals.get(header.shard_id, header.period()).iter())
+            .any(|stored| stored.header == *header);
+        if still_stored {
+            return;
+        }
+        let header_hash = header.hash();
+        self.chunk_source.forget_body(&header_hash);
+        self.availability.remove(&header_hash);
+        self.voted.remove(&header_hash);
+    }
+
+
+    /// Sample the body of each stored collation and proposal that hasn't been sampled
+    /// yet, and record whether it is available.
+    fn get_availability(&mut self) {
+        for collation in self.collations.iter().chain(self.proposals.iter()) {
+            let header_hash = collation.header.hash();
+            if self.availability.contains_key(&header_hash) {
+                continue;
+            }
+            let available = availability::sample_availability(
+                &collation.header, &*self.chunk_source, self.availability_samples, &mut self.rng);
+            debug!("Notary id {} found collation {:?} {}", self.id, header_hash,
+                   if available { "available" } else { "unavailable" });
+            self.availability.insert(header_hash, available);
+        }
+    }
+
+
+    /// Promote the proposal chosen by `select_proposal` in each shard and period into the
+    /// stored collations. A promoted proposal is replaced when a better one arrives,
+    /// until the notary votes on it.
+    fn promote_proposals(&mut self) {
+        let mut shards_and_periods: Vec<(ShardIdHash, ChunkPeriodHash)> = self.proposals.iter()
+            .map(|proposal| (proposal.header.shard_id, proposal.header.period()))
+            .collect();
+        shards_and_periods.dedup();
+
+        for (shard_id, period) in shards_and_periods {
+            let proposals = self.proposals.get(shard_id, period);
+            let winner = match select_proposal(proposals, |proposal| self.vote_for(proposal)) {
+                Some(winner) => winner.clone(),
+                None => continue
+            };
+            let promoted: Vec<CollationHeaderHash> = self.collations.get(shard_id, period).iter()
+                .filter(|collation| proposals.iter().any(|proposal| proposal.header == collation.header))
+                .map(|collation| collation.header.hash())
+                .collect();
+            let winner_hash = winner.header.hash();
+            if promoted.contains(&winner_hash) || promoted.iter().any(|header_hash| self.voted.contains(header_hash)) {
+                continue;
             }
 
-            if self.selected {
-                self.get_availability();
-                self.submit_vote();
+            for header_hash in &promoted {
+                debug!("Notary id {} replaces promoted proposal {:?}", self.id, header_hash);
+                self.collations.remove(shard_id, period, header_hash);
             }
+            debug!("Notary id {} promotes proposal {:?} with bid {} in shard id {} and period {}",
+                   self.id, winner_hash, winner.header.proposer_bid(), shard_id, period);
+            self.collations.insert(winner);
+            self.evict_over_ceiling();
         }
     }
 
 
-    fn store_collation(&mut self, collation: Collation) {
-        debug!("Storing in notary id {} a new collation mapped to shard id {}", self.id, self.shard_id);
-        self.collation_vectors.entry(self.shard_id).or_insert(vec![]);
-        let vector = self.collation_vectors.get_mut(&self.shard_id).unwrap();
-        vector.push(collation);
+    /// Whether to vote for the collation: only if its body was found available.
+    fn vote_for(&self, collation: &Collation) -> bool {
+        self.availability(&collation.header.hash()).unwrap_or(false)
     }
 
 
-    fn store_proposal(&mut self, proposal: Collation) {
-        debug!("Storing in notary id {} a new proposal collation mapped to shard id {}", self.id, self.shard_id);
-        self.proposal_vectors.entry(self.shard_id).or_insert(vec![]);
-        let vector = self.proposal_vectors.get_mut(&self.shard_id).unwrap();
-        vector.push(proposal);
+    /// Select the next vote: in the first shard and period the notary hasn't voted in
+    /// yet, a vote for the sampled collation picked by `select_proposal`, e.g. the
+    /// promoted proposal, or that the first sampled collation is unavailable if none is
+    /// available. Votes are counted once per notary, shard and period, so the notary
+    /// votes on one collation in each.
+    fn select_vote(&self) -> Option<Vote> {
+        let mut shards_and_periods: Vec<(ShardIdHash, ChunkPeriodHash)> = self.collations.iter()
+            .map(|collation| (collation.header.shard_id, collation.header.period()))
+            .collect();
+        shards_and_periods.dedup();
+
+        for (shard_id, period) in shards_and_periods {
+            let collations = self.collations.get(shard_id, period);
+            if collations.iter().any(|collation| self.voted.contains(&collation.header.hash())) {
+                continue;
+            }
+            if let Some(collation) = select_proposal(collations, |collation| self.vote_for(collation)) {
+                return Some(Vote::for_header(&collation.header, self.id, true));
+            }
+            let first_sampled = collations.iter()
+                .find(|collation| self.availability.contains_key(&collation.header.hash()));
+            if let Some(collation) = first_sampled {
+                return Some(Vote::for_header(&collation.header, self.id, false));
+            }
+        }
+        None
     }
 
 
-    fn get_availability(&mut self) {}
+    /// Vote in each shard and period as selected by `select_vote`, counting the vote in
+    /// the notary's tally and sending it to the vote sender, if any.
+    fn submit_vote(&mut self) {
+        while let Some(vote) = self.select_vote() {
+            debug!("Notary id {} submits vote {:?}", self.id, vote);
+            self.voted.insert(vote.header_hash);
+            self.tally.add_vote(&vote);
+            if let Some(ref vote_sender) = self.vote_sender {
+                if vote_sender.send(Message::Vote { value: vote }).is_err() {
+                    warn!("Notary id {} failed to send vote, receiver hung up", self.id);
+                }
+            }
+        }
+    }
+
 
+    /// Count a vote from another notary.
+    fn receive_vote(&mut self, vote: Vote) {
+        debug!("Notary id {} received vote {:?}", self.id, vote);
+        self.tally.add_vote(&vote);
+    }
+}
 
-    fn submit_vote(&self) {}
+/// Choose between the proposals of a shard and period: of those passing `available`,
+/// the one with the highest bid, and of equal bids the one with the lowest header hash.
+pub fn select_proposal<F>(proposals: &[Collation], available: F) -> Option<&Collation>
+    where F: Fn(&Collation) -> bool {
+    proposals.iter()
+        .filter(|proposal| available(proposal))
+        .max_by(|a, b| {
+            a.header.proposer_bid().cmp(&b.header.proposer_bid())
+                .then_with(|| b.header.hash().cmp(&a.header.hash()))
+        })
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use modules::client_thread::merge_events;
+    use modules::message_log;
+    use modules::message_log::MessageRecorder;
+    use modules::collation::blob::Blob;
     use modules::collation::header;
     use modules::collation::body;
-    use modules::constants::{/* CHUNK_SIZE, */
+    use modules::collation::merkle;
+    use modules::constants::{CHUNK_SIZE,
         CHUNK_DATA_SIZE,
         /*COLLATION_SIZE, */
         CHUNKS_PER_COLLATION,
@@ -134,7 +620,7 @@ mod tests {
         let chunk_root = ChunkRootHash::zero();
         let period = ChunkPeriodHash::from_dec_str("0").unwrap();
         let proposer_address = ProposerAddress::zero();
-        let genesis_header = header::Header::new(shard_id, chunk_root, period, proposer_address);
+        let genesis_header = header::Header::new(shard_id, chunk_root, period, proposer_address, ProposerBidHash::zero());
         let chunk = Chunk::new(0x00, [0x00; CHUNK_DATA_SIZE]);
         let chunks = vec![chunk; CHUNKS_PER_COLLATION];
         Collation::new(
@@ -146,7 +632,7 @@ mod tests {
                           period: ChunkPeriodHash) -> Collation {
         let chunk_root = ChunkRootHash::zero();
         let proposer_address = ProposerAddress::zero();
-        let collation_header = header::Header::new(shard_id, chunk_root, period, proposer_address);
+        let collation_header = header::Header::new(shard_id, chunk_root, period, proposer_address, ProposerBidHash::zero());
         // refactor, duplication.
         let chunk = Chunk::new(0x00, [0x00; CHUNK_DATA_SIZE]);
         let chunks = vec![chunk; CHUNKS_PER_COLLATION];
@@ -156,10 +642,13 @@ mod tests {
         )
     }
 
+    /// A notary assigned to shard 0 in period 1
     fn generate_notary() -> Notary {
         let (_tx, rx) = mpsc::channel();
-        let (_mtx, mrx) = mpsc::channel();
-        Notary::new(rx, mrx)
+        let mut notary = Notary::new(rx);
+        notary.period = ChunkPeriodHash::from(1);
+        notary.assign_shard(ShardIdHash::zero());
+        notary
     }
 
     #[test]
@@ -181,16 +670,17 @@ mod tests {
         let first_collation_cmp = first_collation.clone();
 
         // Push genesis collation into notary
-        notary.store_collation(genesis_collation);
-        notary.store_collation(first_collation);
+        notary.period = ChunkPeriodHash::zero();
+        notary.store_collation(genesis_collation).unwrap();
+        notary.period = ChunkPeriodHash::from(1);
+        notary.store_collation(first_collation).unwrap();
 
         // Check that the operations succeded
-        let vector = notary.collation_vectors.get(
-            &ShardIdHash::from_dec_str("0").unwrap())
-            .unwrap();
-
-        assert_eq!(vector[0], genesis_collation_cmp);
-        assert_eq!(vector[1], first_collation_cmp);
+        let shard_id = ShardIdHash::from_dec_str("0").unwrap();
+        assert_eq!(notary.collations(shard_id, ChunkPeriodHash::from_dec_str("0").unwrap()),
+                   &[genesis_collation_cmp]);
+        assert_eq!(notary.collations(shard_id, ChunkPeriodHash::from_dec_str("1").unwrap()),
+                   &[first_collation_cmp]);
     }
 
     #[test]
@@ -205,25 +695,601 @@ mod tests {
         let proposal_cmp = proposal.clone();
 
         // Store proposal in notary
-        notary.store_proposal(proposal);
+        notary.store_proposal(proposal).unwrap();
 
         // Check that the operations succeeded
-        let vector = notary.proposal_vectors.get(
-            &ShardIdHash::from_dec_str("0").unwrap())
-            .unwrap();
+        assert_eq!(notary.proposals(ShardIdHash::from_dec_str("0").unwrap(),
+                                    ChunkPeriodHash::from_dec_str("1").unwrap()),
+                   &[proposal_cmp]);
+    }
 
-        assert_eq!(vector[0], proposal_cmp);
+    #[test]
+    fn it_rejects_collations_of_unassigned_shards() {
+        let mut notary = generate_notary();
+        let shard_id = ShardIdHash::from_dec_str("1").unwrap();
+        let period = ChunkPeriodHash::from_dec_str("1").unwrap();
+        match notary.store_collation(generate_collation(shard_id, period)) {
+            Err(Error(ErrorKind::ShardNotAssigned(rejected), _)) => assert_eq!(rejected, shard_id),
+            result => panic!("Expected shard not assigned error, got {:?}", result)
+        }
+        match notary.store_proposal(generate_collation(shard_id, period)) {
+            Err(Error(ErrorKind::ShardNotAssigned(rejected), _)) => assert_eq!(rejected, shard_id),
+            result => panic!("Expected shard not assigned error, got {:?}", result)
+        }
+        assert!(notary.collations(shard_id, period).is_empty());
+        assert!(notary.proposals(shard_id, period).is_empty());
+    }
+
+    #[test]
+    fn it_stores_interleaved_collations_by_their_shard() {
+        let (_tx, rx) = mpsc::channel();
+        let mut notary = Notary::new(rx);
+        let (vote_sender, vote_receiver) = mpsc::channel();
+        notary.set_vote_sender(vote_sender);
+        let shard = |id: u64| ShardIdHash::from(id);
+        let period = ChunkPeriodHash::from_dec_str("1").unwrap();
+        let first_collation = generate_available_collation(shard(1), period);
+        let second_collation = generate_available_collation(shard(2), period);
+        let third_collation = generate_collation(shard(1), period);
+        let unassigned_collation = generate_available_collation(shard(3), period);
+
+        for message in vec![Message::PeriodStart { value: period },
+                            Message::ShardId { value: shard(1) },
+                            Message::ShardId { value: shard(2) },
+                            Message::Selected { value: true },
+                            Message::Collation { value: second_collation.clone() },
+                            Message::Collation { value: first_collation.clone() },
+                            Message::Collation { value: unassigned_collation.clone() },
+                            Message::Proposal { value: third_collation.clone() },
+                            Message::Collation { value: third_collation.clone() }] {
+            notary.handle_message(message);
+            notary.act();
+        }
+        assert_eq!(notary.collations(shard(1), period), &[first_collation.clone(), third_collation.clone()]);
+        assert_eq!(notary.collations(shard(2), period).to_vec(), vec![second_collation.clone()]);
+        assert!(notary.collations(shard(3), period).is_empty());
+        assert_eq!(notary.proposals(shard(1), period).to_vec(), vec![third_collation.clone()]);
+
+        // The notary votes once in each shard, as it receives the first collation
+        let votes: Vec<Message> = vote_receiver.try_iter().collect();
+        assert_eq!(votes, vec![Message::Vote { value: Vote::for_header(&second_collation.header, notary.id, true) },
+                               Message::Vote { value: Vote::for_header(&first_collation.header, notary.id, true) }]);
+
+        // Assignments only last until the next period, and only cover its collations
+        let next_period = ChunkPeriodHash::from_dec_str("2").unwrap();
+        let next_collation = generate_available_collation(shard(3), next_period);
+        notary.handle_message(Message::PeriodStart { value: next_period });
+        notary.handle_message(Message::ShardId { value: shard(3) });
+        notary.handle_message(Message::Collation { value: generate_available_collation(shard(1), next_period) });
+        notary.handle_message(Message::Collation { value: unassigned_collation.clone() });
+        notary.handle_message(Message::Collation { value: next_collation.clone() });
+        assert!(notary.collations(shard(1), next_period).is_empty());
+        assert!(notary.collations(shard(3), period).is_empty());
+        assert_eq!(notary.collations(shard(3), next_period), &[next_collation]);
+        assert_eq!(notary.collations(shard(1), period).len(), 2);
+        match notary.store_collation(unassigned_collation) {
+            Err(Error(ErrorKind::WrongPeriod(rejected, current), _)) => {
+                assert_eq!((rejected, current), (period, next_period));
+            },
+            result => panic!("Expected wrong period error, got {:?}", result)
+        }
+    }
+
+    #[test]
+    fn it_drops_proposals_and_collations_of_past_periods() {
+        let mut notary = generate_notary();
+        notary.set_collation_lookback(1);
+        let shard_id = ShardIdHash::zero();
+        let period = |period: u64| ChunkPeriodHash::from(period);
+        let old_collation = generate_available_collation(shard_id, period(1));
+        let recent_collation = generate_available_collation(shard_id, period(2));
+        notary.store_collation(old_collation.clone()).unwrap();
+        notary.period = period(2);
+        notary.store_collation(recent_collation.clone()).unwrap();
+        notary.store_proposal(generate_collation(shard_id, period(2))).unwrap();
+        notary.get_availability();
+
+        notary.handle_message(Message::PeriodStart { value: period(3) });
+        assert!(notary.collations(shard_id, period(1)).is_empty());
+        assert_eq!(notary.collations(shard_id, period(2)).to_vec(), vec![recent_collation.clone()]);
+        assert!(notary.proposals(shard_id, period(2)).is_empty());
+        assert_eq!(notary.availability(&old_collation.header.hash()), None);
+        assert_eq!(notary.availability(&recent_collation.header.hash()), Some(true));
+
+        // The body of a dropped collation is no longer served for sampling
+        notary.availability.clear();
+        notary.collations.insert(old_collation.clone());
+        notary.get_availability();
+        assert_eq!(notary.availability(&old_collation.header.hash()), Some(false));
+    }
+
+    #[test]
+    fn it_evicts_the_oldest_bodies_over_the_memory_ceiling() {
+        let mut notary = generate_notary();
+        // Only the stored bodies count without the default chunk source
+        notary.set_chunk_source(Box::new(NoChunks));
+        let collation_size = CHUNKS_PER_COLLATION * CHUNK_SIZE;
+        notary.set_max_stored_bytes(2 * collation_size);
+        let shard_id = ShardIdHash::zero();
+        let period = |period: u64| ChunkPeriodHash::from(period);
+        notary.store_collation(generate_collation(shard_id, period(1))).unwrap();
+        notary.store_proposal(generate_collation(shard_id, period(1))).unwrap();
+
+        // Of the same period, the proposal goes first
+        notary.period = period(2);
+        notary.store_collation(generate_collation(shard_id, period(2))).unwrap();
+        assert_eq!(notary.collations(shard_id, period(1)).len(), 1);
+        assert!(notary.proposals(shard_id, period(1)).is_empty());
+
+        notary.store_proposal(generate_collation(shard_id, period(2))).unwrap();
+        assert!(notary.collations(shard_id, period(1)).is_empty());
+        assert_eq!(notary.collations(shard_id, period(2)).len(), 1);
+        assert_eq!(notary.proposals(shard_id, period(2)).len(), 1);
+        assert_eq!(notary.stored_bytes(), 2 * collation_size);
+    }
+
+    #[test]
+    fn it_counts_the_chunk_source_towards_the_memory_ceiling() {
+        let mut notary = generate_notary();
+        let first = generate_proposal(1, 5, true);
+        let second = generate_proposal(2, 9, true);
+        notary.store_collation(first.clone()).unwrap();
+        let kept_bytes = notary.stored_bytes();
+        assert!(kept_bytes > first.body.size());
+
+        // Storing the second body evicts the first from the store and the chunk source
+        notary.set_max_stored_bytes(2 * kept_bytes - 1);
+        notary.store_collation(second.clone()).unwrap();
+        assert_eq!(notary.collations(ShardIdHash::zero(), ChunkPeriodHash::from(1)), &[second]);
+        assert_eq!(notary.stored_bytes(), kept_bytes);
+        assert!(notary.chunk_source.fetch_chunk(&first.header.hash(), 0).is_none());
+    }
+
+    struct NoChunks;
+
+    impl ChunkSource for NoChunks {
+        fn fetch_chunk(&self, header_hash: &CollationHeaderHash, index: usize)
+                       -> Option<(Chunk, merkle::MerkleProof)> {
+            None
+        }
+    }
+
+    fn generate_available_collation(shard_id: ShardIdHash,
+                                    period: ChunkPeriodHash) -> Collation {
+        let body = Blob::new(vec![0xab; 1000]).blob_to_collation_body().unwrap();
+        let collation_header = header::Header::new(shard_id, body.chunk_root(), period, ProposerAddress::zero(),
+                                                   ProposerBidHash::zero());
+        Collation::new(collation_header, body)
+    }
+
+    #[test]
+    fn it_finds_received_collation_available() {
+        let mut notary = generate_notary();
+        let collation = generate_available_collation(
+            ShardIdHash::from_dec_str("0").unwrap(),
+            ChunkPeriodHash::from_dec_str("1").unwrap()
+        );
+        let header_hash = collation.header.hash();
+        assert_eq!(notary.availability(&header_hash), None);
+
+        notary.store_collation(collation.clone()).unwrap();
+        notary.get_availability();
+
+        assert_eq!(notary.availability(&header_hash), Some(true));
+        assert!(notary.vote_for(&collation));
+    }
+
+    #[test]
+    fn it_finds_collation_not_matching_chunk_root_unavailable() {
+        let mut notary = generate_notary();
+        // The chunk root of the generated collation is zero, not the root of its body.
+        let collation = generate_collation(
+            ShardIdHash::from_dec_str("0").unwrap(),
+            ChunkPeriodHash::from_dec_str("1").unwrap()
+        );
+        notary.store_collation(collation.clone()).unwrap();
+        notary.get_availability();
+
+        assert_eq!(notary.availability(&collation.header.hash()), Some(false));
+        assert!(!notary.vote_for(&collation));
+    }
+
+    #[test]
+    fn it_finds_collation_unavailable_from_chunk_source() {
+        let mut notary = generate_notary();
+        notary.set_chunk_source(Box::new(NoChunks));
+        notary.set_availability_samples(1);
+        let collation = generate_available_collation(
+            ShardIdHash::from_dec_str("0").unwrap(),
+            ChunkPeriodHash::from_dec_str("1").unwrap()
+        );
+        notary.store_collation(collation.clone()).unwrap();
+        notary.get_availability();
+
+        assert_eq!(notary.availability(&collation.header.hash()), Some(false));
+        assert!(!notary.vote_for(&collation));
+    }
+
+    /// A proposal in shard 0 and period 1, with a body that is available if its chunk
+    /// root matches
+    fn generate_proposal(proposer: u8, bid: u64, available: bool) -> Collation {
+        let body = Blob::new(vec![proposer; 1000]).blob_to_collation_body().unwrap();
+        let chunk_root = if available { body.chunk_root() } else { ChunkRootHash::zero() };
+        let proposal_header = header::Header::new(ShardIdHash::zero(), chunk_root, ChunkPeriodHash::from(1),
+                                                  ProposerAddress::from([proposer; 20]), ProposerBidHash::from(bid));
+        Collation::new(proposal_header, body)
+    }
+
+    fn matches_chunk_root(proposal: &Collation) -> bool {
+        proposal.header.chunk_root() == proposal.body.chunk_root()
+    }
+
+    #[test]
+    fn it_selects_the_proposal_with_the_highest_bid() {
+        let proposals = vec![generate_proposal(1, 5, true),
+                             generate_proposal(2, 9, true),
+                             generate_proposal(3, 7, true)];
+        assert_eq!(select_proposal(&proposals, matches_chunk_root), Some(&proposals[1]));
+    }
+
+    #[test]
+    fn it_selects_the_lowest_header_hash_of_equal_bids() {
+        let proposals = vec![generate_proposal(1, 5, true),
+                             generate_proposal(2, 5, true),
+                             generate_proposal(3, 5, true)];
+        let lowest = proposals.iter().min_by_key(|proposal| proposal.header.hash()).unwrap();
+        assert_eq!(select_proposal(&proposals, matches_chunk_root), Some(lowest));
+    }
+
+    #[test]
+    fn it_selects_only_available_proposals() {
+        let proposals = vec![generate_proposal(1, 9, false),
+                             generate_proposal(2, 5, true),
+                             generate_proposal(3, 7, false)];
+        assert_eq!(select_proposal(&proposals, matches_chunk_root), Some(&proposals[1]));
+        assert_eq!(select_proposal(&proposals[..1], matches_chunk_root), None);
+        assert_eq!(select_proposal(&[], matches_chunk_root), None);
+    }
+
+    #[test]
+    fn it_promotes_the_winning_proposal_until_voting() {
+        let mut notary = generate_notary();
+        let shard_id = ShardIdHash::zero();
+        let period = ChunkPeriodHash::from(1);
+        let low_bid = generate_proposal(1, 5, true);
+        let unavailable_high_bid = generate_proposal(2, 9, false);
+        let better_bid = generate_proposal(3, 7, true);
+        let late_bid = generate_proposal(4, 20, true);
+
+        notary.store_proposal(low_bid.clone()).unwrap();
+        notary.store_proposal(unavailable_high_bid.clone()).unwrap();
+        notary.get_availability();
+        notary.promote_proposals();
+        assert_eq!(notary.collations(shard_id, period).to_vec(), vec![low_bid.clone()]);
+
+        // A better proposal replaces the promoted one before the notary voted
+        notary.store_proposal(better_bid.clone()).unwrap();
+        notary.get_availability();
+        notary.promote_proposals();
+        assert_eq!(notary.collations(shard_id, period).to_vec(), vec![better_bid.clone()]);
+        assert_eq!(notary.availability(&low_bid.header.hash()), Some(true));
+
+        // Once voted on, the promoted proposal stays
+        notary.submit_vote();
+        assert!(notary.voted.contains(&better_bid.header.hash()));
+        notary.store_proposal(late_bid.clone()).unwrap();
+        notary.get_availability();
+        notary.promote_proposals();
+        assert_eq!(notary.collations(shard_id, period).to_vec(), vec![better_bid.clone()]);
+        assert_eq!(notary.proposals(shard_id, period),
+                   &[low_bid, unavailable_high_bid, better_bid, late_bid]);
+    }
+
+    /// A valid collation in shard 0 and period 1, to commit to and reveal
+    fn generate_revealed_collation(proposer: u8, bid: u64) -> Collation {
+        let mut builder = body::BodyBuilder::new();
+        builder.add_blob(Blob::new(vec![proposer; 100])).unwrap();
+        let body = builder.build_padded();
+        let collation_header = header::Header::new(ShardIdHash::zero(), body.chunk_root(), ChunkPeriodHash::from(1),
+                                                   ProposerAddress::from([proposer; 20]), ProposerBidHash::from(bid));
+        Collation::new(collation_header, body)
+    }
+
+    #[test]
+    fn it_chooses_the_best_commitment_and_stores_its_reveal() {
+        let mut notary = generate_notary();
+        notary.selected = true;
+        let (chosen_sender, chosen_receiver) = mpsc::channel();
+        notary.set_chosen_sender(chosen_sender);
+        let shard_id = ShardIdHash::zero();
+        let period = ChunkPeriodHash::from(1);
+        let low_bid = generate_revealed_collation(1, 5);
+        let high_bid = generate_revealed_collation(2, 9);
+        let late_bid = generate_revealed_collation(3, 20);
+        let commit = |collation: &Collation| Message::Commitment { value: Commitment::for_header(&collation.header) };
+
+        notary.handle_message(commit(&low_bid));
+        notary.handle_message(commit(&high_bid));
+        notary.act();
+        notary.act();
+        assert_eq!(chosen_receiver.try_iter().collect::<Vec<Message>>(),
+                   vec![Message::Chosen { value: Commitment::for_header(&high_bid.header) }]);
+        assert_eq!(notary.chosen(shard_id, period), Some(&Commitment::for_header(&high_bid.header)));
+
+        // Once sent, the choice stays, even when a better commitment arrives
+        notary.handle_message(commit(&late_bid));
+        notary.act();
+        assert!(chosen_receiver.try_recv().is_err());
+        assert_eq!(notary.chosen(shard_id, period), Some(&Commitment::for_header(&high_bid.header)));
+
+        // Only the chosen collation is accepted, with the committed bid, which is part of
+        // its header hash
+        let mut other_bid = high_bid.clone();
+        other_bid.header = header::Header::new(shard_id, high_bid.header.chunk_root(), period,
+                                               high_bid.header.proposer_address(), ProposerBidHash::from(1));
+        for rejected in &[low_bid.clone(), other_bid] {
+            match notary.receive_reveal(rejected.clone()) {
+                Err(Error(ErrorKind::UnknownCommitment(header_hash), _)) => {
+                    assert_eq!(header_hash, rejected.header.hash());
+                },
+                result => panic!("Expected unknown commitment error, got {:?}", result)
+            }
+        }
+        notary.handle_message(Message::Reveal { value: high_bid.clone() });
+        assert_eq!(notary.collations(shard_id, period).to_vec(), vec![high_bid.clone()]);
+        notary.act();
+        assert_eq!(notary.availability(&high_bid.header.hash()), Some(true));
+
+        notary.handle_message(Message::PeriodStart { value: ChunkPeriodHash::from(2) });
+        assert_eq!(notary.chosen(shard_id, period), None);
+        assert!(notary.penalties().proposers().next().is_none());
+    }
+
+    #[test]
+    fn it_penalizes_chosen_proposers_that_never_reveal() {
+        let mut notary = generate_notary();
+        notary.selected = true;
+        let collation = generate_revealed_collation(1, 5);
+        let commitment = Commitment::for_header(&collation.header);
+        notary.handle_message(Message::Commitment { value: commitment.clone() });
+        notary.act();
+        // Adding the header without revealing the body doesn't help
+        notary.handle_message(Message::Header { value: collation.header.clone() });
+
+        notary.handle_message(Message::PeriodStart { value: ChunkPeriodHash::from(2) });
+        assert_eq!(notary.penalties().missed_reveals(&ProposerAddress::from([1; 20])), &[commitment]);
+        assert!(notary.commitments.is_empty());
+        assert!(notary.added_headers.is_empty());
+
+        // A late reveal isn't accepted anymore
+        notary.assign_shard(ShardIdHash::zero());
+        match notary.receive_reveal(collation) {
+            Err(Error(ErrorKind::UnknownCommitment(_), _)) => {},
+            result => panic!("Expected unknown commitment error, got {:?}", result)
+        }
+    }
+
+    #[test]
+    fn it_leaves_slots_filled_by_other_proposers_alone() {
+        let chosen = generate_revealed_collation(1, 5);
+        let other = generate_revealed_collation(2, 9);
+        let commitment = Message::Commitment { value: Commitment::for_header(&chosen.header) };
+        let other_header = Message::Header { value: other.header.clone() };
+
+        // Another proposer's header took the slot, so the chosen one can't add its own
+        let mut notary = generate_notary();
+        notary.selected = true;
+        notary.handle_message(commitment.clone());
+        notary.act();
+        assert!(notary.chosen(ShardIdHash::zero(), ChunkPeriodHash::from(1)).is_some());
+        notary.handle_message(other_header.clone());
+        notary.handle_message(Message::PeriodStart { value: ChunkPeriodHash::from(2) });
+        assert!(notary.penalties().proposers().next().is_none());
+
+        // Nothing is chosen for a slot that is filled already
+        let mut notary = generate_notary();
+        notary.selected = true;
+        let (chosen_sender, chosen_receiver) = mpsc::channel();
+        notary.set_chosen_sender(chosen_sender);
+        notary.handle_message(other_header);
+        notary.handle_message(commitment);
+        notary.act();
+        assert!(chosen_receiver.try_recv().is_err());
+        assert_eq!(notary.chosen(ShardIdHash::zero(), ChunkPeriodHash::from(1)), None);
     }
 
     #[test]
-    #[ignore]
     fn it_selects_vote() {
-        assert!(false);
+        let mut notary = generate_notary();
+        let shard_id = ShardIdHash::from_dec_str("0").unwrap();
+        let available_collation = generate_available_collation(
+            shard_id, ChunkPeriodHash::from_dec_str("1").unwrap());
+        let unavailable_collation = generate_collation(
+            shard_id, ChunkPeriodHash::from_dec_str("2").unwrap());
+
+        // Nothing to vote on before the collations are sampled.
+        notary.store_collation(available_collation.clone()).unwrap();
+        assert_eq!(notary.select_vote(), None);
+
+        notary.get_availability();
+        notary.period = ChunkPeriodHash::from(2);
+        notary.store_collation(unavailable_collation.clone()).unwrap();
+        assert_eq!(notary.select_vote(),
+                   Some(Vote::for_header(&available_collation.header, notary.id, true)));
+
+        notary.voted.insert(available_collation.header.hash());
+        assert_eq!(notary.select_vote(), None);
+        notary.get_availability();
+        assert_eq!(notary.select_vote(),
+                   Some(Vote::for_header(&unavailable_collation.header, notary.id, false)));
+    }
+
+    #[test]
+    fn it_votes_once_per_shard_and_period() {
+        let mut notary = generate_notary();
+        let shard_id = ShardIdHash::zero();
+        let period = ChunkPeriodHash::from(1);
+        let unavailable_collation = generate_collation(shard_id, period);
+        let low_bid = generate_proposal(1, 5, true);
+        let high_bid = generate_proposal(2, 9, true);
+        for collation in &[unavailable_collation.clone(), low_bid.clone(), high_bid.clone()] {
+            notary.store_collation(collation.clone()).unwrap();
+        }
+        notary.get_availability();
+
+        // The best available collation is voted on
+        assert_eq!(notary.select_vote(), Some(Vote::for_header(&high_bid.header, notary.id, true)));
+        notary.submit_vote();
+        assert_eq!(notary.select_vote(), None);
+        assert_eq!(notary.tally().vote_count(shard_id, period, &high_bid.header.hash()), 1);
+
+        // Without available collations, the notary votes that the first is unavailable
+        let mut notary = generate_notary();
+        notary.store_collation(unavailable_collation.clone()).unwrap();
+        notary.store_collation(generate_proposal(1, 5, false)).unwrap();
+        notary.get_availability();
+        assert_eq!(notary.select_vote(), Some(Vote::for_header(&unavailable_collation.header, notary.id, false)));
     }
 
     #[test]
-    #[ignore]
     fn it_submits_vote() {
-        assert!(false);
+        let mut notary = generate_notary();
+        let (vote_sender, vote_receiver) = mpsc::channel();
+        notary.set_vote_sender(vote_sender);
+        let shard_id = ShardIdHash::from_dec_str("0").unwrap();
+        let period = ChunkPeriodHash::from_dec_str("1").unwrap();
+        let collation = generate_available_collation(shard_id, period);
+        let header_hash = collation.header.hash();
+        notary.store_collation(collation.clone()).unwrap();
+        notary.get_availability();
+
+        notary.submit_vote();
+        match vote_receiver.try_recv() {
+            Ok(Message::Vote { value }) => {
+                assert_eq!(value, Vote::new(shard_id, period, header_hash, notary.id, true));
+            },
+            result => panic!("Expected a vote, got {:?}", result)
+        }
+        assert_eq!(notary.tally().vote_count(shard_id, period, &header_hash), 1);
+
+        // The notary only votes once on each collation.
+        notary.submit_vote();
+        assert!(vote_receiver.try_recv().is_err());
+        assert_eq!(notary.tally().vote_count(shard_id, period, &header_hash), 1);
+    }
+
+    #[test]
+    fn it_counts_received_votes() {
+        let mut notary = generate_notary();
+        let shard_id = ShardIdHash::from_dec_str("0").unwrap();
+        let period = ChunkPeriodHash::from_dec_str("1").unwrap();
+        let header_hash = CollationHeaderHash::from([1; 32]);
+        for notary_id in 1..QUORUM_SIZE as u64 + 1 {
+            let vote = Vote::new(shard_id, period, header_hash, NotaryIdHash::from(notary_id), true);
+            notary.receive_vote(vote);
+        }
+        assert_eq!(notary.tally().vote_count(shard_id, period, &header_hash), QUORUM_SIZE);
+        assert_eq!(notary.tally().notarized(shard_id, period), Some(header_hash));
+    }
+
+    #[test]
+    fn it_replays_recorded_messages() {
+        let shard_id = ShardIdHash::from_dec_str("0").unwrap();
+        let period = ChunkPeriodHash::from_dec_str("1").unwrap();
+        let collation = generate_available_collation(shard_id, period);
+        let other_vote = Vote::for_header(&collation.header, NotaryIdHash::from(7), true);
+        let mut recorder = MessageRecorder::new(vec![]);
+        for message in &[Message::PeriodStart { value: period },
+                         Message::ShardId { value: shard_id },
+                         Message::Selected { value: true },
+                         Message::Collation { value: collation.clone() },
+                         Message::Vote { value: other_vote }] {
+            recorder.record(message).unwrap();
+        }
+        let log = message_log::read_message_log(&recorder.into_inner()[..]).unwrap();
+
+        let replay = |log: &[RecordedMessage]| {
+            let mut notary = generate_notary();
+            notary.set_rng_seed([1, 2, 3, 4]);
+            let (vote_sender, vote_receiver) = mpsc::channel();
+            notary.set_vote_sender(vote_sender);
+            notary.replay(log);
+            (notary.availability(&collation.header.hash()), vote_receiver.try_iter().collect::<Vec<Message>>(),
+             notary.tally().vote_count(shard_id, period, &collation.header.hash()))
+        };
+        let (availability, votes, vote_count) = replay(&log);
+        assert_eq!(availability, Some(true));
+        assert_eq!(votes, vec![Message::Vote { value: Vote::for_header(&collation.header, NotaryIdHash::zero(), true) }]);
+        assert_eq!(vote_count, 2);
+
+        // Replaying the same log gives the same result
+        assert_eq!(replay(&log), (availability, votes, vote_count));
+    }
+
+    #[test]
+    fn it_acts_on_the_events_it_runs_on() {
+        let (smc_sender, smc_receiver) = mpsc::channel();
+        let (manager_sender, manager_receiver) = mpsc::channel();
+        let (vote_sender, vote_receiver) = mpsc::channel();
+        let mut notary = Notary::new(merge_events(smc_receiver, manager_receiver));
+        notary.set_vote_sender(vote_sender);
+        let handle = thread::spawn(move || notary.run());
+
+        let shard_id = ShardIdHash::from_dec_str("0").unwrap();
+        let period = ChunkPeriodHash::from_dec_str("1").unwrap();
+        let collation = generate_available_collation(shard_id, period);
+        smc_sender.send(Message::PeriodStart { value: period }).unwrap();
+        smc_sender.send(Message::ShardId { value: shard_id }).unwrap();
+        smc_sender.send(Message::Selected { value: true }).unwrap();
+        smc_sender.send(Message::Collation { value: collation.clone() }).unwrap();
+
+        // The notary acts on each message, and then stops once everyone hung up
+        drop(smc_sender);
+        drop(manager_sender);
+        handle.join().unwrap();
+        match vote_receiver.try_recv() {
+            Ok(Message::Vote { value }) => assert_eq!(value.header_hash, collation.header.hash()),
+            result => panic!("Expected a vote, got {:?}", result)
+        }
+    }
+
+    #[test]
+    fn it_stops_when_everyone_hung_up() {
+        let (smc_sender, smc_receiver) = mpsc::channel();
+        let (manager_sender, manager_receiver) = mpsc::channel::<Command>();
+        let mut notary = Notary::new(merge_events(smc_receiver, manager_receiver));
+        let handle = thread::spawn(move || notary.run());
+        drop(smc_sender);
+        drop(manager_sender);
+        handle.join().unwrap();
+    }
+
+    /// The user and system CPU time of the current thread, in clock ticks
+    #[cfg(target_os = "linux")]
+    fn thread_cpu_ticks() -> u64 {
+        let stat = ::std::fs::read_to_string("/proc/thread-self/stat").unwrap();
+        // The fields after the parenthesized command name, from the state onwards
+        let fields: Vec<&str> = stat[stat.rfind(')').unwrap() + 2..].split(' ').collect();
+        fields[11].parse::<u64>().unwrap() + fields[12].parse::<u64>().unwrap()
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn it_uses_no_cpu_while_idle() {
+        let (_smc_sender, smc_receiver) = mpsc::channel();
+        let (manager_sender, manager_receiver) = mpsc::channel();
+        let mut notary = Notary::new(merge_events(smc_receiver, manager_receiver));
+        notary.set_tick_interval(Duration::from_millis(50));
+        let handle = thread::spawn(move || {
+            let start_ticks = thread_cpu_ticks();
+            notary.run();
+            thread_cpu_ticks() - start_ticks
+        });
+
+        thread::sleep(Duration::from_millis(1000));
+        manager_sender.send(Command::Terminate).unwrap();
+        // A busy loop would use about 100 ticks of CPU time in a second
+        let cpu_ticks = handle.join().unwrap();
+        assert!(cpu_ticks < 10, "Idle notary used {} ticks of CPU time", cpu_ticks);
     }
 }
@@ -0,0 +1,198 @@
+use modules::constants::PERIOD_LENGTH;
+use modules::errors::*;
+use modules::message::Message;
+use modules::primitives::ChunkPeriodHash;
+use modules::smc::rpc::JsonRpcClient;
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+/// The progress of the main chain, in blocks.
+pub trait TimeSource {
+    /// The current block number of the main chain, or an estimate of it
+    fn now(&self) -> Result<u64>;
+}
+
+impl<T: TimeSource + ?Sized> TimeSource for Arc<T> {
+    fn now(&self) -> Result<u64> {
+        (**self).now()
+    }
+}
+
+/// The latest block number of an Ethereum node
+impl TimeSource for JsonRpcClient {
+    fn now(&self) -> Result<u64> {
+        self.block_number()
+    }
+}
+
+/// Estimates the block number from the time since the clock was created, for local
+/// networks without a main chain.
+pub struct WallClock {
+    start: Instant,
+    block_time: Duration
+}
+
+impl WallClock {
+    pub fn new(block_time: Duration) -> WallClock {
+        WallClock {
+            start: Instant::now(),
+            block_time
+        }
+    }
+}
+
+impl TimeSource for WallClock {
+    fn now(&self) -> Result<u64> {
+        Ok((self.start.elapsed().as_nanos() / self.block_time.as_nanos().max(1)) as u64)
+    }
+}
+
+/// A block number that only changes when it's set, so tests can step through periods.
+///
+/// Clones share the same block number.
+#[derive(Clone, Default, Debug)]
+pub struct MockTimeSource {
+    block_number: Arc<AtomicU64>
+}
+
+impl MockTimeSource {
+    pub fn new() -> MockTimeSource {
+        MockTimeSource::default()
+    }
+
+    pub fn set_block_number(&self, block_number: u64) {
+        self.block_number.store(block_number, Ordering::SeqCst);
+    }
+
+    /// Move forward by a number of blocks and return the new block number
+    pub fn advance_blocks(&self, blocks: u64) -> u64 {
+        self.block_number.fetch_add(blocks, Ordering::SeqCst) + blocks
+    }
+}
+
+impl TimeSource for MockTimeSource {
+    fn now(&self) -> Result<u64> {
+        Ok(self.block_number.load(Ordering::SeqCst))
+    }
+}
+
+/// Turns the block numbers of a time source into periods of `PERIOD_LENGTH` blocks,
+/// and tells when a new period starts.
+pub struct PeriodClock {
+    time_source: Box<dyn TimeSource + Send>,
+    period_length: u64,
+    // The period of the last poll
+    period: Option<ChunkPeriodHash>
+}
+
+impl PeriodClock {
+    pub fn new(time_source: Box<dyn TimeSource + Send>) -> PeriodClock {
+        PeriodClock {
+            time_source,
+            period_length: PERIOD_LENGTH,
+            period: None
+        }
+    }
+
+    /// Sets the number of blocks in a period, which must match the SMC's
+    pub fn set_period_length(&mut self, period_length: u64) {
+        assert!(period_length > 0, "A period must have at least one block");
+        self.period_length = period_length;
+    }
+
+    pub fn period_length(&self) -> u64 {
+        self.period_length
+    }
+
+    /// The period that a block is in
+    pub fn period_of(&self, block_number: u64) -> ChunkPeriodHash {
+        ChunkPeriodHash::from(block_number / self.period_length)
+    }
+
+    /// The first block of a period
+    pub fn first_block(&self, period: ChunkPeriodHash) -> u64 {
+        period.low_u64() * self.period_length
+    }
+
+    pub fn current_period(&self) -> Result<ChunkPeriodHash> {
+        Ok(self.period_of(self.time_source.now()?))
+    }
+
+    /// The period of the last poll, if any
+    pub fn period(&self) -> Option<ChunkPeriodHash> {
+        self.period
+    }
+
+    /// Checks the time source, and gives a `PeriodStart` message if a period started
+    /// since the last poll. The first poll always gives one.
+    pub fn poll(&mut self) -> Result<Option<Message>> {
+        let period = self.current_period()?;
+        if self.period == Some(period) {
+            return Ok(None);
+        }
+        self.period = Some(period);
+        Ok(Some(Message::PeriodStart { value: period }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn generate_period_clock() -> (PeriodClock, MockTimeSource) {
+        let time = MockTimeSource::new();
+        (PeriodClock::new(Box::new(time.clone())), time)
+    }
+
+    fn period_start(message: Option<Message>) -> Option<ChunkPeriodHash> {
+        match message {
+            Some(Message::PeriodStart { value }) => Some(value),
+            None => None,
+            message => panic!("Expected period start, got {:?}", message)
+        }
+    }
+
+    #[test]
+    fn it_turns_block_numbers_into_periods() {
+        let (mut clock, _) = generate_period_clock();
+        assert_eq!(clock.period_of(0), ChunkPeriodHash::zero());
+        assert_eq!(clock.period_of(PERIOD_LENGTH - 1), ChunkPeriodHash::zero());
+        assert_eq!(clock.period_of(PERIOD_LENGTH), ChunkPeriodHash::from(1));
+        assert_eq!(clock.first_block(ChunkPeriodHash::from(3)), 3 * PERIOD_LENGTH);
+
+        clock.set_period_length(100);
+        assert_eq!(clock.period_length(), 100);
+        assert_eq!(clock.period_of(250), ChunkPeriodHash::from(2));
+        assert_eq!(clock.first_block(ChunkPeriodHash::from(2)), 200);
+    }
+
+    #[test]
+    fn it_steps_through_periods() {
+        let (mut clock, time) = generate_period_clock();
+        assert_eq!(clock.period(), None);
+        assert_eq!(period_start(clock.poll().unwrap()), Some(ChunkPeriodHash::zero()));
+        assert_eq!(period_start(clock.poll().unwrap()), None);
+
+        time.advance_blocks(PERIOD_LENGTH - 1);
+        assert_eq!(period_start(clock.poll().unwrap()), None);
+        time.advance_blocks(1);
+        assert_eq!(period_start(clock.poll().unwrap()), Some(ChunkPeriodHash::from(1)));
+
+        // Periods that pass between polls are skipped
+        time.set_block_number(10 * PERIOD_LENGTH);
+        assert_eq!(period_start(clock.poll().unwrap()), Some(ChunkPeriodHash::from(10)));
+        assert_eq!(clock.period(), Some(ChunkPeriodHash::from(10)));
+    }
+
+    #[test]
+    fn it_estimates_block_numbers_from_wall_clock() {
+        let clock = WallClock::new(Duration::from_secs(3600));
+        assert_eq!(clock.now().unwrap(), 0);
+
+        let clock = WallClock::new(Duration::from_millis(1));
+        ::std::thread::sleep(Duration::from_millis(5));
+        assert!(clock.now().unwrap() >= 5);
+    }
+}
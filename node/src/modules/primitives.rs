@@ -17,4 +17,9 @@ pub type ParentCollationHeaderHash = ethereum_types::H256;
 pub type ProposerBidHash = ethereum_types::U256;
 pub type ProposerSignature = ethereum_types::Signature;
 
+pub type BlobHash = ethereum_types::H256;
+
+/// An amount of ether in wei, e.g. a deposit
+pub type Wei = ethereum_types::U256;
+
 //pub type BinaryU8 = modules::collation::chunk::Binaryu8;
\ No newline at end of file
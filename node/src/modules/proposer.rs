@@ -1,27 +1,519 @@
-use modules::primitives::ProposerIdHash;
+use modules::bidding::BidStrategy;
+use modules::blob_pool::BlobPool;
+use modules::client_thread::{Command, Event};
+use modules::collation::blob::Blob;
+use modules::collation::body::BodyBuilder;
+use modules::collation::collation::Collation;
+use modules::collation::header::Header;
+use modules::commitment::Commitment;
+use modules::constants::{CHUNKS_PER_COLLATION, PROPOSER_DEPOSIT};
+use modules::errors::*;
+use modules::message::Message;
+use modules::primitives::{
+    ShardIdHash,
+    ChunkPeriodHash,
+    ProposerIdHash,
+    ProposerAddress,
+    ProposerBidHash,
+    BlobHash,
+    Wei
+};
+use modules::smc::backend::SmcBackend;
+
+use std::sync::{mpsc, Arc, Mutex, MutexGuard};
+use std::thread;
 
 pub struct Proposer {
-    id: ProposerIdHash
+    id: ProposerIdHash,
+    address: ProposerAddress,
+    // The shard the proposer adds collations to
+    shard_id: ShardIdHash,
+    backend: Box<dyn SmcBackend + Send>,
+    registered: bool,
+    // Decides the bid for each collation from the fees of its blobs
+    bid_strategy: BidStrategy,
+    // Blobs waiting to be put into a collation, shared with whoever submits them
+    blob_pool: Arc<Mutex<BlobPool>>,
+    // The collation committed to in the current period and the hashes of its blobs,
+    // until it is chosen and revealed
+    committed: Option<(Collation, Vec<BlobHash>)>,
+    proposal_sender: Option<mpsc::Sender<Message>>,
+    // Messages from the SMC listener and commands from the thread manager
+    events: mpsc::Receiver<Event>
 }
 
 impl Proposer {
-    pub fn new() -> Proposer {
+    /// Creates a new Proposer, adding collations through the given SMC backend
+    ///
+    /// The proposer reacts to the messages of the SMC listener and to the commands of
+    /// the thread manager, e.g. merged by `client_thread::merge_events`.
+    pub fn new(events: mpsc::Receiver<Event>, backend: Box<dyn SmcBackend + Send>) -> Proposer {
         Proposer {
-            id: ProposerIdHash::from_dec_str("0").unwrap()
+            id: ProposerIdHash::from_dec_str("0").unwrap(),
+            address: ProposerAddress::zero(),
+            shard_id: ShardIdHash::zero(),
+            backend,
+            registered: false,
+            bid_strategy: BidStrategy::default(),
+            blob_pool: Arc::new(Mutex::new(BlobPool::new())),
+            committed: None,
+            proposal_sender: None,
+            events
+        }
+    }
+
+    /// Sets the address the proposer registers and adds headers with
+    pub fn set_address(&mut self, address: ProposerAddress) {
+        self.address = address;
+    }
+
+    /// Sets the shard the proposer adds collations to, shard 0 by default
+    pub fn set_shard_id(&mut self, shard_id: ShardIdHash) {
+        self.shard_id = shard_id;
+    }
+
+    /// Sets how the proposer bids for its collations, bidding nothing by default
+    pub fn set_bid_strategy(&mut self, bid_strategy: BidStrategy) {
+        self.bid_strategy = bid_strategy;
+    }
+
+    /// Sets the pool the proposer collects blobs from
+    pub fn set_blob_pool(&mut self, blob_pool: Arc<Mutex<BlobPool>>) {
+        self.blob_pool = blob_pool;
+    }
+
+    /// The pool the proposer collects blobs from, to submit blobs to
+    pub fn blob_pool(&self) -> Arc<Mutex<BlobPool>> {
+        self.blob_pool.clone()
+    }
+
+    /// Sends the proposer's commitments and revealed collations, e.g. to notaries
+    pub fn set_proposal_sender(&mut self, proposal_sender: mpsc::Sender<Message>) {
+        self.proposal_sender = Some(proposal_sender);
+    }
+
+    /// Runs the proposer
+    ///
+    /// At the start of each period, the proposer registers in the SMC if it isn't yet,
+    /// collects the best pooled blobs, and commits to a collation of them in its shard.
+    /// Once a notary chooses the commitment, the proposer adds the header to the SMC and
+    /// reveals the collation. It stops on `Command::Terminate`, or when both the SMC
+    /// listener and the thread manager hung up.
+    pub fn run(&mut self) {
+        loop {
+            match self.events.recv() {
+                Ok(Event::Command(command)) => {
+                    debug!("Received command {:?} in thread {:?} from the thread manager", command, thread::current());
+                    match command {
+                        Command::Terminate => { break }
+                    }
+                },
+                Ok(Event::Message(msg)) => {
+                    debug!("Received message {:?} in thread {:?} from SMC Listener", msg, thread::current());
+                    self.handle_message(msg);
+                },
+                Err(mpsc::RecvError) => {
+                    debug!("SMC Listener and thread manager of thread {:?} hung up", thread::current());
+                    break;
+                }
+            }
+        }
+    }
+
+    fn handle_message(&mut self, msg: Message) {
+        match msg {
+            Message::PeriodStart { value } => {
+                debug!("Period {} started", value);
+                if let Err(e) = self.propose(value) {
+                    warn!("Proposer id {} failed to propose a collation in period {}: {}", self.id, value, e);
+                }
+            },
+            Message::Chosen { value } => {
+                if let Err(e) = self.reveal_proposal(&value) {
+                    warn!("Proposer id {} failed to reveal collation {:?}: {}", self.id, value.header_hash, e);
+                }
+            },
+            _ => {}
+        }
+    }
+
+    /// Commit to a collation of the best pooled blobs in the proposer's shard and the
+    /// period, replacing an earlier commitment that wasn't chosen.
+    fn propose(&mut self, period: ChunkPeriodHash) -> Result<()> {
+        self.committed = None;
+        self.register()?;
+        let expired = self.lock_blob_pool().start_period(period);
+        if expired > 0 {
+            debug!("Proposer id {} dropped {} expired blobs", self.id, expired);
+        }
+        let (hashes, blobs, fees) = self.collect_blobs();
+        if blobs.is_empty() {
+            debug!("Proposer id {} has no blobs to propose in period {}", self.id, period);
+            return Ok(());
+        }
+
+        let bid = self.bid(fees)?;
+        let collation = self.prepare_collation(&blobs, period, bid)?;
+        let commitment = Commitment::for_header(&collation.header);
+        info!("Proposer id {} commits to collation {:?} of {} blobs with bid {} in shard id {} and period {}",
+              self.id, commitment.header_hash, blobs.len(), bid, self.shard_id, period);
+        self.committed = Some((collation, hashes));
+        self.send(Message::Commitment { value: commitment });
+        Ok(())
+    }
+
+    /// Register in the proposer registry with PROPOSER_DEPOSIT, unless registered
+    /// already
+    fn register(&mut self) -> Result<()> {
+        if self.registered {
+            return Ok(());
+        }
+        if self.backend.proposer_deposit(self.address)?.is_none() {
+            self.backend.register_proposer(self.address, PROPOSER_DEPOSIT)?;
+            info!("Registered proposer address {:?} with SMC Contract", self.address);
+        }
+        self.registered = true;
+        Ok(())
+    }
+
+    /// Add to the proposer's balance in the SMC, which its bids are paid from,
+    /// registering the proposer first if it isn't yet
+    pub fn add_balance(&mut self, amount: Wei) -> Result<()> {
+        self.register()?;
+        self.backend.add_proposer_balance(self.address, amount)?;
+        info!("Added {} wei to the balance of proposer address {:?}", amount, self.address);
+        Ok(())
+    }
+
+    /// The bid for a collation of blobs paying `fees`: the bid of the proposer's
+    /// strategy, but no more than its balance, so that the SMC accepts the header.
+    fn bid(&self, fees: Wei) -> Result<ProposerBidHash> {
+        let bid = self.bid_strategy.bid(fees);
+        if bid.is_zero() {
+            return Ok(bid);
+        }
+        let balance = self.backend.proposer_balance(self.address)?;
+        if balance < bid {
+            debug!("Proposer id {} lowers its bid of {} to its balance of {} wei", self.id, bid, balance);
+            return Ok(balance);
+        }
+        Ok(bid)
+    }
+
+    fn lock_blob_pool(&self) -> MutexGuard<'_, BlobPool> {
+        // The pool is consistent between its method calls, so it is still usable if
+        // another holder panicked.
+        match self.blob_pool.lock() {
+            Ok(blob_pool) => blob_pool,
+            Err(poisoned) => poisoned.into_inner()
+        }
+    }
+
+    /// The best pooled blobs that fit into one collation body, with their hashes and
+    /// their total fee. They stay in the pool until they are included.
+    fn collect_blobs(&self) -> (Vec<BlobHash>, Vec<Blob>, Wei) {
+        let blob_pool = self.lock_blob_pool();
+        let selected = blob_pool.select(CHUNKS_PER_COLLATION);
+        let fees = selected.iter().fold(Wei::zero(), |fees, pooled| fees.saturating_add(pooled.fee));
+        let (hashes, blobs) = selected.into_iter()
+            .map(|pooled| (pooled.hash, pooled.blob.clone()))
+            .unzip();
+        (hashes, blobs, fees)
+    }
+
+    /// Build a collation of the blobs in the proposer's shard and the current period,
+    /// with the proposer's bid. The blobs are packed in order into a body padded to
+    /// CHUNKS_PER_COLLATION chunks, and the header commits to the body's chunk root.
+    ///
+    /// Returns an error if the blobs don't fit into one body, or if the collation
+    /// doesn't pass `Collation::validate`, e.g. as the proposer's shard doesn't exist.
+    pub fn prepare_collation(&self, blobs: &[Blob], period: ChunkPeriodHash,
+                             bid: ProposerBidHash) -> Result<Collation> {
+        let chunks: usize = blobs.iter().map(|blob| blob.chunk_count()).sum();
+        if chunks > CHUNKS_PER_COLLATION {
+            bail!(ErrorKind::BodyTooLarge(chunks));
+        }
+        let mut builder = BodyBuilder::new();
+        for blob in blobs {
+            if let Err(blob) = builder.add_blob(blob.clone()) {
+                bail!(ErrorKind::BlobTooLarge(blob.chunk_count()));
+            }
+        }
+        let body = builder.build_padded();
+        let header = Header::new(self.shard_id, body.chunk_root(), period, self.address, bid);
+        let collation = Collation::new(header, body);
+        collation.validate()?;
+        Ok(collation)
+    }
+
+    /// Reveal the committed collation once a notary chose its commitment: add its header
+    /// to the SMC, which debits the bid from the proposer's balance, and broadcast its
+    /// body. Its blobs are removed from the pool once the SMC accepted the header, so if
+    /// it rejects it, they are kept for the next period. Commitments of other proposers
+    /// are ignored.
+    fn reveal_proposal(&mut self, chosen: &Commitment) -> Result<()> {
+        let is_committed = match self.committed {
+            Some((ref collation, _)) => Commitment::for_header(&collation.header) == *chosen,
+            None => false
+        };
+        if !is_committed {
+            return Ok(());
+        }
+        let (collation, hashes) = match self.committed.take() {
+            Some(committed) => committed,
+            None => return Ok(())
+        };
+
+        self.backend.add_header(&collation.header)?;
+        info!("Proposer id {} added header {:?} in shard id {} and period {}, paying its bid of {} wei",
+              self.id, chosen.header_hash, chosen.shard_id, chosen.period, chosen.bid);
+        {
+            let mut blob_pool = self.lock_blob_pool();
+            for hash in &hashes {
+                blob_pool.remove(hash);
+            }
         }
+        self.broadcast_collation_body(collation);
+        Ok(())
     }
 
-    pub fn run(&self) {}
+    /// Send the revealed collation, with its body, to the proposal sender
+    fn broadcast_collation_body(&self, collation: Collation) {
+        self.send(Message::Reveal { value: collation });
+    }
+
+    fn send(&self, msg: Message) {
+        if let Some(ref proposal_sender) = self.proposal_sender {
+            if proposal_sender.send(msg).is_err() {
+                warn!("Proposer id {} failed to send a proposal message, receiver hung up", self.id);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use modules::client_thread::merge_events;
+    use modules::constants::{DATA_BYTES_PER_COLLATION, PERIOD_LENGTH, SHARD_COUNT};
+    use modules::primitives::Wei;
+    use modules::period_clock::MockTimeSource;
+    use modules::smc::in_memory::InMemorySmc;
+
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    /// A proposer on an in-memory contract, with the time source of the contract's
+    /// block numbers and the receiver of its commitments and revealed collations
+    fn generate_proposer() -> (Proposer, InMemorySmc, MockTimeSource, mpsc::Receiver<Message>) {
+        let time = MockTimeSource::new();
+        let backend = InMemorySmc::with_time_source(Arc::new(time.clone()));
+        let (_tx, rx) = mpsc::channel();
+        let mut proposer = Proposer::new(rx, Box::new(backend.clone()));
+        proposer.set_address(ProposerAddress::from([3; 20]));
+        proposer.set_shard_id(ShardIdHash::from(2));
+        let (proposal_sender, proposal_receiver) = mpsc::channel();
+        proposer.set_proposal_sender(proposal_sender);
+        (proposer, backend, time, proposal_receiver)
+    }
+
+    fn receive_commitment(proposals: &mpsc::Receiver<Message>) -> Commitment {
+        match proposals.try_recv() {
+            Ok(Message::Commitment { value }) => value,
+            result => panic!("Expected a commitment, got {:?}", result)
+        }
+    }
+
+    fn receive_reveal(proposals: &mpsc::Receiver<Message>) -> Collation {
+        match proposals.try_recv() {
+            Ok(Message::Reveal { value }) => value,
+            result => panic!("Expected a revealed collation, got {:?}", result)
+        }
+    }
+
+    fn submit(proposer: &Proposer, blob: Blob, fee: u64) -> BlobHash {
+        proposer.blob_pool().lock().unwrap().submit(blob, ProposerAddress::from([9; 20]), Wei::from(fee)).unwrap()
+    }
+
+    #[test]
+    fn it_commits_and_reveals_a_collation_each_period() {
+        let (mut proposer, backend, time, proposals) = generate_proposer();
+        let address = ProposerAddress::from([3; 20]);
+        let shard_id = ShardIdHash::from(2);
+
+        // Without blobs, the proposer only registers
+        proposer.handle_message(Message::PeriodStart { value: ChunkPeriodHash::zero() });
+        assert_eq!(backend.proposer_deposit(address).unwrap(), Some(PROPOSER_DEPOSIT));
+        assert!(proposals.try_recv().is_err());
+
+        let blobs = vec![Blob::new(vec![1; 100]), Blob::new(vec![2; 40])];
+        submit(&proposer, blobs[0].clone(), 40);
+        submit(&proposer, blobs[1].clone(), 10);
+        time.advance_blocks(PERIOD_LENGTH);
+        let period = ChunkPeriodHash::from(1);
+        proposer.handle_message(Message::PeriodStart { value: period });
+
+        // The header is only added, and the body revealed, once the commitment is chosen
+        let commitment = receive_commitment(&proposals);
+        assert_eq!((commitment.shard_id, commitment.period, commitment.proposer), (shard_id, period, address));
+        assert_eq!(backend.get_header(shard_id, period).unwrap(), None);
+        let other_commitment = Commitment { proposer: ProposerAddress::from([4; 20]), ..commitment.clone() };
+        proposer.handle_message(Message::Chosen { value: other_commitment });
+        assert!(proposals.try_recv().is_err());
+
+        proposer.handle_message(Message::Chosen { value: commitment.clone() });
+        let collation = receive_reveal(&proposals);
+        commitment.check_reveal(&collation).unwrap();
+        assert_eq!(backend.get_header(shard_id, period).unwrap(), Some(collation.header.clone()));
+        assert_eq!(collation.body.to_blobs().unwrap(), blobs);
+        assert!(proposer.blob_pool().lock().unwrap().is_empty());
+
+        // A collation is revealed once
+        proposer.handle_message(Message::Chosen { value: commitment });
+        assert!(proposals.try_recv().is_err());
+    }
 
-    fn register(&self) {}
+    #[test]
+    fn it_keeps_blobs_of_rejected_headers() {
+        let (mut proposer, _, time, proposals) = generate_proposer();
+        let blob = Blob::new(vec![1; 100]);
+        let hash = submit(&proposer, blob.clone(), 10);
 
-    fn add_balance(&self) {}
+        // The contract is still in period 0
+        proposer.handle_message(Message::PeriodStart { value: ChunkPeriodHash::from(1) });
+        let commitment = receive_commitment(&proposals);
+        proposer.handle_message(Message::Chosen { value: commitment });
+        assert!(proposals.try_recv().is_err());
+        assert!(proposer.blob_pool().lock().unwrap().contains(&hash));
 
-    fn collect_blobs(&self) {}
+        time.advance_blocks(PERIOD_LENGTH);
+        proposer.handle_message(Message::PeriodStart { value: ChunkPeriodHash::from(1) });
+        let commitment = receive_commitment(&proposals);
+        proposer.handle_message(Message::Chosen { value: commitment });
+        assert_eq!(receive_reveal(&proposals).body.to_blobs().unwrap(), vec![blob]);
+    }
+
+    #[test]
+    fn it_replaces_unchosen_commitments_each_period() {
+        let (mut proposer, _, time, proposals) = generate_proposer();
+        submit(&proposer, Blob::new(vec![1; 100]), 10);
+        proposer.handle_message(Message::PeriodStart { value: ChunkPeriodHash::zero() });
+        let first_commitment = receive_commitment(&proposals);
+
+        time.advance_blocks(PERIOD_LENGTH);
+        proposer.handle_message(Message::PeriodStart { value: ChunkPeriodHash::from(1) });
+        let second_commitment = receive_commitment(&proposals);
+        assert_eq!(second_commitment.period, ChunkPeriodHash::from(1));
+
+        // Being chosen for the past period no longer reveals anything
+        proposer.handle_message(Message::Chosen { value: first_commitment });
+        assert!(proposals.try_recv().is_err());
+        proposer.handle_message(Message::Chosen { value: second_commitment });
+        receive_reveal(&proposals);
+    }
+
+    #[test]
+    fn it_bids_within_its_balance_and_pays_once_revealed() {
+        let (mut proposer, backend, _, proposals) = generate_proposer();
+        let address = ProposerAddress::from([3; 20]);
+        proposer.set_bid_strategy(BidStrategy::FeePercentage(50));
+        proposer.add_balance(Wei::from(30)).unwrap();
+        assert_eq!(backend.proposer_deposit(address).unwrap(), Some(PROPOSER_DEPOSIT));
+        assert_eq!(backend.proposer_balance(address).unwrap(), Wei::from(30));
+
+        submit(&proposer, Blob::new(vec![1; 100]), 40);
+        proposer.handle_message(Message::PeriodStart { value: ChunkPeriodHash::zero() });
+        let commitment = receive_commitment(&proposals);
+        assert_eq!(commitment.bid, ProposerBidHash::from(20));
+        assert_eq!(backend.proposer_balance(address).unwrap(), Wei::from(30));
+
+        proposer.handle_message(Message::Chosen { value: commitment });
+        assert_eq!(receive_reveal(&proposals).header.proposer_bid(), ProposerBidHash::from(20));
+        assert_eq!(backend.proposer_balance(address).unwrap(), Wei::from(10));
+    }
+
+    #[test]
+    fn it_lowers_bids_to_its_balance() {
+        let (mut proposer, backend, time, proposals) = generate_proposer();
+        proposer.set_bid_strategy(BidStrategy::Fixed(ProposerBidHash::from(50)));
+        proposer.add_balance(Wei::from(30)).unwrap();
+        submit(&proposer, Blob::new(vec![1; 100]), 1);
+        submit(&proposer, Blob::new(vec![2; 100]), 1);
+        time.advance_blocks(PERIOD_LENGTH);
+        proposer.handle_message(Message::PeriodStart { value: ChunkPeriodHash::from(1) });
+        let commitment = receive_commitment(&proposals);
+        assert_eq!(commitment.bid, ProposerBidHash::from(30));
+        proposer.handle_message(Message::Chosen { value: commitment });
+        receive_reveal(&proposals);
+        assert_eq!(backend.proposer_balance(ProposerAddress::from([3; 20])).unwrap(), Wei::zero());
+    }
+
+    #[test]
+    fn it_collects_the_best_blobs_that_fit_into_a_collation() {
+        let (proposer, _, _, _) = generate_proposer();
+        let cheap_blob = Blob::new(vec![1; 100]);
+        let full_blob = Blob::new(vec![2; DATA_BYTES_PER_COLLATION]);
+        let expensive_blob = Blob::new(vec![3; 100]);
+        let cheap_hash = submit(&proposer, cheap_blob.clone(), 1);
+        submit(&proposer, full_blob.clone(), 1_000_000);
+        let expensive_hash = submit(&proposer, expensive_blob.clone(), 1_000);
 
-    fn prepare_collation(&self) {}
+        assert_eq!(proposer.collect_blobs(),
+                   (vec![expensive_hash, cheap_hash], vec![expensive_blob, cheap_blob], Wei::from(1_001)));
+        assert_eq!(proposer.lock_blob_pool().len(), 3);
+    }
+
+    #[test]
+    fn it_prepares_a_valid_collation() {
+        let (proposer, _, _, _) = generate_proposer();
+        let blobs = vec![Blob::new(vec![1; 100]), Blob::new(vec![2; DATA_BYTES_PER_COLLATION / 2])];
+        let collation = proposer.prepare_collation(&blobs, ChunkPeriodHash::from(4), ProposerBidHash::from(5)).unwrap();
+
+        collation.validate().unwrap();
+        assert_eq!(collation.body.chunks.len(), CHUNKS_PER_COLLATION);
+        assert_eq!(collation.body.to_blobs().unwrap(), blobs);
+        assert_eq!(collation.header.shard_id, ShardIdHash::from(2));
+        assert_eq!(collation.header.period(), ChunkPeriodHash::from(4));
+        assert_eq!(collation.header.proposer_address(), ProposerAddress::from([3; 20]));
+        assert_eq!(collation.header.proposer_bid(), ProposerBidHash::from(5));
+        assert_eq!(collation.header.chunk_root(), collation.body.chunk_root());
+    }
+
+    #[test]
+    fn it_refuses_to_prepare_invalid_collations() {
+        let (mut proposer, _, _, _) = generate_proposer();
+        let blobs = vec![Blob::new(vec![1; 100]), Blob::new(vec![2; DATA_BYTES_PER_COLLATION])];
+        match proposer.prepare_collation(&blobs, ChunkPeriodHash::from(4), ProposerBidHash::zero()) {
+            Err(Error(ErrorKind::BodyTooLarge(chunks), _)) => assert_eq!(chunks, CHUNKS_PER_COLLATION + 4),
+            result => panic!("Expected body too large error, got {:?}", result)
+        }
 
-    fn reveal_proposal(&self) {}
+        proposer.set_shard_id(ShardIdHash::from(SHARD_COUNT));
+        match proposer.prepare_collation(&blobs[..1], ChunkPeriodHash::from(4), ProposerBidHash::zero()) {
+            Err(Error(ErrorKind::ShardIdOutOfRange(_), _)) => {},
+            result => panic!("Expected shard id out of range error, got {:?}", result)
+        }
+    }
+
+    #[test]
+    fn it_runs_until_terminated() {
+        let (smc_sender, smc_receiver) = mpsc::channel();
+        let (manager_sender, manager_receiver) = mpsc::channel();
+        let backend = InMemorySmc::new();
+        let mut proposer = Proposer::new(merge_events(smc_receiver, manager_receiver), Box::new(backend.clone()));
+        let handle = thread::spawn(move || proposer.run());
 
-    fn broadcast_collation_body(&self) {}
-}
\ No newline at end of file
+        // The message and the command are forwarded by different threads, so the
+        // command is only sent once the message was handled
+        smc_sender.send(Message::PeriodStart { value: ChunkPeriodHash::zero() }).unwrap();
+        for _ in 0..50 {
+            if backend.proposer_deposit(ProposerAddress::zero()).unwrap().is_some() {
+                break;
+            }
+            thread::sleep(Duration::from_millis(100));
+        }
+        manager_sender.send(Command::Terminate).unwrap();
+        handle.join().unwrap();
+        assert_eq!(backend.proposer_deposit(ProposerAddress::zero()).unwrap(), Some(PROPOSER_DEPOSIT));
+    }
+}
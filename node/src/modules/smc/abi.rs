@@ -0,0 +1,387 @@
+use ethereum_types::{Address, H256, U256};
+use modules::collation::header::Header;
+use modules::errors::*;
+use modules::primitives::{
+    ShardIdHash,
+    ChunkRootHash,
+    ChunkPeriodHash,
+    ProposerBidHash,
+    NotaryAddress,
+    ProposerAddress
+};
+use modules::smc::rpc::Log;
+
+use tiny_keccak;
+
+/// Size of an ABI word in bytes
+pub const WORD_SIZE: usize = 32;
+
+/// A value of a static ABI type, which is encoded in a single word.
+#[derive(PartialEq, Debug, Clone)]
+pub enum Token {
+    Address(Address),
+    Uint(U256),
+    Bool(bool),
+    FixedBytes(H256)
+}
+
+impl Token {
+    pub fn encode(&self) -> [u8; WORD_SIZE] {
+        let mut word: [u8; WORD_SIZE] = [0; WORD_SIZE];
+        match *self {
+            Token::Address(ref address) => word[12..].copy_from_slice(&address[..]),
+            Token::Uint(ref number) => number.to_big_endian(&mut word),
+            Token::Bool(value) => word[WORD_SIZE - 1] = value as u8,
+            Token::FixedBytes(ref bytes) => word.copy_from_slice(&bytes[..])
+        }
+        word
+    }
+}
+
+/// The selector of a function: the first 4 bytes of the hash of its signature,
+/// e.g. `transfer(address,uint256)`.
+pub fn selector(signature: &str) -> [u8; 4] {
+    let hash = tiny_keccak::keccak256(signature.as_bytes());
+    [hash[0], hash[1], hash[2], hash[3]]
+}
+
+/// The first topic of an event's logs: the hash of its signature,
+/// e.g. `Transfer(address,address,uint256)`.
+pub fn event_topic(signature: &str) -> H256 {
+    H256::from(tiny_keccak::keccak256(signature.as_bytes()))
+}
+
+/// The call data of a function call: the function's selector, followed by its arguments.
+pub fn encode_call(signature: &str, arguments: &[Token]) -> Vec<u8> {
+    let mut data = selector(signature).to_vec();
+    for argument in arguments {
+        data.extend_from_slice(&argument.encode());
+    }
+    data
+}
+
+/// Split data into words, which must fill it exactly.
+pub fn decode_words(data: &[u8]) -> Result<Vec<&[u8]>> {
+    if !data.len().is_multiple_of(WORD_SIZE) {
+        bail!(ErrorKind::InvalidAbiData(format!("{} bytes aren't a whole number of words", data.len())));
+    }
+    Ok(data.chunks(WORD_SIZE).collect())
+}
+
+fn first_word(data: &[u8]) -> Result<&[u8]> {
+    match decode_words(data)?.first() {
+        Some(word) => Ok(*word),
+        None => bail!(ErrorKind::InvalidAbiData(String::from("No value to decode")))
+    }
+}
+
+/// Decode a `uint256` value from return data or a word.
+pub fn decode_uint(data: &[u8]) -> Result<U256> {
+    Ok(U256::from(first_word(data)?))
+}
+
+/// Decode an `address` value from return data or a word. The 12 bytes before the
+/// address must be zero.
+pub fn decode_address(data: &[u8]) -> Result<Address> {
+    let word = first_word(data)?;
+    if word[..12].iter().any(|byte| *byte != 0) {
+        bail!(ErrorKind::InvalidAbiData(format!("{:?} is not an address", word)));
+    }
+    Ok(Address::from(&word[12..]))
+}
+
+/// Decode a `bytes32` value from return data or a word.
+pub fn decode_fixed_bytes(data: &[u8]) -> Result<H256> {
+    Ok(H256::from(first_word(data)?))
+}
+
+/// The functions of the sharding manager contract that the node calls.
+#[derive(PartialEq, Debug, Clone)]
+pub enum SmcCall {
+    /// Payable with the notary deposit
+    RegisterNotary,
+    DeregisterNotary,
+    /// Returns the deposit of the notary as a `uint256`, zero if it isn't registered
+    GetNotaryDeposit { notary: NotaryAddress },
+    /// Payable with the proposer deposit
+    RegisterProposer,
+    DeregisterProposer,
+    /// Returns the deposit of the proposer as a `uint256`, zero if it isn't registered
+    GetProposerDeposit { proposer: ProposerAddress },
+    /// Payable with the amount added to the proposer's balance
+    AddProposerBalance,
+    WithdrawProposerBalance,
+    /// Returns the balance of the proposer as a `uint256`
+    GetProposerBalance { proposer: ProposerAddress },
+    /// Returns the address of the notary at `index` of the shard's committee in the
+    /// current period, the zero address after the last notary
+    GetNotaryInCommittee { shard_id: ShardIdHash, index: U256 },
+    /// Returns a `uint256` with the bit of each shard id set whose committee the notary
+    /// is in, in the current period
+    GetNotaryShards { notary: NotaryAddress },
+    /// `bid` is debited from the proposer's balance
+    AddHeader { shard_id: ShardIdHash, period: ChunkPeriodHash, chunk_root: ChunkRootHash, bid: ProposerBidHash },
+    /// `index` is the position of the notary in the shard's committee. Only votes that
+    /// the body is `available` are counted, but either is the notary's vote.
+    SubmitVote { shard_id: ShardIdHash, period: ChunkPeriodHash, index: U256, chunk_root: ChunkRootHash, available: bool },
+    /// Returns the number of votes as a `uint256`
+    GetVoteCount { shard_id: ShardIdHash, period: ChunkPeriodHash }
+}
+
+impl SmcCall {
+    pub fn signature(&self) -> &'static str {
+        match *self {
+            SmcCall::RegisterNotary => "register_notary()",
+            SmcCall::DeregisterNotary => "deregister_notary()",
+            SmcCall::GetNotaryDeposit { .. } => "get_notary_deposit(address)",
+            SmcCall::RegisterProposer => "register_proposer()",
+            SmcCall::DeregisterProposer => "deregister_proposer()",
+            SmcCall::GetProposerDeposit { .. } => "get_proposer_deposit(address)",
+            SmcCall::AddProposerBalance => "add_proposer_balance()",
+            SmcCall::WithdrawProposerBalance => "withdraw_proposer_balance()",
+            SmcCall::GetProposerBalance { .. } => "get_proposer_balance(address)",
+            SmcCall::GetNotaryInCommittee { .. } => "get_notary_in_committee(uint256,uint256)",
+            SmcCall::GetNotaryShards { .. } => "get_notary_shards(address)",
+            SmcCall::AddHeader { .. } => "add_header(uint256,uint256,bytes32,uint256)",
+            SmcCall::SubmitVote { .. } => "submit_vote(uint256,uint256,uint256,bytes32,bool)",
+            SmcCall::GetVoteCount { .. } => "get_vote_count(uint256,uint256)"
+        }
+    }
+
+    pub fn arguments(&self) -> Vec<Token> {
+        match *self {
+            SmcCall::RegisterNotary |
+            SmcCall::DeregisterNotary |
+            SmcCall::RegisterProposer |
+            SmcCall::DeregisterProposer |
+            SmcCall::AddProposerBalance |
+            SmcCall::WithdrawProposerBalance => vec![],
+            SmcCall::GetNotaryDeposit { notary } |
+            SmcCall::GetNotaryShards { notary } => vec![Token::Address(notary)],
+            SmcCall::GetProposerDeposit { proposer } |
+            SmcCall::GetProposerBalance { proposer } => vec![Token::Address(proposer)],
+            SmcCall::GetNotaryInCommittee { shard_id, index } => vec![Token::Uint(shard_id), Token::Uint(index)],
+            SmcCall::AddHeader { shard_id, period, chunk_root, bid } =>
+                vec![Token::Uint(shard_id), Token::Uint(period), Token::FixedBytes(chunk_root), Token::Uint(bid)],
+            SmcCall::SubmitVote { shard_id, period, index, chunk_root, available } =>
+                vec![Token::Uint(shard_id), Token::Uint(period), Token::Uint(index), Token::FixedBytes(chunk_root),
+                     Token::Bool(available)],
+            SmcCall::GetVoteCount { shard_id, period } => vec![Token::Uint(shard_id), Token::Uint(period)]
+        }
+    }
+
+    /// The call data of the call
+    pub fn encode(&self) -> Vec<u8> {
+        encode_call(self.signature(), &self.arguments())
+    }
+}
+
+/// Check that a log is an event with the signature and the number of indexed
+/// arguments and data words, and return its data words.
+fn event_data<'a>(log: &'a Log, signature: &str, indexed: usize, words: usize) -> Result<Vec<&'a [u8]>> {
+    if log.topics.first() != Some(&event_topic(signature)) {
+        bail!(ErrorKind::InvalidAbiData(format!("Log {:?} is not a {} event", log, signature)));
+    }
+    let data = decode_words(&log.data)?;
+    if log.topics.len() != indexed + 1 || data.len() != words {
+        bail!(ErrorKind::InvalidAbiData(format!("Log {:?} doesn't match {}", log, signature)));
+    }
+    Ok(data)
+}
+
+/// `HeaderAdded(uint256 indexed shard_id, uint256 period, bytes32 chunk_root, address proposer,
+/// uint256 bid)`, logged when a proposer adds a collation header.
+#[derive(PartialEq, Debug, Clone)]
+pub struct HeaderAdded {
+    pub shard_id: ShardIdHash,
+    pub period: ChunkPeriodHash,
+    pub chunk_root: ChunkRootHash,
+    pub proposer: ProposerAddress,
+    pub bid: ProposerBidHash
+}
+
+impl HeaderAdded {
+    pub const SIGNATURE: &'static str = "HeaderAdded(uint256,uint256,bytes32,address,uint256)";
+
+    pub fn topic() -> H256 {
+        event_topic(HeaderAdded::SIGNATURE)
+    }
+
+    pub fn decode(log: &Log) -> Result<HeaderAdded> {
+        let data = event_data(log, HeaderAdded::SIGNATURE, 1, 4)?;
+        Ok(HeaderAdded {
+            shard_id: decode_uint(&log.topics[1])?,
+            period: decode_uint(data[0])?,
+            chunk_root: decode_fixed_bytes(data[1])?,
+            proposer: decode_address(data[2])?,
+            bid: decode_uint(data[3])?
+        })
+    }
+
+    /// The header that was added
+    pub fn header(&self) -> Header {
+        Header::new(self.shard_id, self.chunk_root, self.period, self.proposer, self.bid)
+    }
+}
+
+/// `Voted(uint256 indexed shard_id, uint256 period, bytes32 chunk_root, address notary)`,
+/// logged when a notary votes that a header's body is available.
+#[derive(PartialEq, Debug, Clone)]
+pub struct Voted {
+    pub shard_id: ShardIdHash,
+    pub period: ChunkPeriodHash,
+    pub chunk_root: ChunkRootHash,
+    pub notary: NotaryAddress
+}
+
+impl Voted {
+    pub const SIGNATURE: &'static str = "Voted(uint256,uint256,bytes32,address)";
+
+    pub fn topic() -> H256 {
+        event_topic(Voted::SIGNATURE)
+    }
+
+    pub fn decode(log: &Log) -> Result<Voted> {
+        let data = event_data(log, Voted::SIGNATURE, 1, 3)?;
+        Ok(Voted {
+            shard_id: decode_uint(&log.topics[1])?,
+            period: decode_uint(data[0])?,
+            chunk_root: decode_fixed_bytes(data[1])?,
+            notary: decode_address(data[2])?
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use modules::hex::{from_hex, to_hex};
+
+    fn generate_log(signature: &str, shard_id: u64, data: &[Token]) -> Log {
+        Log {
+            address: Address::from([0xcc; 20]),
+            topics: vec![event_topic(signature), H256::from(Token::Uint(U256::from(shard_id)).encode())],
+            data: data.iter().flat_map(|token| token.encode().to_vec()).collect(),
+            block_number: 11
+        }
+    }
+
+    #[test]
+    fn it_computes_known_selectors_and_topics() {
+        assert_eq!(selector("transfer(address,uint256)"), [0xa9, 0x05, 0x9c, 0xbb]);
+        assert_eq!(selector("baz(uint32,bool)"), [0xcd, 0xcd, 0x77, 0xc0]);
+        assert_eq!(event_topic("Transfer(address,address,uint256)"),
+                   H256::from(&from_hex("0xddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef")
+                       .unwrap()[..]));
+    }
+
+    #[test]
+    fn it_encodes_known_calls() {
+        // The example of the Solidity ABI specification
+        assert_eq!(to_hex(&encode_call("baz(uint32,bool)", &[Token::Uint(U256::from(69)), Token::Bool(true)])),
+                   "0xcdcd77c0\
+                    0000000000000000000000000000000000000000000000000000000000000045\
+                    0000000000000000000000000000000000000000000000000000000000000001");
+        let address = Address::from(&from_hex("0x5b38da6a701c568545dcfcb03fcb875f56beddc4").unwrap()[..]);
+        assert_eq!(to_hex(&encode_call("transfer(address,uint256)",
+                                       &[Token::Address(address), Token::Uint(U256::from(1000))])),
+                   "0xa9059cbb\
+                    0000000000000000000000005b38da6a701c568545dcfcb03fcb875f56beddc4\
+                    00000000000000000000000000000000000000000000000000000000000003e8");
+    }
+
+    #[test]
+    fn it_encodes_smc_calls() {
+        assert_eq!(SmcCall::RegisterNotary.encode(), selector("register_notary()").to_vec());
+
+        let chunk_root = ChunkRootHash::from([0x0b; 32]);
+        let data = SmcCall::AddHeader {
+            shard_id: ShardIdHash::from(4),
+            period: ChunkPeriodHash::from(2),
+            chunk_root,
+            bid: ProposerBidHash::from(300)
+        }.encode();
+        assert_eq!(data.len(), 4 + 4 * WORD_SIZE);
+        assert_eq!(&data[..4], &selector("add_header(uint256,uint256,bytes32,uint256)"));
+        assert_eq!(decode_uint(&data[4..36]).unwrap(), ShardIdHash::from(4));
+        assert_eq!(decode_uint(&data[36..68]).unwrap(), ChunkPeriodHash::from(2));
+        assert_eq!(decode_fixed_bytes(&data[68..100]).unwrap(), chunk_root);
+        assert_eq!(decode_uint(&data[100..]).unwrap(), ProposerBidHash::from(300));
+
+        let notary = Address::from([0xaa; 20]);
+        let data = SmcCall::GetNotaryDeposit { notary }.encode();
+        assert_eq!(decode_address(&data[4..]).unwrap(), notary);
+    }
+
+    #[test]
+    fn it_decodes_return_values() {
+        assert_eq!(decode_uint(&Token::Uint(U256::from(1000)).encode()).unwrap(), U256::from(1000));
+        assert_eq!(decode_address(&Token::Address(Address::from([1; 20])).encode()).unwrap(),
+                   Address::from([1; 20]));
+        match decode_address(&Token::Uint(U256::max_value()).encode()) {
+            Err(Error(ErrorKind::InvalidAbiData(..), _)) => {},
+            result => panic!("Expected invalid ABI data error, got {:?}", result)
+        }
+        match decode_uint(&[0; 31]) {
+            Err(Error(ErrorKind::InvalidAbiData(..), _)) => {},
+            result => panic!("Expected invalid ABI data error, got {:?}", result)
+        }
+        match decode_uint(&[]) {
+            Err(Error(ErrorKind::InvalidAbiData(..), _)) => {},
+            result => panic!("Expected invalid ABI data error, got {:?}", result)
+        }
+    }
+
+    #[test]
+    fn it_decodes_header_added_events() {
+        let chunk_root = ChunkRootHash::from([0x0b; 32]);
+        let proposer = Address::from([0xaa; 20]);
+        let log = generate_log(HeaderAdded::SIGNATURE, 4, &[
+            Token::Uint(U256::from(2)), Token::FixedBytes(chunk_root), Token::Address(proposer),
+            Token::Uint(U256::from(300))
+        ]);
+        let event = HeaderAdded::decode(&log).unwrap();
+        assert_eq!(event, HeaderAdded {
+            shard_id: ShardIdHash::from(4),
+            period: ChunkPeriodHash::from(2),
+            chunk_root,
+            proposer,
+            bid: ProposerBidHash::from(300)
+        });
+        assert_eq!(event.header(), Header::new(ShardIdHash::from(4), chunk_root, ChunkPeriodHash::from(2), proposer,
+                                               ProposerBidHash::from(300)));
+    }
+
+    #[test]
+    fn it_decodes_voted_events() {
+        let chunk_root = ChunkRootHash::from([0x0b; 32]);
+        let notary = Address::from([0xbb; 20]);
+        let log = generate_log(Voted::SIGNATURE, 4, &[
+            Token::Uint(U256::from(2)), Token::FixedBytes(chunk_root), Token::Address(notary)
+        ]);
+        assert_eq!(Voted::decode(&log).unwrap(), Voted {
+            shard_id: ShardIdHash::from(4),
+            period: ChunkPeriodHash::from(2),
+            chunk_root,
+            notary
+        });
+    }
+
+    #[test]
+    fn it_rejects_logs_of_other_events() {
+        let log = generate_log(Voted::SIGNATURE, 4, &[
+            Token::Uint(U256::from(2)), Token::FixedBytes(ChunkRootHash::zero()), Token::Address(Address::zero())
+        ]);
+        match HeaderAdded::decode(&log) {
+            Err(Error(ErrorKind::InvalidAbiData(..), _)) => {},
+            result => panic!("Expected invalid ABI data error, got {:?}", result)
+        }
+
+        let mut short_log = log.clone();
+        short_log.data.truncate(2 * WORD_SIZE);
+        match Voted::decode(&short_log) {
+            Err(Error(ErrorKind::InvalidAbiData(..), _)) => {},
+            result => panic!("Expected invalid ABI data error, got {:?}", result)
+        }
+    }
+}
@@ -0,0 +1,77 @@
+use modules::collation::header::Header;
+use modules::errors::*;
+use modules::primitives::{
+    ShardIdHash,
+    ChunkPeriodHash,
+    NotaryAddress,
+    ProposerAddress,
+    Wei
+};
+use modules::vote::Vote;
+
+/// The state and transactions of the sharding manager contract (SMC) that the node
+/// uses, so that the SMC listener, notary and proposer can run against a contract on
+/// an Ethereum node or against a mock.
+///
+/// Methods take `&self`, so a backend can be shared between threads behind a handle.
+/// Transactions that the contract would revert give an error.
+pub trait SmcBackend {
+    /// Register a notary in the notary registry, locking its deposit, which must be
+    /// at least `NOTARY_DEPOSIT`.
+    fn register_notary(&self, notary: NotaryAddress, deposit: Wei) -> Result<()>;
+
+    /// Remove a notary from the notary registry and return its deposit.
+    fn deregister_notary(&self, notary: NotaryAddress) -> Result<Wei>;
+
+    /// The deposit of a registered notary, or `None` if it isn't registered.
+    fn notary_deposit(&self, notary: NotaryAddress) -> Result<Option<Wei>>;
+
+    /// Register a proposer in the proposer registry, locking its deposit, which must
+    /// be at least `PROPOSER_DEPOSIT`.
+    fn register_proposer(&self, proposer: ProposerAddress, deposit: Wei) -> Result<()>;
+
+    /// Remove a proposer from the proposer registry and return its deposit.
+    fn deregister_proposer(&self, proposer: ProposerAddress) -> Result<Wei>;
+
+    /// The deposit of a registered proposer, or `None` if it isn't registered.
+    fn proposer_deposit(&self, proposer: ProposerAddress) -> Result<Option<Wei>>;
+
+    /// Add to the balance that a registered proposer's bids are paid from.
+    fn add_proposer_balance(&self, proposer: ProposerAddress, amount: Wei) -> Result<()>;
+
+    /// Withdraw the whole balance of a proposer and return it.
+    fn withdraw_proposer_balance(&self, proposer: ProposerAddress) -> Result<Wei>;
+
+    /// The balance of a proposer, zero if it has none.
+    fn proposer_balance(&self, proposer: ProposerAddress) -> Result<Wei>;
+
+    /// The current period of the main chain.
+    fn current_period(&self) -> Result<ChunkPeriodHash>;
+
+    /// The notaries sampled to vote on the shard in the current period.
+    fn get_notary_committee(&self, shard_id: ShardIdHash) -> Result<Vec<NotaryAddress>>;
+
+    /// The shards whose committee the notary is sampled in for the current period,
+    /// ordered by shard id.
+    fn get_notary_shards(&self, notary: NotaryAddress) -> Result<Vec<ShardIdHash>>;
+
+    /// Add a collation header for its shard in the current period, from a registered
+    /// proposer. Only one header is added per shard and period. The proposer's bid is
+    /// debited from its balance, which must cover it.
+    fn add_header(&self, header: &Header) -> Result<()>;
+
+    /// The header added for the shard in the period, if any.
+    fn get_header(&self, shard_id: ShardIdHash, period: ChunkPeriodHash) -> Result<Option<Header>>;
+
+    /// The headers added for all shards in the period, ordered by shard id.
+    fn get_headers(&self, period: ChunkPeriodHash) -> Result<Vec<Header>>;
+
+    /// Record a vote by a notary in the committee of the vote's shard, on the header
+    /// added for the shard in the current period. Each notary votes at most once per
+    /// shard and period. Votes that the body is unavailable are accepted but not
+    /// counted, and still are the notary's vote.
+    fn submit_vote(&self, notary: NotaryAddress, vote: &Vote) -> Result<()>;
+
+    /// The number of votes counted for the header added for the shard in the period.
+    fn get_vote_count(&self, shard_id: ShardIdHash, period: ChunkPeriodHash) -> Result<usize>;
+}
@@ -0,0 +1,524 @@
+use modules::collation::header::Header;
+use modules::constants::{SHARD_COUNT, COMMITTEE_SIZE, NOTARY_DEPOSIT, PERIOD_LENGTH, PROPOSER_DEPOSIT};
+use modules::errors::*;
+use modules::period_clock::{MockTimeSource, TimeSource};
+use modules::primitives::{
+    ShardIdHash,
+    ChunkPeriodHash,
+    NotaryAddress,
+    ProposerAddress,
+    Wei
+};
+use modules::smc::backend::SmcBackend;
+use modules::vote::Vote;
+
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex, MutexGuard};
+use tiny_keccak;
+
+#[derive(Default)]
+struct SmcState {
+    // Number of blocks in a period
+    period_length: u64,
+    notary_deposits: HashMap<NotaryAddress, Wei>,
+    proposer_deposits: HashMap<ProposerAddress, Wei>,
+    proposer_balances: HashMap<ProposerAddress, Wei>,
+    headers: HashMap<(ShardIdHash, ChunkPeriodHash), Header>,
+    // Notaries that voted in each shard and period, whether or not their vote was counted
+    voters: HashMap<(ShardIdHash, ChunkPeriodHash), HashSet<NotaryAddress>>,
+    // Number of votes that the body of the header of each shard and period is available
+    vote_counts: HashMap<(ShardIdHash, ChunkPeriodHash), usize>
+}
+
+/// A sharding manager contract kept in memory, for tests and local networks.
+///
+/// Clones share the same contract state, so a listener, notary and proposer can
+/// each hold a handle to it. The period is the block number of its time source
+/// divided by the period length, PERIOD_LENGTH by default.
+#[derive(Clone)]
+pub struct InMemorySmc {
+    state: Arc<Mutex<SmcState>>,
+    time_source: Arc<dyn TimeSource + Send + Sync>
+}
+
+impl InMemorySmc {
+    /// Creates a contract whose block number stays at zero
+    pub fn new() -> InMemorySmc {
+        InMemorySmc::with_time_source(Arc::new(MockTimeSource::new()))
+    }
+
+    pub fn with_time_source(time_source: Arc<dyn TimeSource + Send + Sync>) -> InMemorySmc {
+        InMemorySmc {
+            state: Arc::new(Mutex::new(SmcState { period_length: PERIOD_LENGTH, ..SmcState::default() })),
+            time_source
+        }
+    }
+
+    /// Sets the number of blocks in a period, for all handles to the contract. A
+    /// `PeriodClock` on the contract must use the same length.
+    pub fn set_period_length(&self, period_length: u64) {
+        assert!(period_length > 0, "A period must have at least one block");
+        self.state().period_length = period_length;
+    }
+
+    pub fn period_length(&self) -> u64 {
+        self.state().period_length
+    }
+
+    fn state(&self) -> MutexGuard<'_, SmcState> {
+        // Every change of the state is completed before the lock is released, so the
+        // state is still consistent if another holder panicked.
+        match self.state.lock() {
+            Ok(state) => state,
+            Err(poisoned) => poisoned.into_inner()
+        }
+    }
+}
+
+impl Default for InMemorySmc {
+    fn default() -> InMemorySmc {
+        InMemorySmc::new()
+    }
+}
+
+fn check_shard_id(shard_id: ShardIdHash) -> Result<()> {
+    if shard_id >= ShardIdHash::from(SHARD_COUNT) {
+        bail!(ErrorKind::ShardIdOutOfRange(shard_id));
+    }
+    Ok(())
+}
+
+/// The notaries sampled for the shard and period: the registered notaries ordered by
+/// a hash of the period, shard id and their address, up to COMMITTEE_SIZE of them.
+fn sample_committee(notaries: &HashMap<NotaryAddress, Wei>, shard_id: ShardIdHash,
+                    period: ChunkPeriodHash) -> Vec<NotaryAddress> {
+    let mut seed: [u8; 64] = [0; 64];
+    period.to_big_endian(&mut seed[..32]);
+    shard_id.to_big_endian(&mut seed[32..]);
+    let mut committee: Vec<(Vec<u8>, NotaryAddress)> = notaries.keys()
+        .map(|notary| {
+            let mut keccak = tiny_keccak::Keccak::new_keccak256();
+            keccak.update(&seed);
+            keccak.update(&notary[..]);
+            let mut sample_hash: [u8; 32] = [0; 32];
+            keccak.finalize(&mut sample_hash);
+            (sample_hash.to_vec(), *notary)
+        })
+        .collect();
+    committee.sort();
+    committee.into_iter()
+        .take(COMMITTEE_SIZE)
+        .map(|(_, notary)| notary)
+        .collect()
+}
+
+impl SmcBackend for InMemorySmc {
+    fn register_notary(&self, notary: NotaryAddress, deposit: Wei) -> Result<()> {
+        let mut state = self.state();
+        if state.notary_deposits.contains_key(&notary) {
+            bail!(ErrorKind::NotaryAlreadyRegistered(notary));
+        }
+        if deposit < NOTARY_DEPOSIT {
+            bail!(ErrorKind::DepositTooLow(deposit, NOTARY_DEPOSIT));
+        }
+        state.notary_deposits.insert(notary, deposit);
+        Ok(())
+    }
+
+    fn deregister_notary(&self, notary: NotaryAddress) -> Result<Wei> {
+        match self.state().notary_deposits.remove(&notary) {
+            Some(deposit) => Ok(deposit),
+            None => bail!(ErrorKind::NotaryNotRegistered(notary))
+        }
+    }
+
+    fn notary_deposit(&self, notary: NotaryAddress) -> Result<Option<Wei>> {
+        Ok(self.state().notary_deposits.get(&notary).cloned())
+    }
+
+    fn register_proposer(&self, proposer: ProposerAddress, deposit: Wei) -> Result<()> {
+        let mut state = self.state();
+        if state.proposer_deposits.contains_key(&proposer) {
+            bail!(ErrorKind::ProposerAlreadyRegistered(proposer));
+        }
+        if deposit < PROPOSER_DEPOSIT {
+            bail!(ErrorKind::DepositTooLow(deposit, PROPOSER_DEPOSIT));
+        }
+        state.proposer_deposits.insert(proposer, deposit);
+        Ok(())
+    }
+
+    fn deregister_proposer(&self, proposer: ProposerAddress) -> Result<Wei> {
+        match self.state().proposer_deposits.remove(&proposer) {
+            Some(deposit) => Ok(deposit),
+            None => bail!(ErrorKind::ProposerNotRegistered(proposer))
+        }
+    }
+
+    fn proposer_deposit(&self, proposer: ProposerAddress) -> Result<Option<Wei>> {
+        Ok(self.state().proposer_deposits.get(&proposer).cloned())
+    }
+
+    fn add_proposer_balance(&self, proposer: ProposerAddress, amount: Wei) -> Result<()> {
+        let mut state = self.state();
+        if !state.proposer_deposits.contains_key(&proposer) {
+            bail!(ErrorKind::ProposerNotRegistered(proposer));
+        }
+        let balance = state.proposer_balances.entry(proposer).or_insert_with(Wei::zero);
+        *balance = balance.saturating_add(amount);
+        Ok(())
+    }
+
+    fn withdraw_proposer_balance(&self, proposer: ProposerAddress) -> Result<Wei> {
+        Ok(self.state().proposer_balances.remove(&proposer).unwrap_or_else(Wei::zero))
+    }
+
+    fn proposer_balance(&self, proposer: ProposerAddress) -> Result<Wei> {
+        Ok(self.state().proposer_balances.get(&proposer).cloned().unwrap_or_else(Wei::zero))
+    }
+
+    fn current_period(&self) -> Result<ChunkPeriodHash> {
+        let block_number = self.time_source.now()?;
+        Ok(ChunkPeriodHash::from(block_number / self.period_length()))
+    }
+
+    fn get_notary_committee(&self, shard_id: ShardIdHash) -> Result<Vec<NotaryAddress>> {
+        check_shard_id(shard_id)?;
+        let period = self.current_period()?;
+        Ok(sample_committee(&self.state().notary_deposits, shard_id, period))
+    }
+
+    fn get_notary_shards(&self, notary: NotaryAddress) -> Result<Vec<ShardIdHash>> {
+        let period = self.current_period()?;
+        let state = self.state();
+        Ok((0..SHARD_COUNT)
+            .map(ShardIdHash::from)
+            .filter(|shard_id| sample_committee(&state.notary_deposits, *shard_id, period).contains(&notary))
+            .collect())
+    }
+
+    fn add_header(&self, header: &Header) -> Result<()> {
+        check_shard_id(header.shard_id)?;
+        let period = self.current_period()?;
+        if header.period() != period {
+            bail!(ErrorKind::WrongPeriod(header.period(), period));
+        }
+        let mut state = self.state();
+        if !state.proposer_deposits.contains_key(&header.proposer_address()) {
+            bail!(ErrorKind::ProposerNotRegistered(header.proposer_address()));
+        }
+        let key = (header.shard_id, header.period());
+        if state.headers.contains_key(&key) {
+            bail!(ErrorKind::HeaderAlreadyAdded(header.shard_id, header.period()));
+        }
+        let proposer = header.proposer_address();
+        let balance = state.proposer_balances.get(&proposer).cloned().unwrap_or_else(Wei::zero);
+        if balance < header.proposer_bid() {
+            bail!(ErrorKind::InsufficientBalance(proposer, header.proposer_bid(), balance));
+        }
+        state.proposer_balances.insert(proposer, balance - header.proposer_bid());
+        state.headers.insert(key, header.clone());
+        Ok(())
+    }
+
+    fn get_header(&self, shard_id: ShardIdHash, period: ChunkPeriodHash) -> Result<Option<Header>> {
+        check_shard_id(shard_id)?;
+        Ok(self.state().headers.get(&(shard_id, period)).cloned())
+    }
+
+    fn get_headers(&self, period: ChunkPeriodHash) -> Result<Vec<Header>> {
+        let mut headers: Vec<Header> = self.state().headers.values()
+            .filter(|header| header.period() == period)
+            .cloned()
+            .collect();
+        headers.sort_by_key(|header| header.shard_id);
+        Ok(headers)
+    }
+
+    fn submit_vote(&self, notary: NotaryAddress, vote: &Vote) -> Result<()> {
+        check_shard_id(vote.shard_id)?;
+        let period = self.current_period()?;
+        if vote.period != period {
+            bail!(ErrorKind::WrongPeriod(vote.period, period));
+        }
+        let mut state = self.state();
+        if !sample_committee(&state.notary_deposits, vote.shard_id, period).contains(&notary) {
+            bail!(ErrorKind::NotaryNotInCommittee(notary, vote.shard_id));
+        }
+        let key = (vote.shard_id, vote.period);
+        match state.headers.get(&key) {
+            Some(header) if header.hash() == vote.header_hash => {},
+            _ => bail!(ErrorKind::UnknownHeader(vote.header_hash))
+        }
+        if !state.voters.entry(key).or_default().insert(notary) {
+            bail!(ErrorKind::AlreadyVoted(notary, vote.shard_id, vote.period));
+        }
+        if vote.available {
+            *state.vote_counts.entry(key).or_insert(0) += 1;
+        }
+        Ok(())
+    }
+
+    fn get_vote_count(&self, shard_id: ShardIdHash, period: ChunkPeriodHash) -> Result<usize> {
+        check_shard_id(shard_id)?;
+        Ok(self.state().vote_counts.get(&(shard_id, period)).cloned().unwrap_or(0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use modules::primitives::{ChunkRootHash, NotaryIdHash, ProposerBidHash};
+
+    fn address(byte: u8) -> NotaryAddress {
+        NotaryAddress::from([byte; 20])
+    }
+
+    fn shard_id(id: u64) -> ShardIdHash {
+        ShardIdHash::from(id)
+    }
+
+    fn generate_header(shard_id: ShardIdHash, period: ChunkPeriodHash, proposer: ProposerAddress) -> Header {
+        Header::new(shard_id, ChunkRootHash::from([7; 32]), period, proposer, ProposerBidHash::zero())
+    }
+
+    /// A contract with a registered proposer and notary, and a header added by the
+    /// proposer in shard 1 of the current period.
+    fn generate_smc_with_header() -> (InMemorySmc, Header) {
+        let smc = InMemorySmc::new();
+        smc.register_proposer(address(1), PROPOSER_DEPOSIT).unwrap();
+        smc.register_notary(address(2), NOTARY_DEPOSIT).unwrap();
+        let header = generate_header(shard_id(1), smc.current_period().unwrap(), address(1));
+        smc.add_header(&header).unwrap();
+        (smc, header)
+    }
+
+    #[test]
+    fn it_registers_and_deregisters_notaries() {
+        let smc = InMemorySmc::new();
+        assert_eq!(smc.notary_deposit(address(1)).unwrap(), None);
+        smc.register_notary(address(1), NOTARY_DEPOSIT).unwrap();
+        assert_eq!(smc.notary_deposit(address(1)).unwrap(), Some(NOTARY_DEPOSIT));
+
+        match smc.register_notary(address(1), NOTARY_DEPOSIT) {
+            Err(Error(ErrorKind::NotaryAlreadyRegistered(notary), _)) => assert_eq!(notary, address(1)),
+            result => panic!("Expected notary already registered error, got {:?}", result)
+        }
+
+        assert_eq!(smc.deregister_notary(address(1)).unwrap(), NOTARY_DEPOSIT);
+        assert_eq!(smc.notary_deposit(address(1)).unwrap(), None);
+        match smc.deregister_notary(address(1)) {
+            Err(Error(ErrorKind::NotaryNotRegistered(notary), _)) => assert_eq!(notary, address(1)),
+            result => panic!("Expected notary not registered error, got {:?}", result)
+        }
+    }
+
+    #[test]
+    fn it_registers_and_deregisters_proposers() {
+        let smc = InMemorySmc::new();
+        smc.register_proposer(address(1), PROPOSER_DEPOSIT).unwrap();
+        assert_eq!(smc.proposer_deposit(address(1)).unwrap(), Some(PROPOSER_DEPOSIT));
+        assert!(smc.register_proposer(address(1), PROPOSER_DEPOSIT).is_err());
+        assert_eq!(smc.deregister_proposer(address(1)).unwrap(), PROPOSER_DEPOSIT);
+        assert_eq!(smc.proposer_deposit(address(1)).unwrap(), None);
+        assert!(smc.deregister_proposer(address(1)).is_err());
+    }
+
+    #[test]
+    fn it_rejects_deposits_too_low() {
+        let smc = InMemorySmc::new();
+        let deposit = NOTARY_DEPOSIT - Wei::from(1);
+        match smc.register_notary(address(1), deposit) {
+            Err(Error(ErrorKind::DepositTooLow(given, required), _)) => {
+                assert_eq!(given, deposit);
+                assert_eq!(required, NOTARY_DEPOSIT);
+            },
+            result => panic!("Expected deposit too low error, got {:?}", result)
+        }
+        assert!(smc.register_proposer(address(1), Wei::zero()).is_err());
+    }
+
+    #[test]
+    fn clones_share_state() {
+        let smc = InMemorySmc::new();
+        let handle = smc.clone();
+        handle.register_notary(address(1), NOTARY_DEPOSIT).unwrap();
+        assert!(smc.notary_deposit(address(1)).unwrap().is_some());
+    }
+
+    #[test]
+    fn it_takes_period_from_time_source() {
+        let time = MockTimeSource::new();
+        let smc = InMemorySmc::with_time_source(Arc::new(time.clone()));
+        assert_eq!(smc.current_period().unwrap(), ChunkPeriodHash::zero());
+        time.advance_blocks(PERIOD_LENGTH - 1);
+        assert_eq!(smc.current_period().unwrap(), ChunkPeriodHash::zero());
+        time.advance_blocks(1);
+        assert_eq!(smc.current_period().unwrap(), ChunkPeriodHash::from(1));
+
+        // Every handle uses the configured period length
+        smc.clone().set_period_length(2);
+        assert_eq!(smc.period_length(), 2);
+        assert_eq!(smc.current_period().unwrap(), ChunkPeriodHash::from(PERIOD_LENGTH / 2));
+    }
+
+    #[test]
+    fn it_samples_committee_from_registered_notaries() {
+        let smc = InMemorySmc::new();
+        for byte in 0..COMMITTEE_SIZE as u8 + 10 {
+            smc.register_notary(address(byte), NOTARY_DEPOSIT).unwrap();
+        }
+        let committee = smc.get_notary_committee(shard_id(3)).unwrap();
+        assert_eq!(committee.len(), COMMITTEE_SIZE);
+        assert_eq!(committee.iter().collect::<HashSet<_>>().len(), COMMITTEE_SIZE);
+        assert_eq!(smc.get_notary_committee(shard_id(3)).unwrap(), committee);
+        assert_ne!(smc.get_notary_committee(shard_id(4)).unwrap(), committee);
+
+        assert!(smc.get_notary_committee(shard_id(SHARD_COUNT as u64)).is_err());
+    }
+
+    #[test]
+    fn it_gets_the_shards_of_a_notary() {
+        let smc = InMemorySmc::new();
+        for byte in 0..COMMITTEE_SIZE as u8 + 10 {
+            smc.register_notary(address(byte), NOTARY_DEPOSIT).unwrap();
+        }
+        let shards = smc.get_notary_shards(address(1)).unwrap();
+        let expected: Vec<ShardIdHash> = (0..SHARD_COUNT as u64)
+            .map(shard_id)
+            .filter(|shard_id| smc.get_notary_committee(*shard_id).unwrap().contains(&address(1)))
+            .collect();
+        assert_eq!(shards, expected);
+        assert!(shards.len() > 1);
+
+        assert_eq!(smc.get_notary_shards(address(0xff)).unwrap(), vec![]);
+    }
+
+    #[test]
+    fn it_adds_one_header_per_shard_and_period() {
+        let (smc, header) = generate_smc_with_header();
+        let period = smc.current_period().unwrap();
+        assert_eq!(smc.get_header(shard_id(1), period).unwrap(), Some(header.clone()));
+        assert_eq!(smc.get_header(shard_id(2), period).unwrap(), None);
+        assert_eq!(smc.get_headers(period).unwrap(), vec![header.clone()]);
+        assert_eq!(smc.get_headers(period + ChunkPeriodHash::from(1)).unwrap(), vec![]);
+
+        match smc.add_header(&header) {
+            Err(Error(ErrorKind::HeaderAlreadyAdded(..), _)) => {},
+            result => panic!("Expected header already added error, got {:?}", result)
+        }
+
+        // The headers of all shards are ordered by shard id
+        let first_header = generate_header(shard_id(0), period, address(1));
+        smc.add_header(&first_header).unwrap();
+        assert_eq!(smc.get_headers(period).unwrap(), vec![first_header, header]);
+    }
+
+    #[test]
+    fn it_rejects_headers_of_other_periods_or_proposers() {
+        let smc = InMemorySmc::new();
+        smc.register_proposer(address(1), PROPOSER_DEPOSIT).unwrap();
+        match smc.add_header(&generate_header(shard_id(1), ChunkPeriodHash::from(1), address(1))) {
+            Err(Error(ErrorKind::WrongPeriod(..), _)) => {},
+            result => panic!("Expected wrong period error, got {:?}", result)
+        }
+        match smc.add_header(&generate_header(shard_id(1), ChunkPeriodHash::zero(), address(2))) {
+            Err(Error(ErrorKind::ProposerNotRegistered(..), _)) => {},
+            result => panic!("Expected proposer not registered error, got {:?}", result)
+        }
+    }
+
+    #[test]
+    fn it_manages_proposer_balances() {
+        let smc = InMemorySmc::new();
+        match smc.add_proposer_balance(address(1), Wei::from(100)) {
+            Err(Error(ErrorKind::ProposerNotRegistered(proposer), _)) => assert_eq!(proposer, address(1)),
+            result => panic!("Expected proposer not registered error, got {:?}", result)
+        }
+        smc.register_proposer(address(1), PROPOSER_DEPOSIT).unwrap();
+        assert_eq!(smc.proposer_balance(address(1)).unwrap(), Wei::zero());
+        smc.add_proposer_balance(address(1), Wei::from(100)).unwrap();
+        smc.add_proposer_balance(address(1), Wei::from(50)).unwrap();
+        assert_eq!(smc.proposer_balance(address(1)).unwrap(), Wei::from(150));
+
+        // The balance is separate from the deposit
+        assert_eq!(smc.proposer_deposit(address(1)).unwrap(), Some(PROPOSER_DEPOSIT));
+        assert_eq!(smc.withdraw_proposer_balance(address(1)).unwrap(), Wei::from(150));
+        assert_eq!(smc.proposer_balance(address(1)).unwrap(), Wei::zero());
+        assert_eq!(smc.withdraw_proposer_balance(address(1)).unwrap(), Wei::zero());
+    }
+
+    #[test]
+    fn it_debits_bids_of_added_headers() {
+        let smc = InMemorySmc::new();
+        smc.register_proposer(address(1), PROPOSER_DEPOSIT).unwrap();
+        smc.add_proposer_balance(address(1), Wei::from(100)).unwrap();
+        let header = Header::new(shard_id(1), ChunkRootHash::from([7; 32]), ChunkPeriodHash::zero(), address(1),
+                                 ProposerBidHash::from(101));
+        match smc.add_header(&header) {
+            Err(Error(ErrorKind::InsufficientBalance(proposer, bid, balance), _)) => {
+                assert_eq!((proposer, bid, balance), (address(1), ProposerBidHash::from(101), Wei::from(100)));
+            },
+            result => panic!("Expected insufficient balance error, got {:?}", result)
+        }
+        assert_eq!(smc.get_header(shard_id(1), ChunkPeriodHash::zero()).unwrap(), None);
+
+        let header = Header::new(shard_id(1), ChunkRootHash::from([7; 32]), ChunkPeriodHash::zero(), address(1),
+                                 ProposerBidHash::from(60));
+        smc.add_header(&header).unwrap();
+        assert_eq!(smc.proposer_balance(address(1)).unwrap(), Wei::from(40));
+
+        // Rejected headers aren't debited
+        assert!(smc.add_header(&header).is_err());
+        assert_eq!(smc.proposer_balance(address(1)).unwrap(), Wei::from(40));
+    }
+
+    #[test]
+    fn it_counts_votes_on_added_header() {
+        let (smc, header) = generate_smc_with_header();
+        smc.register_notary(address(3), NOTARY_DEPOSIT).unwrap();
+        let period = smc.current_period().unwrap();
+        let vote = Vote::for_header(&header, NotaryIdHash::zero(), true);
+
+        smc.submit_vote(address(2), &vote).unwrap();
+        assert_eq!(smc.get_vote_count(shard_id(1), period).unwrap(), 1);
+        match smc.submit_vote(address(2), &vote) {
+            Err(Error(ErrorKind::AlreadyVoted(..), _)) => {},
+            result => panic!("Expected already voted error, got {:?}", result)
+        }
+
+        // A vote that the body is unavailable isn't counted.
+        let unavailable_vote = Vote { available: false, ..vote.clone() };
+        smc.submit_vote(address(3), &unavailable_vote).unwrap();
+        assert_eq!(smc.get_vote_count(shard_id(1), period).unwrap(), 1);
+
+        // It still is the notary's only vote in the shard and period
+        match smc.submit_vote(address(3), &vote) {
+            Err(Error(ErrorKind::AlreadyVoted(notary, ..), _)) => assert_eq!(notary, address(3)),
+            result => panic!("Expected already voted error, got {:?}", result)
+        }
+        assert_eq!(smc.get_vote_count(shard_id(1), period).unwrap(), 1);
+    }
+
+    #[test]
+    fn it_rejects_invalid_votes() {
+        let (smc, header) = generate_smc_with_header();
+        let vote = Vote::for_header(&header, NotaryIdHash::zero(), true);
+        match smc.submit_vote(address(9), &vote) {
+            Err(Error(ErrorKind::NotaryNotInCommittee(..), _)) => {},
+            result => panic!("Expected notary not in committee error, got {:?}", result)
+        }
+
+        let other_header = generate_header(shard_id(1), header.period(), address(2));
+        match smc.submit_vote(address(2), &Vote::for_header(&other_header, NotaryIdHash::zero(), true)) {
+            Err(Error(ErrorKind::UnknownHeader(..), _)) => {},
+            result => panic!("Expected unknown header error, got {:?}", result)
+        }
+
+        let next_period_vote = Vote { period: header.period() + ChunkPeriodHash::from(1), ..vote.clone() };
+        match smc.submit_vote(address(2), &next_period_vote) {
+            Err(Error(ErrorKind::WrongPeriod(..), _)) => {},
+            result => panic!("Expected wrong period error, got {:?}", result)
+        }
+    }
+}
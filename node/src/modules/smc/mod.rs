@@ -0,0 +1,6 @@
+pub mod abi;
+pub mod backend;
+pub mod in_memory;
+pub mod rpc;
+pub mod rpc_backend;
+pub mod signer;
@@ -0,0 +1,431 @@
+use ethereum_types::{Address, H256, U256};
+use modules::errors::*;
+use modules::hex::{from_hex, to_hex};
+
+use serde_json::Value;
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+/// How long the client waits to connect to, write to or read from the node.
+const RPC_TIMEOUT_SECS: u64 = 30;
+
+/// A filter for `eth_getLogs`: logs of the contract at `address` in the block range,
+/// whose topics match `topics`, where `None` matches any topic.
+#[derive(PartialEq, Debug, Clone)]
+pub struct LogFilter {
+    pub address: Address,
+    pub topics: Vec<Option<H256>>,
+    pub from_block: u64,
+    pub to_block: u64
+}
+
+/// An event log from `eth_getLogs`.
+#[derive(PartialEq, Debug, Clone)]
+pub struct Log {
+    pub address: Address,
+    pub topics: Vec<H256>,
+    pub data: Vec<u8>,
+    pub block_number: u64
+}
+
+/// A client for the JSON-RPC API of an Ethereum node over plain HTTP, e.g. a local
+/// dev chain.
+pub struct JsonRpcClient {
+    // host:port to connect to
+    host: String,
+    path: String,
+    next_id: AtomicUsize
+}
+
+impl JsonRpcClient {
+    /// Creates a client for the node at an `http://host:port/path` URL
+    pub fn new(url: &str) -> Result<JsonRpcClient> {
+        let without_scheme = match url.strip_prefix("http://") {
+            Some(without_scheme) => without_scheme,
+            None => bail!(ErrorKind::InvalidRpcUrl(url.to_string()))
+        };
+        let (host, path) = match without_scheme.find('/') {
+            Some(index) => without_scheme.split_at(index),
+            None => (without_scheme, "/")
+        };
+        let host = if host.contains(':') { host.to_string() } else { format!("{}:80", host) };
+        Ok(JsonRpcClient {
+            host,
+            path: path.to_string(),
+            next_id: AtomicUsize::new(1)
+        })
+    }
+
+    /// Calls a JSON-RPC method and returns its result
+    pub fn request(&self, method: &str, params: Value) -> Result<Value> {
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst);
+        let request = json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "method": method,
+            "params": params
+        });
+        trace!("Sending JSON-RPC request {}", request);
+        let response: Value = ::serde_json::from_slice(&self.post(&request.to_string())?)?;
+
+        if let Some(error) = response.get("error") {
+            let code = error.get("code").and_then(Value::as_i64).unwrap_or(0);
+            let message = error.get("message").and_then(Value::as_str).unwrap_or("").to_string();
+            bail!(ErrorKind::RpcError(code, message));
+        }
+        match response.get("result") {
+            Some(result) => Ok(result.clone()),
+            None => bail!(ErrorKind::InvalidRpcResponse(format!("No result in {}", response)))
+        }
+    }
+
+    /// `eth_blockNumber`: the number of the latest block
+    pub fn block_number(&self) -> Result<u64> {
+        let block_number = parse_quantity(&self.request("eth_blockNumber", json!([]))?)?;
+        Ok(block_number.low_u64())
+    }
+
+    /// `eth_call`: the return data of calling the contract at `to` with `data` in the
+    /// latest block
+    pub fn call(&self, to: &Address, data: &[u8]) -> Result<Vec<u8>> {
+        let result = self.request("eth_call", json!([
+            { "to": to_hex(&to[..]), "data": to_hex(data) },
+            "latest"
+        ]))?;
+        parse_data(&result)
+    }
+
+    /// `eth_getLogs`: the logs matching the filter
+    pub fn get_logs(&self, filter: &LogFilter) -> Result<Vec<Log>> {
+        let topics: Vec<Value> = filter.topics.iter()
+            .map(|topic| match *topic {
+                Some(ref topic) => Value::String(to_hex(&topic[..])),
+                None => Value::Null
+            })
+            .collect();
+        let result = self.request("eth_getLogs", json!([{
+            "address": to_hex(&filter.address[..]),
+            "topics": topics,
+            "fromBlock": format!("{:#x}", filter.from_block),
+            "toBlock": format!("{:#x}", filter.to_block)
+        }]))?;
+        match result.as_array() {
+            Some(logs) => logs.iter().map(parse_log).collect(),
+            None => bail!(ErrorKind::InvalidRpcResponse(format!("Expected a list of logs, got {}", result)))
+        }
+    }
+
+    /// `eth_getTransactionCount`: the nonce of the next transaction from `address`,
+    /// counting pending transactions
+    pub fn transaction_count(&self, address: &Address) -> Result<U256> {
+        parse_quantity(&self.request("eth_getTransactionCount", json!([to_hex(&address[..]), "pending"]))?)
+    }
+
+    /// `eth_sendRawTransaction`: sends a signed transaction and returns its hash
+    pub fn send_raw_transaction(&self, raw_transaction: &[u8]) -> Result<H256> {
+        let result = self.request("eth_sendRawTransaction", json!([to_hex(raw_transaction)]))?;
+        let hash = parse_data(&result)?;
+        if hash.len() != 32 {
+            bail!(ErrorKind::InvalidRpcResponse(format!("Expected a transaction hash, got {}", result)));
+        }
+        Ok(H256::from(&hash[..]))
+    }
+
+    /// Posts the body to the node and returns the body of the response
+    fn post(&self, body: &str) -> Result<Vec<u8>> {
+        let timeout = Some(Duration::from_secs(RPC_TIMEOUT_SECS));
+        let mut stream = TcpStream::connect(&self.host[..])?;
+        stream.set_read_timeout(timeout)?;
+        stream.set_write_timeout(timeout)?;
+        write!(stream, "POST {} HTTP/1.1\r\nHost: {}\r\nContent-Type: application/json\r\n\
+                        Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+               self.path, self.host, body.len(), body)?;
+        stream.flush()?;
+
+        let mut response = vec![];
+        stream.read_to_end(&mut response)?;
+        parse_http_response(&response)
+    }
+}
+
+/// Splits an HTTP response into its head and body, and checks its status
+fn parse_http_response(response: &[u8]) -> Result<Vec<u8>> {
+    let head_end = match response.windows(4).position(|window| window == b"\r\n\r\n") {
+        Some(head_end) => head_end,
+        None => bail!(ErrorKind::InvalidRpcResponse(String::from("Incomplete HTTP response")))
+    };
+    let head = String::from_utf8_lossy(&response[..head_end]).to_lowercase();
+    let body = &response[head_end + 4..];
+
+    let status = head.split_whitespace().nth(1).unwrap_or("");
+    if status != "200" {
+        bail!(ErrorKind::InvalidRpcResponse(format!("HTTP status {}", status)));
+    }
+    if head.lines().any(|line| line.starts_with("transfer-encoding:") && line.contains("chunked")) {
+        return decode_chunked(body);
+    }
+    Ok(body.to_vec())
+}
+
+/// Decodes a body sent with chunked transfer encoding
+fn decode_chunked(mut body: &[u8]) -> Result<Vec<u8>> {
+    let mut decoded = vec![];
+    loop {
+        let line_end = match body.windows(2).position(|window| window == b"\r\n") {
+            Some(line_end) => line_end,
+            None => bail!(ErrorKind::InvalidRpcResponse(String::from("Incomplete chunked body")))
+        };
+        let size_line = String::from_utf8_lossy(&body[..line_end]);
+        let size_hex = size_line.split(';').next().unwrap_or("").trim();
+        let size = match usize::from_str_radix(size_hex, 16) {
+            Ok(size) => size,
+            Err(_) => bail!(ErrorKind::InvalidRpcResponse(format!("Invalid chunk size {}", size_hex)))
+        };
+        body = &body[line_end + 2..];
+        if size == 0 {
+            return Ok(decoded);
+        }
+        if body.len() < size {
+            bail!(ErrorKind::InvalidRpcResponse(String::from("Incomplete chunked body")));
+        }
+        decoded.extend_from_slice(&body[..size]);
+        body = &body[(size + 2).min(body.len())..];
+    }
+}
+
+fn parse_data(value: &Value) -> Result<Vec<u8>> {
+    match value.as_str() {
+        Some(hex) => from_hex(hex),
+        None => bail!(ErrorKind::InvalidRpcResponse(format!("Expected hex data, got {}", value)))
+    }
+}
+
+fn parse_quantity(value: &Value) -> Result<U256> {
+    let bytes = parse_data(value)?;
+    if bytes.len() > 32 {
+        bail!(ErrorKind::InvalidRpcResponse(format!("Quantity {} is too large", value)));
+    }
+    Ok(U256::from(&bytes[..]))
+}
+
+fn parse_log(value: &Value) -> Result<Log> {
+    let field = |name: &str| -> Result<&Value> {
+        match value.get(name) {
+            Some(field) => Ok(field),
+            None => bail!(ErrorKind::InvalidRpcResponse(format!("No {} in log {}", name, value)))
+        }
+    };
+    let address = parse_data(field("address")?)?;
+    if address.len() != 20 {
+        bail!(ErrorKind::InvalidRpcResponse(format!("Invalid address in log {}", value)));
+    }
+    let topics = match field("topics")?.as_array() {
+        Some(topics) => topics.iter()
+            .map(|topic| {
+                let topic = parse_data(topic)?;
+                if topic.len() != 32 {
+                    bail!(ErrorKind::InvalidRpcResponse(format!("Invalid topic in log {}", value)));
+                }
+                Ok(H256::from(&topic[..]))
+            })
+            .collect::<Result<Vec<H256>>>()?,
+        None => bail!(ErrorKind::InvalidRpcResponse(format!("Invalid topics in log {}", value)))
+    };
+    Ok(Log {
+        address: Address::from(&address[..]),
+        topics,
+        data: parse_data(field("data")?)?,
+        block_number: parse_quantity(field("blockNumber")?)?.low_u64()
+    })
+}
+
+/// A stand-in for the JSON-RPC server of an Ethereum node, for tests. It answers each
+/// request with the result or error of its handler, and records the requests.
+#[cfg(test)]
+pub mod stand_in {
+    use serde_json::Value;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::{TcpListener, TcpStream};
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+
+    pub type Handler = dyn Fn(&str, &Value) -> ::std::result::Result<Value, (i64, String)> + Send + Sync;
+
+    pub struct StandInServer {
+        pub url: String,
+        requests: Arc<Mutex<Vec<(String, Value)>>>
+    }
+
+    impl StandInServer {
+        /// Starts serving on a free local port. The server thread runs until the test ends.
+        pub fn start(handler: Box<Handler>) -> StandInServer {
+            let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            let url = format!("http://{}/", listener.local_addr().unwrap());
+            let requests = Arc::new(Mutex::new(vec![]));
+            let server_requests = requests.clone();
+            let handler: Arc<Handler> = Arc::from(handler);
+            thread::spawn(move || {
+                for stream in listener.incoming() {
+                    let stream = match stream {
+                        Ok(stream) => stream,
+                        Err(_) => continue
+                    };
+                    serve(stream, &*handler, &server_requests);
+                }
+            });
+            StandInServer {
+                url,
+                requests
+            }
+        }
+
+        /// The method and params of each request so far
+        pub fn requests(&self) -> Vec<(String, Value)> {
+            self.requests.lock().unwrap().clone()
+        }
+    }
+
+    fn serve(stream: TcpStream, handler: &Handler, requests: &Mutex<Vec<(String, Value)>>) {
+        let mut reader = BufReader::new(stream);
+        let mut content_length = 0;
+        loop {
+            let mut line = String::new();
+            if reader.read_line(&mut line).unwrap_or(0) == 0 {
+                return;
+            }
+            let line = line.trim_end().to_lowercase();
+            if line.is_empty() {
+                break;
+            }
+            if let Some(length) = line.strip_prefix("content-length:") {
+                content_length = length.trim().parse().unwrap();
+            }
+        }
+        let mut body = vec![0; content_length];
+        reader.read_exact(&mut body).unwrap();
+
+        let request: Value = ::serde_json::from_slice(&body).unwrap();
+        let method = request["method"].as_str().unwrap().to_string();
+        let params = request["params"].clone();
+        requests.lock().unwrap().push((method.clone(), params.clone()));
+        let response = match handler(&method, &params) {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": request["id"], "result": result }),
+            Err((code, message)) => json!({
+                "jsonrpc": "2.0", "id": request["id"], "error": { "code": code, "message": message }
+            })
+        }.to_string();
+        let mut stream = reader.into_inner();
+        let _result = write!(stream, "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\n\
+                                      Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+                             response.len(), response);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::stand_in::StandInServer;
+
+    #[test]
+    fn it_parses_urls() {
+        let client = JsonRpcClient::new("http://localhost:8545").unwrap();
+        assert_eq!((client.host.as_str(), client.path.as_str()), ("localhost:8545", "/"));
+        let client = JsonRpcClient::new("http://node/rpc").unwrap();
+        assert_eq!((client.host.as_str(), client.path.as_str()), ("node:80", "/rpc"));
+        match JsonRpcClient::new("https://node") {
+            Err(Error(ErrorKind::InvalidRpcUrl(url), _)) => assert_eq!(url, "https://node"),
+            result => panic!("Expected invalid RPC URL error, got {:?}", result.map(|_| ()))
+        }
+    }
+
+    #[test]
+    fn it_decodes_chunked_responses() {
+        let response = b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n4\r\n{\"a\"\r\n3\r\n:1}\r\n0\r\n\r\n";
+        assert_eq!(parse_http_response(response).unwrap(), b"{\"a\":1}".to_vec());
+        assert!(parse_http_response(b"HTTP/1.1 500 Internal Server Error\r\n\r\n").is_err());
+    }
+
+    #[test]
+    fn it_gets_block_number() {
+        let server = StandInServer::start(Box::new(|method, _| {
+            assert_eq!(method, "eth_blockNumber");
+            Ok(json!("0x1b4"))
+        }));
+        let client = JsonRpcClient::new(&server.url).unwrap();
+        assert_eq!(client.block_number().unwrap(), 436);
+    }
+
+    #[test]
+    fn it_calls_contracts() {
+        let server = StandInServer::start(Box::new(|_, _| Ok(json!("0x0102"))));
+        let client = JsonRpcClient::new(&server.url).unwrap();
+        let contract = Address::from([0x11; 20]);
+        assert_eq!(client.call(&contract, &[0xde, 0xad]).unwrap(), vec![0x01, 0x02]);
+
+        let (method, params) = server.requests()[0].clone();
+        assert_eq!(method, "eth_call");
+        assert_eq!(params, json!([
+            { "to": "0x1111111111111111111111111111111111111111", "data": "0xdead" },
+            "latest"
+        ]));
+    }
+
+    #[test]
+    fn it_gets_logs() {
+        let server = StandInServer::start(Box::new(|_, _| Ok(json!([{
+            "address": "0x1111111111111111111111111111111111111111",
+            "topics": ["0x2222222222222222222222222222222222222222222222222222222222222222"],
+            "data": "0x0a",
+            "blockNumber": "0x10"
+        }]))));
+        let client = JsonRpcClient::new(&server.url).unwrap();
+        let filter = LogFilter {
+            address: Address::from([0x11; 20]),
+            topics: vec![Some(H256::from([0x22; 32])), None],
+            from_block: 16,
+            to_block: 20
+        };
+        let logs = client.get_logs(&filter).unwrap();
+        assert_eq!(logs, vec![Log {
+            address: Address::from([0x11; 20]),
+            topics: vec![H256::from([0x22; 32])],
+            data: vec![0x0a],
+            block_number: 16
+        }]);
+
+        let (_, params) = server.requests()[0].clone();
+        assert_eq!(params[0]["topics"][1], Value::Null);
+        assert_eq!(params[0]["fromBlock"], json!("0x10"));
+        assert_eq!(params[0]["toBlock"], json!("0x14"));
+    }
+
+    #[test]
+    fn it_sends_raw_transactions() {
+        let server = StandInServer::start(Box::new(|method, params| match method {
+            "eth_getTransactionCount" => Ok(json!("0x7")),
+            "eth_sendRawTransaction" => {
+                assert_eq!(params[0], json!("0xf8"));
+                Ok(json!(to_hex(&[0x33; 32])))
+            },
+            _ => Err((-32601, String::from("Method not found")))
+        }));
+        let client = JsonRpcClient::new(&server.url).unwrap();
+        assert_eq!(client.transaction_count(&Address::zero()).unwrap(), U256::from(7));
+        assert_eq!(client.send_raw_transaction(&[0xf8]).unwrap(), H256::from([0x33; 32]));
+    }
+
+    #[test]
+    fn it_returns_rpc_errors() {
+        let server = StandInServer::start(Box::new(|_, _| Err((-32000, String::from("execution reverted")))));
+        let client = JsonRpcClient::new(&server.url).unwrap();
+        match client.block_number() {
+            Err(Error(ErrorKind::RpcError(code, message), _)) => {
+                assert_eq!(code, -32000);
+                assert_eq!(message, "execution reverted");
+            },
+            result => panic!("Expected an RPC error, got {:?}", result)
+        }
+    }
+}
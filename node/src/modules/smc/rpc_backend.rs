@@ -0,0 +1,580 @@
+use ethereum_types::{Address, H256, U256};
+use modules::collation::header::Header;
+use modules::constants::{COMMITTEE_SIZE, NOTARY_DEPOSIT, PERIOD_LENGTH, PROPOSER_DEPOSIT, SHARD_COUNT};
+use modules::errors::*;
+use modules::primitives::{
+    ShardIdHash,
+    ChunkRootHash,
+    ChunkPeriodHash,
+    NotaryAddress,
+    ProposerAddress,
+    Wei
+};
+use modules::smc::abi::{self, HeaderAdded, SmcCall, Token};
+use modules::smc::backend::SmcBackend;
+use modules::smc::rpc::{JsonRpcClient, LogFilter};
+use modules::smc::signer::{Transaction, TransactionSigner};
+use modules::vote::Vote;
+
+/// Gas limit of the transactions sent to the SMC
+const SMC_GAS_LIMIT: u64 = 1_000_000;
+/// Gas price of the transactions sent to the SMC, 1 gwei
+const SMC_GAS_PRICE: u64 = 1_000_000_000;
+
+/// A sharding manager contract on an Ethereum node, read with `eth_call` and
+/// `eth_getLogs` and changed with signed transactions from the signer's account.
+///
+/// The period is the latest block number divided by the period length, PERIOD_LENGTH
+/// by default. Headers are read
+/// from the contract's `HeaderAdded(uint256 indexed shard_id, uint256 period,
+/// bytes32 chunk_root, address proposer, uint256 bid)` events.
+pub struct RpcSmc {
+    client: JsonRpcClient,
+    contract: Address,
+    signer: Box<dyn TransactionSigner + Send>,
+    // Number of blocks in a period
+    period_length: u64
+}
+
+impl RpcSmc {
+    pub fn new(client: JsonRpcClient, contract: Address, signer: Box<dyn TransactionSigner + Send>) -> RpcSmc {
+        RpcSmc {
+            client,
+            contract,
+            signer,
+            period_length: PERIOD_LENGTH
+        }
+    }
+
+    /// Sets the number of blocks in a period, which must match the contract's. A
+    /// `PeriodClock` on the contract must use the same length.
+    pub fn set_period_length(&mut self, period_length: u64) {
+        assert!(period_length > 0, "A period must have at least one block");
+        self.period_length = period_length;
+    }
+
+    pub fn period_length(&self) -> u64 {
+        self.period_length
+    }
+
+    fn call(&self, call: &SmcCall) -> Result<Vec<u8>> {
+        self.client.call(&self.contract, &call.encode())
+    }
+
+    /// Sends a transaction calling the contract from `sender`, which must be the signer's account
+    fn transact(&self, sender: Address, call: &SmcCall, value: Wei) -> Result<H256> {
+        if sender != self.signer.address() {
+            bail!(ErrorKind::UnknownAccount(sender));
+        }
+        let transaction = Transaction {
+            nonce: self.client.transaction_count(&sender)?,
+            gas_price: Wei::from(SMC_GAS_PRICE),
+            gas: U256::from(SMC_GAS_LIMIT),
+            to: self.contract,
+            value,
+            data: call.encode()
+        };
+        let transaction_hash = self.client.send_raw_transaction(&self.signer.sign_transaction(&transaction)?)?;
+        debug!("Sent transaction {:?} calling {} on SMC Contract", transaction_hash, call.signature());
+        Ok(transaction_hash)
+    }
+
+    /// The headers added in the period from its `HeaderAdded` events, in the order they
+    /// were logged, with one `eth_getLogs` request for the shard or for all shards.
+    fn added_headers(&self, period: ChunkPeriodHash, shard_id: Option<ShardIdHash>) -> Result<Vec<Header>> {
+        let mut topics = vec![Some(HeaderAdded::topic())];
+        if let Some(shard_id) = shard_id {
+            topics.push(Some(H256::from(Token::Uint(shard_id).encode())));
+        }
+        // Headers are added during their period, so only the period's blocks are searched.
+        let from_block = period.low_u64() * self.period_length;
+        let filter = LogFilter {
+            address: self.contract,
+            topics,
+            from_block,
+            to_block: from_block + self.period_length - 1
+        };
+        let mut headers = vec![];
+        for log in self.client.get_logs(&filter)? {
+            let event = HeaderAdded::decode(&log)?;
+            if event.period == period {
+                headers.push(event.header());
+            }
+        }
+        Ok(headers)
+    }
+
+    fn deposit(&self, call: &SmcCall) -> Result<Option<Wei>> {
+        let deposit = abi::decode_uint(&self.call(call)?)?;
+        Ok(if deposit.is_zero() { None } else { Some(deposit) })
+    }
+}
+
+impl SmcBackend for RpcSmc {
+    fn register_notary(&self, notary: NotaryAddress, deposit: Wei) -> Result<()> {
+        if self.notary_deposit(notary)?.is_some() {
+            bail!(ErrorKind::NotaryAlreadyRegistered(notary));
+        }
+        if deposit < NOTARY_DEPOSIT {
+            bail!(ErrorKind::DepositTooLow(deposit, NOTARY_DEPOSIT));
+        }
+        self.transact(notary, &SmcCall::RegisterNotary, deposit)?;
+        Ok(())
+    }
+
+    fn deregister_notary(&self, notary: NotaryAddress) -> Result<Wei> {
+        let deposit = match self.notary_deposit(notary)? {
+            Some(deposit) => deposit,
+            None => bail!(ErrorKind::NotaryNotRegistered(notary))
+        };
+        self.transact(notary, &SmcCall::DeregisterNotary, Wei::zero())?;
+        Ok(deposit)
+    }
+
+    fn notary_deposit(&self, notary: NotaryAddress) -> Result<Option<Wei>> {
+        self.deposit(&SmcCall::GetNotaryDeposit { notary })
+    }
+
+    fn register_proposer(&self, proposer: ProposerAddress, deposit: Wei) -> Result<()> {
+        if self.proposer_deposit(proposer)?.is_some() {
+            bail!(ErrorKind::ProposerAlreadyRegistered(proposer));
+        }
+        if deposit < PROPOSER_DEPOSIT {
+            bail!(ErrorKind::DepositTooLow(deposit, PROPOSER_DEPOSIT));
+        }
+        self.transact(proposer, &SmcCall::RegisterProposer, deposit)?;
+        Ok(())
+    }
+
+    fn deregister_proposer(&self, proposer: ProposerAddress) -> Result<Wei> {
+        let deposit = match self.proposer_deposit(proposer)? {
+            Some(deposit) => deposit,
+            None => bail!(ErrorKind::ProposerNotRegistered(proposer))
+        };
+        self.transact(proposer, &SmcCall::DeregisterProposer, Wei::zero())?;
+        Ok(deposit)
+    }
+
+    fn proposer_deposit(&self, proposer: ProposerAddress) -> Result<Option<Wei>> {
+        self.deposit(&SmcCall::GetProposerDeposit { proposer })
+    }
+
+    fn add_proposer_balance(&self, proposer: ProposerAddress, amount: Wei) -> Result<()> {
+        if self.proposer_deposit(proposer)?.is_none() {
+            bail!(ErrorKind::ProposerNotRegistered(proposer));
+        }
+        self.transact(proposer, &SmcCall::AddProposerBalance, amount)?;
+        Ok(())
+    }
+
+    fn withdraw_proposer_balance(&self, proposer: ProposerAddress) -> Result<Wei> {
+        let balance = self.proposer_balance(proposer)?;
+        self.transact(proposer, &SmcCall::WithdrawProposerBalance, Wei::zero())?;
+        Ok(balance)
+    }
+
+    fn proposer_balance(&self, proposer: ProposerAddress) -> Result<Wei> {
+        abi::decode_uint(&self.call(&SmcCall::GetProposerBalance { proposer })?)
+    }
+
+    fn current_period(&self) -> Result<ChunkPeriodHash> {
+        Ok(ChunkPeriodHash::from(self.client.block_number()? / self.period_length))
+    }
+
+    fn get_notary_committee(&self, shard_id: ShardIdHash) -> Result<Vec<NotaryAddress>> {
+        // The contract returns the zero address after the last notary of the committee.
+        let mut committee = vec![];
+        for index in 0..COMMITTEE_SIZE {
+            let notary = abi::decode_address(&self.call(&SmcCall::GetNotaryInCommittee {
+                shard_id,
+                index: U256::from(index)
+            })?)?;
+            if notary.is_zero() {
+                break;
+            }
+            committee.push(notary);
+        }
+        Ok(committee)
+    }
+
+    fn get_notary_shards(&self, notary: NotaryAddress) -> Result<Vec<ShardIdHash>> {
+        let shards = abi::decode_uint(&self.call(&SmcCall::GetNotaryShards { notary })?)?;
+        Ok((0..SHARD_COUNT)
+            .filter(|shard| shards.bit(*shard))
+            .map(ShardIdHash::from)
+            .collect())
+    }
+
+    fn add_header(&self, header: &Header) -> Result<()> {
+        let period = self.current_period()?;
+        if header.period() != period {
+            bail!(ErrorKind::WrongPeriod(header.period(), period));
+        }
+        if self.get_header(header.shard_id, period)?.is_some() {
+            bail!(ErrorKind::HeaderAlreadyAdded(header.shard_id, period));
+        }
+        let balance = self.proposer_balance(header.proposer_address())?;
+        if balance < header.proposer_bid() {
+            bail!(ErrorKind::InsufficientBalance(header.proposer_address(), header.proposer_bid(), balance));
+        }
+        let call = SmcCall::AddHeader {
+            shard_id: header.shard_id,
+            period,
+            chunk_root: header.chunk_root(),
+            bid: header.proposer_bid()
+        };
+        self.transact(header.proposer_address(), &call, Wei::zero())?;
+        Ok(())
+    }
+
+    fn get_header(&self, shard_id: ShardIdHash, period: ChunkPeriodHash) -> Result<Option<Header>> {
+        Ok(self.added_headers(period, Some(shard_id))?.into_iter().next())
+    }
+
+    fn get_headers(&self, period: ChunkPeriodHash) -> Result<Vec<Header>> {
+        let mut headers = self.added_headers(period, None)?;
+        // The contract adds one header per shard, so a later event of a shard is ignored.
+        headers.sort_by_key(|header| header.shard_id);
+        headers.dedup_by_key(|header| header.shard_id);
+        Ok(headers)
+    }
+
+    fn submit_vote(&self, notary: NotaryAddress, vote: &Vote) -> Result<()> {
+        let period = self.current_period()?;
+        if vote.period != period {
+            bail!(ErrorKind::WrongPeriod(vote.period, period));
+        }
+        let index = match self.get_notary_committee(vote.shard_id)?.iter().position(|member| *member == notary) {
+            Some(index) => index,
+            None => bail!(ErrorKind::NotaryNotInCommittee(notary, vote.shard_id))
+        };
+        let header = match self.get_header(vote.shard_id, period)? {
+            Some(ref header) if header.hash() == vote.header_hash => header.clone(),
+            _ => bail!(ErrorKind::UnknownHeader(vote.header_hash))
+        };
+        let call = SmcCall::SubmitVote {
+            shard_id: vote.shard_id,
+            period,
+            index: U256::from(index),
+            chunk_root: header.chunk_root(),
+            available: vote.available
+        };
+        self.transact(notary, &call, Wei::zero())?;
+        Ok(())
+    }
+
+    fn get_vote_count(&self, shard_id: ShardIdHash, period: ChunkPeriodHash) -> Result<usize> {
+        let count = abi::decode_uint(&self.call(&SmcCall::GetVoteCount { shard_id, period })?)?;
+        Ok(count.low_u64() as usize)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use modules::primitives::{NotaryIdHash, ProposerBidHash};
+    use modules::smc::abi::selector;
+    use modules::hex::{from_hex, to_hex};
+    use modules::smc::rpc::stand_in::StandInServer;
+    use modules::smc::signer::LocalSigner;
+    use serde_json::Value;
+
+    fn contract() -> Address {
+        Address::from([0xcc; 20])
+    }
+
+    fn generate_signer() -> LocalSigner {
+        LocalSigner::new(&H256::from([0x46; 32]), 1).unwrap()
+    }
+
+    fn generate_rpc_smc(server: &StandInServer) -> RpcSmc {
+        RpcSmc::new(JsonRpcClient::new(&server.url).unwrap(), contract(), Box::new(generate_signer()))
+    }
+
+    fn word_hex(token: Token) -> Value {
+        json!(to_hex(&token.encode()))
+    }
+
+    /// The call data of an `eth_call` request
+    fn call_data(params: &Value) -> Vec<u8> {
+        from_hex(params[0]["data"].as_str().unwrap()).unwrap()
+    }
+
+    /// The raw transactions sent to the stand-in server
+    fn sent_transactions(server: &StandInServer) -> Vec<Vec<u8>> {
+        server.requests().iter()
+            .filter(|&(method, _)| method == "eth_sendRawTransaction")
+            .map(|(_, params)| from_hex(params[0].as_str().unwrap()).unwrap())
+            .collect()
+    }
+
+    fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+        haystack.windows(needle.len()).any(|window| window == needle)
+    }
+
+    /// Answers like a node where no one is registered, at block 12 (period 2)
+    fn empty_contract(method: &str, params: &Value) -> ::std::result::Result<Value, (i64, String)> {
+        match method {
+            "eth_blockNumber" => Ok(json!("0xc")),
+            "eth_call" => Ok(word_hex(Token::Uint(U256::zero()))),
+            "eth_getLogs" => Ok(json!([])),
+            "eth_getTransactionCount" => Ok(json!("0x0")),
+            "eth_sendRawTransaction" => Ok(json!(to_hex(&[0x77; 32]))),
+            _ => Err((-32601, String::from("Method not found")))
+        }
+    }
+
+    #[test]
+    fn it_gets_period_from_block_number() {
+        let server = StandInServer::start(Box::new(empty_contract));
+        let smc = generate_rpc_smc(&server);
+        assert_eq!(smc.current_period().unwrap(), ChunkPeriodHash::from(12 / PERIOD_LENGTH));
+    }
+
+    #[test]
+    fn it_uses_the_configured_period_length() {
+        let server = StandInServer::start(Box::new(empty_contract));
+        let mut smc = generate_rpc_smc(&server);
+        smc.set_period_length(4);
+        assert_eq!(smc.period_length(), 4);
+        assert_eq!(smc.current_period().unwrap(), ChunkPeriodHash::from(3));
+
+        // Headers of a period are searched in its blocks
+        assert_eq!(smc.get_headers(ChunkPeriodHash::from(2)).unwrap(), vec![]);
+        let (_, params) = server.requests()[1].clone();
+        assert_eq!(params[0]["fromBlock"], json!("0x8"));
+        assert_eq!(params[0]["toBlock"], json!("0xb"));
+    }
+
+    #[test]
+    fn it_registers_notary_with_a_transaction() {
+        let server = StandInServer::start(Box::new(empty_contract));
+        let smc = generate_rpc_smc(&server);
+        let notary = generate_signer().address();
+        smc.register_notary(notary, NOTARY_DEPOSIT).unwrap();
+
+        let transactions = sent_transactions(&server);
+        assert_eq!(transactions.len(), 1);
+        assert!(contains(&transactions[0], &selector("register_notary()")));
+        assert!(contains(&transactions[0], &contract()[..]));
+        let mut deposit: [u8; 32] = [0; 32];
+        NOTARY_DEPOSIT.to_big_endian(&mut deposit);
+        assert!(contains(&transactions[0], &deposit[23..]));
+    }
+
+    #[test]
+    fn it_only_sends_transactions_from_the_signers_account() {
+        let server = StandInServer::start(Box::new(empty_contract));
+        let smc = generate_rpc_smc(&server);
+        match smc.register_notary(Address::from([1; 20]), NOTARY_DEPOSIT) {
+            Err(Error(ErrorKind::UnknownAccount(account), _)) => assert_eq!(account, Address::from([1; 20])),
+            result => panic!("Expected unknown account error, got {:?}", result)
+        }
+        assert!(sent_transactions(&server).is_empty());
+    }
+
+    #[test]
+    fn it_reads_deposits_and_rejects_registering_twice() {
+        let server = StandInServer::start(Box::new(|method, params| match method {
+            "eth_call" => {
+                assert_eq!(&call_data(params)[..4], &selector("get_notary_deposit(address)"));
+                Ok(word_hex(Token::Uint(NOTARY_DEPOSIT)))
+            },
+            _ => empty_contract(method, params)
+        }));
+        let smc = generate_rpc_smc(&server);
+        let notary = generate_signer().address();
+        assert_eq!(smc.notary_deposit(notary).unwrap(), Some(NOTARY_DEPOSIT));
+        assert_eq!(&call_data(&server.requests()[0].1)[4..], &Token::Address(notary).encode()[..]);
+        match smc.register_notary(notary, NOTARY_DEPOSIT) {
+            Err(Error(ErrorKind::NotaryAlreadyRegistered(..), _)) => {},
+            result => panic!("Expected notary already registered error, got {:?}", result)
+        }
+    }
+
+    #[test]
+    fn it_adds_headers_with_bids_covered_by_the_balance() {
+        let proposer = generate_signer().address();
+        let server = StandInServer::start(Box::new(|method, params| match method {
+            "eth_call" => {
+                assert_eq!(&call_data(params)[..4], &selector("get_proposer_balance(address)"));
+                Ok(word_hex(Token::Uint(U256::from(100))))
+            },
+            _ => empty_contract(method, params)
+        }));
+        let smc = generate_rpc_smc(&server);
+        assert_eq!(smc.proposer_balance(proposer).unwrap(), Wei::from(100));
+
+        let header = Header::new(ShardIdHash::from(4), ChunkRootHash::from([0x0b; 32]),
+                                 ChunkPeriodHash::from(2), proposer, ProposerBidHash::from(101));
+        match smc.add_header(&header) {
+            Err(Error(ErrorKind::InsufficientBalance(_, bid, balance), _)) => {
+                assert_eq!((bid, balance), (ProposerBidHash::from(101), Wei::from(100)));
+            },
+            result => panic!("Expected insufficient balance error, got {:?}", result)
+        }
+        assert!(sent_transactions(&server).is_empty());
+
+        let header = Header::new(ShardIdHash::from(4), ChunkRootHash::from([0x0b; 32]),
+                                 ChunkPeriodHash::from(2), proposer, ProposerBidHash::from(100));
+        smc.add_header(&header).unwrap();
+        let transactions = sent_transactions(&server);
+        assert_eq!(transactions.len(), 1);
+        assert!(contains(&transactions[0], &selector("add_header(uint256,uint256,bytes32,uint256)")));
+        assert!(contains(&transactions[0], &Token::Uint(ProposerBidHash::from(100)).encode()));
+    }
+
+    #[test]
+    fn it_reads_committee_until_zero_address() {
+        let server = StandInServer::start(Box::new(|method, params| match method {
+            "eth_call" => {
+                let data = call_data(params);
+                let index = abi::decode_uint(&data[36..68]).unwrap().low_u64() as u8;
+                Ok(word_hex(Token::Address(if index < 3 { Address::from([index + 1; 20]) } else { Address::zero() })))
+            },
+            _ => empty_contract(method, params)
+        }));
+        let smc = generate_rpc_smc(&server);
+        assert_eq!(smc.get_notary_committee(ShardIdHash::from(4)).unwrap(),
+                   vec![Address::from([1; 20]), Address::from([2; 20]), Address::from([3; 20])]);
+    }
+
+    #[test]
+    fn it_reads_the_shards_of_a_notary_with_one_call() {
+        let notary = Address::from([0xaa; 20]);
+        let server = StandInServer::start(Box::new(|method, params| match method {
+            "eth_call" => {
+                assert_eq!(&call_data(params)[..4], &selector("get_notary_shards(address)"));
+                let shards = (U256::from(1) << 3) | (U256::from(1) << 64) | (U256::from(1) << (SHARD_COUNT - 1));
+                Ok(word_hex(Token::Uint(shards)))
+            },
+            _ => empty_contract(method, params)
+        }));
+        let smc = generate_rpc_smc(&server);
+        assert_eq!(smc.get_notary_shards(notary).unwrap(),
+                   vec![ShardIdHash::from(3), ShardIdHash::from(64), ShardIdHash::from(SHARD_COUNT - 1)]);
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(&call_data(&requests[0].1)[4..], &Token::Address(notary).encode()[..]);
+    }
+
+    #[test]
+    fn it_reads_headers_from_events() {
+        let proposer = Address::from([0xaa; 20]);
+        let chunk_root = ChunkRootHash::from([0x0b; 32]);
+        let server = StandInServer::start(Box::new(move |method, params| match method {
+            "eth_getLogs" => {
+                let mut data = Token::Uint(U256::from(2)).encode().to_vec();
+                data.extend_from_slice(&chunk_root[..]);
+                data.extend_from_slice(&Token::Address(proposer).encode());
+                data.extend_from_slice(&Token::Uint(U256::from(70)).encode());
+                Ok(json!([{
+                    "address": to_hex(&contract()[..]),
+                    "topics": params[0]["topics"],
+                    "data": to_hex(&data),
+                    "blockNumber": "0xb"
+                }]))
+            },
+            _ => empty_contract(method, params)
+        }));
+        let smc = generate_rpc_smc(&server);
+        let header = smc.get_header(ShardIdHash::from(4), ChunkPeriodHash::from(2)).unwrap();
+        assert_eq!(header, Some(Header::new(ShardIdHash::from(4), chunk_root, ChunkPeriodHash::from(2), proposer,
+                                            ProposerBidHash::from(70))));
+
+        let (_, params) = server.requests()[0].clone();
+        assert_eq!(params[0]["fromBlock"], json!("0xa"));
+        assert_eq!(params[0]["toBlock"], json!("0xe"));
+        assert_eq!(params[0]["topics"][1], word_hex(Token::Uint(U256::from(4))));
+    }
+
+    #[test]
+    fn it_reads_headers_of_all_shards_with_one_request() {
+        let proposer = Address::from([0xaa; 20]);
+        let header = |shard_id: u64| Header::new(ShardIdHash::from(shard_id), ChunkRootHash::from([shard_id as u8; 32]),
+                                                 ChunkPeriodHash::from(2), proposer, ProposerBidHash::from(70));
+        let event_headers = [header(7), header(4)];
+        let server = StandInServer::start(Box::new(move |method, params| match method {
+            "eth_getLogs" => {
+                let logs: Vec<Value> = event_headers.iter()
+                    .map(|event_header| {
+                        let mut data = Token::Uint(event_header.period()).encode().to_vec();
+                        data.extend_from_slice(&event_header.chunk_root()[..]);
+                        data.extend_from_slice(&Token::Address(event_header.proposer_address()).encode());
+                        data.extend_from_slice(&Token::Uint(event_header.proposer_bid()).encode());
+                        json!({
+                            "address": to_hex(&contract()[..]),
+                            "topics": [to_hex(&HeaderAdded::topic()[..]), word_hex(Token::Uint(event_header.shard_id))],
+                            "data": to_hex(&data),
+                            "blockNumber": "0xb"
+                        })
+                    })
+                    .collect();
+                Ok(json!(logs))
+            },
+            _ => empty_contract(method, params)
+        }));
+        let smc = generate_rpc_smc(&server);
+        assert_eq!(smc.get_headers(ChunkPeriodHash::from(2)).unwrap(), vec![header(4), header(7)]);
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].1[0]["topics"], json!([to_hex(&HeaderAdded::topic()[..])]));
+    }
+
+    #[test]
+    fn it_submits_votes_on_added_headers() {
+        let signer_address = generate_signer().address();
+        let header = Header::new(ShardIdHash::from(4), ChunkRootHash::from([0x0b; 32]),
+                                 ChunkPeriodHash::from(2), Address::from([0xaa; 20]), ProposerBidHash::from(70));
+        let event_header = header.clone();
+        let server = StandInServer::start(Box::new(move |method, params| match method {
+            "eth_call" => {
+                let index = abi::decode_uint(&call_data(params)[36..68]).unwrap().low_u64();
+                Ok(word_hex(Token::Address(if index == 0 { signer_address } else { Address::zero() })))
+            },
+            "eth_getLogs" => {
+                let mut data = Token::Uint(event_header.period()).encode().to_vec();
+                data.extend_from_slice(&event_header.chunk_root()[..]);
+                data.extend_from_slice(&Token::Address(event_header.proposer_address()).encode());
+                data.extend_from_slice(&Token::Uint(event_header.proposer_bid()).encode());
+                Ok(json!([{
+                    "address": to_hex(&contract()[..]),
+                    "topics": params[0]["topics"],
+                    "data": to_hex(&data),
+                    "blockNumber": "0xb"
+                }]))
+            },
+            _ => empty_contract(method, params)
+        }));
+        let smc = generate_rpc_smc(&server);
+
+        let vote_call = |available| SmcCall::SubmitVote {
+            shard_id: header.shard_id,
+            period: header.period(),
+            index: U256::zero(),
+            chunk_root: header.chunk_root(),
+            available
+        }.encode();
+        let vote = Vote::for_header(&header, NotaryIdHash::zero(), true);
+        smc.submit_vote(signer_address, &vote).unwrap();
+        let transactions = sent_transactions(&server);
+        assert_eq!(transactions.len(), 1);
+        assert!(contains(&transactions[0], &selector("submit_vote(uint256,uint256,uint256,bytes32,bool)")));
+        assert!(contains(&transactions[0], &vote_call(true)));
+
+        // Votes that the body is unavailable are sent too, since they are the notary's vote.
+        smc.submit_vote(signer_address, &Vote { available: false, ..vote.clone() }).unwrap();
+        let transactions = sent_transactions(&server);
+        assert_eq!(transactions.len(), 2);
+        assert!(contains(&transactions[1], &vote_call(false)));
+
+        let other_header = Header::new(ShardIdHash::from(4), ChunkRootHash::zero(),
+                                       ChunkPeriodHash::from(2), Address::from([0xaa; 20]), ProposerBidHash::zero());
+        match smc.submit_vote(signer_address, &Vote::for_header(&other_header, NotaryIdHash::zero(), true)) {
+            Err(Error(ErrorKind::UnknownHeader(..), _)) => {},
+            result => panic!("Expected unknown header error, got {:?}", result)
+        }
+    }
+}
@@ -0,0 +1,180 @@
+use ethereum_types::{Address, H256, U256};
+use modules::errors::*;
+use modules::primitives::Wei;
+
+use libsecp256k1;
+use tiny_keccak;
+
+/// An Ethereum transaction to sign.
+#[derive(PartialEq, Debug, Clone)]
+pub struct Transaction {
+    pub nonce: U256,
+    pub gas_price: Wei,
+    pub gas: U256,
+    pub to: Address,
+    pub value: Wei,
+    pub data: Vec<u8>
+}
+
+/// Signs transactions from an account, for `eth_sendRawTransaction`.
+pub trait TransactionSigner {
+    /// The account that transactions are sent from
+    fn address(&self) -> Address;
+
+    /// The signed, RLP encoded transaction
+    fn sign_transaction(&self, transaction: &Transaction) -> Result<Vec<u8>>;
+}
+
+/// Signs transactions with a secret key held in memory, with EIP-155 replay protection
+/// for the given chain id.
+pub struct LocalSigner {
+    secret_key: libsecp256k1::SecretKey,
+    address: Address,
+    chain_id: u64
+}
+
+impl LocalSigner {
+    pub fn new(secret: &H256, chain_id: u64) -> Result<LocalSigner> {
+        let mut secret_bytes: [u8; 32] = [0; 32];
+        secret_bytes.copy_from_slice(&secret[..]);
+        let secret_key = match libsecp256k1::SecretKey::parse(&secret_bytes) {
+            Ok(secret_key) => secret_key,
+            Err(_) => bail!(ErrorKind::InvalidSecretKey)
+        };
+        // The address is the last 20 bytes of the hash of the uncompressed public key,
+        // without its 0x04 prefix.
+        let public_key = libsecp256k1::PublicKey::from_secret_key(&secret_key).serialize();
+        let public_key_hash = tiny_keccak::keccak256(&public_key[1..]);
+        Ok(LocalSigner {
+            secret_key,
+            address: Address::from(&public_key_hash[12..]),
+            chain_id
+        })
+    }
+}
+
+impl TransactionSigner for LocalSigner {
+    fn address(&self) -> Address {
+        self.address
+    }
+
+    fn sign_transaction(&self, transaction: &Transaction) -> Result<Vec<u8>> {
+        let mut fields = transaction_fields(transaction);
+        fields.push(rlp_u256(&U256::from(self.chain_id)));
+        fields.push(rlp_u256(&U256::zero()));
+        fields.push(rlp_u256(&U256::zero()));
+        let hash = tiny_keccak::keccak256(&rlp_list(&fields));
+
+        let message = libsecp256k1::Message::parse(&hash);
+        let (signature, recovery_id) = libsecp256k1::sign(&message, &self.secret_key);
+        let signature = signature.serialize();
+        let v = U256::from(recovery_id.serialize() as u64 + 35 + self.chain_id * 2);
+
+        let mut fields = transaction_fields(transaction);
+        fields.push(rlp_u256(&v));
+        fields.push(rlp_u256(&U256::from(&signature[..32])));
+        fields.push(rlp_u256(&U256::from(&signature[32..])));
+        Ok(rlp_list(&fields))
+    }
+}
+
+fn transaction_fields(transaction: &Transaction) -> Vec<Vec<u8>> {
+    vec![
+        rlp_u256(&transaction.nonce),
+        rlp_u256(&transaction.gas_price),
+        rlp_u256(&transaction.gas),
+        rlp_bytes(&transaction.to[..]),
+        rlp_u256(&transaction.value),
+        rlp_bytes(&transaction.data)
+    ]
+}
+
+/// The RLP length prefix of an item, where `offset` is 0x80 for strings and 0xc0 for lists
+fn rlp_length_prefix(length: usize, offset: u8) -> Vec<u8> {
+    if length < 56 {
+        return vec![offset + length as u8];
+    }
+    let length_bytes: Vec<u8> = (length as u64).to_be_bytes().iter()
+        .cloned()
+        .skip_while(|byte| *byte == 0)
+        .collect();
+    let mut prefix = vec![offset + 55 + length_bytes.len() as u8];
+    prefix.extend(length_bytes);
+    prefix
+}
+
+fn rlp_bytes(bytes: &[u8]) -> Vec<u8> {
+    if bytes.len() == 1 && bytes[0] < 0x80 {
+        return bytes.to_vec();
+    }
+    let mut encoded = rlp_length_prefix(bytes.len(), 0x80);
+    encoded.extend_from_slice(bytes);
+    encoded
+}
+
+/// A number is encoded as its big endian bytes without leading zeros
+fn rlp_u256(number: &U256) -> Vec<u8> {
+    let mut bytes: [u8; 32] = [0; 32];
+    number.to_big_endian(&mut bytes);
+    let first_non_zero = bytes.iter().position(|byte| *byte != 0).unwrap_or(32);
+    rlp_bytes(&bytes[first_non_zero..])
+}
+
+fn rlp_list(items: &[Vec<u8>]) -> Vec<u8> {
+    let length = items.iter().map(|item| item.len()).sum();
+    let mut encoded = rlp_length_prefix(length, 0xc0);
+    for item in items {
+        encoded.extend_from_slice(item);
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use modules::hex::{from_hex, to_hex};
+
+    #[test]
+    fn it_encodes_rlp() {
+        assert_eq!(rlp_bytes(b"dog"), vec![0x83, b'd', b'o', b'g']);
+        assert_eq!(rlp_bytes(&[]), vec![0x80]);
+        assert_eq!(rlp_bytes(&[0x0f]), vec![0x0f]);
+        assert_eq!(rlp_u256(&U256::zero()), vec![0x80]);
+        assert_eq!(rlp_u256(&U256::from(1024)), vec![0x82, 0x04, 0x00]);
+        assert_eq!(rlp_list(&[rlp_bytes(b"cat"), rlp_bytes(b"dog")]),
+                   vec![0xc8, 0x83, b'c', b'a', b't', 0x83, b'd', b'o', b'g']);
+
+        let long_string = vec![b'a'; 56];
+        let mut expected = vec![0xb8, 56];
+        expected.extend_from_slice(&long_string);
+        assert_eq!(rlp_bytes(&long_string), expected);
+    }
+
+    #[test]
+    fn it_signs_eip_155_example() {
+        // The example transaction of EIP-155
+        let signer = LocalSigner::new(&H256::from([0x46; 32]), 1).unwrap();
+        let transaction = Transaction {
+            nonce: U256::from(9),
+            gas_price: Wei::from(20_000_000_000u64),
+            gas: U256::from(21000),
+            to: Address::from([0x35; 20]),
+            value: Wei::from(1_000_000_000_000_000_000u64),
+            data: vec![]
+        };
+        let expected = "0xf86c098504a817c800825208943535353535353535353535353535353535353535880de0b6b3a7640000\
+                        8025a028ef61340bd939bc2195fe537567866003e1a15d3c71ff63e1590620aa636276a067cbe9d8997f\
+                        761aecb703304b3800ccf555c9f3dc64214b297fb1966a3b6d83";
+        assert_eq!(to_hex(&signer.sign_transaction(&transaction).unwrap()), expected);
+        assert_eq!(signer.address(),
+                   Address::from(&from_hex("0x9d8a62f656a8d1615c1294fd71e9cfb3e4855a4f").unwrap()[..]));
+    }
+
+    #[test]
+    fn it_rejects_invalid_secret_keys() {
+        match LocalSigner::new(&H256::zero(), 1) {
+            Err(Error(ErrorKind::InvalidSecretKey, _)) => {},
+            result => panic!("Expected invalid secret key error, got {:?}", result.map(|signer| signer.address()))
+        }
+    }
+}
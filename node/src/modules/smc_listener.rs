@@ -1,48 +1,176 @@
+use modules::client_thread::Command;
+use modules::constants::{NOTARY_DEPOSIT, PROPOSER_DEPOSIT, SMC_POLL_INTERVAL_MILLIS};
 use modules::message;
+use modules::period_clock::PeriodClock;
 use modules::primitives::{
     ShardIdHash,
     ChunkPeriodHash,
     NotaryAddress,
     ProposerAddress
 };
+use modules::smc::backend::SmcBackend;
 
+use std::collections::HashSet;
 use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
 
 /// This will monitor the SMC for changes and then send relevant information to the notary or the proposer.
+///
+/// Each poll sends a `PeriodStart` message when the period clock starts a new period,
+/// followed by each shard whose committee the node's notary is selected in for the
+/// period and whether it is selected at all, and a `Header` message for each header added in the period. Messages go to
+/// every subscriber, and subscribers that hung up are dropped.
 pub struct SMCListener {
-    period: ShardIdHash,
-    notary_sender: mpsc::Sender<message::Message>
+    clock: PeriodClock,
+    subscribers: Vec<mpsc::Sender<message::Message>>,
+    backend: Box<dyn SmcBackend + Send>,
+    notary_address: Option<NotaryAddress>,
+    // Shards whose header for the current period was sent already
+    announced_headers: HashSet<ShardIdHash>,
+    poll_interval: Duration
 }
 
 impl SMCListener {
-    /// Creates a new SMC Listener
-    pub fn new(notary_sender: mpsc::Sender<message::Message>) -> SMCListener {
+    /// Creates a new SMC Listener watching the SMC through the given backend, in the
+    /// periods of the given clock
+    pub fn new(backend: Box<dyn SmcBackend + Send>, clock: PeriodClock) -> SMCListener {
         SMCListener {
-            period: ChunkPeriodHash::from_dec_str("0").unwrap(),
-            notary_sender
+            clock,
+            subscribers: vec![],
+            backend,
+            notary_address: None,
+            announced_headers: HashSet::new(),
+            poll_interval: Duration::from_millis(SMC_POLL_INTERVAL_MILLIS)
         }
     }
 
+    /// Sends the messages of the listener to another subscriber, e.g. a notary or proposer
+    pub fn subscribe(&mut self, subscriber: mpsc::Sender<message::Message>) {
+        self.subscribers.push(subscriber);
+    }
+
+    /// Sets the address of the node's notary, whose selection in shard committees is sent
+    /// to the subscribers. Without it, the notary is never selected.
+    pub fn set_notary_address(&mut self, notary_address: NotaryAddress) {
+        self.notary_address = Some(notary_address);
+    }
+
+    /// Sets how long the listener waits between polls of the SMC backend
+    pub fn set_poll_interval(&mut self, poll_interval: Duration) {
+        self.poll_interval = poll_interval;
+    }
+
+    /// Runs the listener in its own thread, until it receives `Command::Terminate` or the
+    /// returned sender is dropped
+    pub fn spawn(mut self) -> (mpsc::Sender<Command>, thread::JoinHandle<()>) {
+        let (manager_sender, manager_listener) = mpsc::channel();
+        let handle = thread::Builder::new()
+            .name(String::from("smc_listener"))
+            .spawn(move || {
+                self.run(manager_listener);
+            })
+            .expect("Failed to spawn an SMC listener thread");
+        (manager_sender, handle)
+    }
+
+    /// Runs the listener, polling the SMC backend every poll interval
+    pub fn run(&mut self, manager_listener: mpsc::Receiver<Command>) {
+        loop {
+            self.poll();
+
+            // Wait for the next poll, or a command from the thread manager
+            match manager_listener.recv_timeout(self.poll_interval) {
+                Ok(Command::Terminate) => {
+                    debug!("Received terminate command in thread {:?}", thread::current());
+                    break;
+                },
+                Err(mpsc::RecvTimeoutError::Timeout) => {},
+                Err(mpsc::RecvTimeoutError::Disconnected) => {
+                    debug!("Thread manager of thread {:?} hung up", thread::current());
+                    break;
+                }
+            }
+        }
+    }
+
+    /// Checks the period clock for a new period and the SMC backend for notary selection
+    /// and new headers once, and sends them to the subscribers
+    pub fn poll(&mut self) {
+        match self.clock.poll() {
+            Ok(Some(period_start)) => {
+                debug!("SMC Listener saw {:?}", period_start);
+                self.announced_headers.clear();
+                self.send(period_start);
+                self.send_selection();
+            },
+            Ok(None) => {},
+            Err(e) => { error!("Error getting the current period: {:?}", e); }
+        }
+        let period = match self.clock.period() {
+            Some(period) => period,
+            None => return
+        };
+
+        match self.backend.get_headers(period) {
+            Ok(headers) => {
+                for header in headers {
+                    if self.announced_headers.insert(header.shard_id) {
+                        self.send(message::Message::Header { value: header });
+                    }
+                }
+            },
+            Err(e) => { error!("Error getting the headers of period {} from SMC Contract: {:?}", period, e); }
+        }
+    }
+
+    /// Sends each shard the notary is selected in, if any
+    fn send_selection(&mut self) {
+        let notary_address = match self.notary_address {
+            Some(notary_address) => notary_address,
+            None => { self.send(message::Message::Selected { value: false }); return; }
+        };
+        let selected_shards = self.get_selected_shards(notary_address);
+        for shard_id in &selected_shards {
+            info!("Notary {:?} is selected in shard id {}", notary_address, shard_id);
+            self.send(message::Message::ShardId { value: *shard_id });
+        }
+        self.send(message::Message::Selected { value: !selected_shards.is_empty() });
+    }
+
+    fn send(&mut self, msg: message::Message) {
+        trace!("SMC Listener sending {:?} to {} subscribers", msg, self.subscribers.len());
+        self.subscribers.retain(|subscriber| subscriber.send(msg.clone()).is_ok());
+    }
+
     fn register_notary_address(&self, notary_addr: NotaryAddress) -> bool {
-        // TODO - Implement registration of notary address in notary registry of SMC Contract
-        let result: Result<String, String> = Result::Err(String::from("Error"));
+        let result = self.backend.register_notary(notary_addr, NOTARY_DEPOSIT);
 
         match result {
-            Ok(msg) => { info!("Success registered notary address {:?} with SMC Contract", notary_addr); true },
+            Ok(()) => { info!("Success registered notary address {:?} with SMC Contract", notary_addr); true },
             Err(e) => { error!("Error registering notary address {:?} with SMC Contract: {:?}", notary_addr, e); false }
         }
     }
 
     fn get_selected_notaries(&self, shard_id: ShardIdHash) -> Vec<NotaryAddress> {
-        vec![NotaryAddress::zero()]
+        match self.backend.get_notary_committee(shard_id) {
+            Ok(notaries) => notaries,
+            Err(e) => { error!("Error getting selected notaries of shard id {} from SMC Contract: {:?}", shard_id, e); vec![] }
+        }
+    }
+
+    fn get_selected_shards(&self, notary_addr: NotaryAddress) -> Vec<ShardIdHash> {
+        match self.backend.get_notary_shards(notary_addr) {
+            Ok(shards) => shards,
+            Err(e) => { error!("Error getting the shards of notary address {:?} from SMC Contract: {:?}", notary_addr, e); vec![] }
+        }
     }
 
     fn register_proposer_address(&self, proposer_addr: ProposerAddress) -> bool {
-        // TODO - Implement registration of proposer address in proposer registry of SMC Contract
-        let result: Result<String, String> = Result::Err(String::from("Error"));
+        let result = self.backend.register_proposer(proposer_addr, PROPOSER_DEPOSIT);
 
         match result {
-            Ok(msg) => { info!("Success registered proposer address {:?} with SMC Contract", proposer_addr); true },
+            Ok(()) => { info!("Success registered proposer address {:?} with SMC Contract", proposer_addr); true },
             Err(e) => { error!("Error registering proposer address {:?} with SMC Contract: {:?}", proposer_addr, e); false }
         }
     }
@@ -51,12 +179,34 @@ impl SMCListener {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use modules::collation::header::Header;
+    use modules::constants::{PERIOD_LENGTH, SHARD_COUNT};
+    use modules::period_clock::MockTimeSource;
+    use modules::primitives::{ChunkRootHash, ProposerBidHash};
+    use modules::smc::in_memory::InMemorySmc;
+
+    use std::sync::Arc;
+
+    /// A contract and the time source of its block numbers
+    fn generate_backend() -> (InMemorySmc, MockTimeSource) {
+        let time = MockTimeSource::new();
+        (InMemorySmc::with_time_source(Arc::new(time.clone())), time)
+    }
+
+    fn generate_smc_listener(backend: &InMemorySmc, time: &MockTimeSource) -> SMCListener {
+        SMCListener::new(Box::new(backend.clone()), PeriodClock::new(Box::new(time.clone())))
+    }
+
+    /// Move the time source to the first block of the next period
+    fn advance_period(time: &MockTimeSource) -> ChunkPeriodHash {
+        let block_number = time.advance_blocks(PERIOD_LENGTH);
+        ChunkPeriodHash::from(block_number / PERIOD_LENGTH)
+    }
 
     #[test]
-    #[ignore]
     fn it_registered_notary_address_in_notary_registry_of_smc_contract() {
-        let (tx, rx) = mpsc::channel();
-        let smc = SMCListener::new(tx);
+        let (backend, time) = generate_backend();
+        let smc = generate_smc_listener(&backend, &time);
         let notary_addr_bytes: [u8; 20] = [0x22, 0xFF, 0x31, 0x10, 0xA2,
                                            0x82, 0xc1, 0x19, 0x77, 0x36, 
                                            0xb3, 0xfC, 0xe3, 0x4a, 0xD4, 
@@ -64,13 +214,16 @@ mod tests {
         let notary_addr: NotaryAddress = NotaryAddress::from_slice(&notary_addr_bytes);
         let result = smc.register_notary_address(notary_addr);
         assert_eq!(true, result);
+        assert_eq!(backend.notary_deposit(notary_addr).unwrap(), Some(NOTARY_DEPOSIT));
+
+        // A notary can only register once
+        assert!(!smc.register_notary_address(notary_addr));
     }
 
     #[test]
-    #[ignore]
     fn it_registered_proposer_address_in_proposer_registry_of_smc_contract() {
-        let (tx, rx) = mpsc::channel();
-        let smc = SMCListener::new(tx);
+        let (backend, time) = generate_backend();
+        let smc = generate_smc_listener(&backend, &time);
         let proposer_addr_bytes: [u8; 20] = [0x22, 0xFF, 0x31, 0x10, 0xA2, 
                                              0x82, 0xc1, 0x19, 0x77, 0x36, 
                                              0xb3, 0xfC, 0xe3, 0x4a, 0xD4, 
@@ -78,15 +231,15 @@ mod tests {
         let proposer_addr: ProposerAddress = ProposerAddress::from_slice(&proposer_addr_bytes);
         let result = smc.register_proposer_address(proposer_addr);
         assert_eq!(true, result);
+        assert_eq!(backend.proposer_deposit(proposer_addr).unwrap(), Some(PROPOSER_DEPOSIT));
     }
 
     #[test]
-    #[ignore]
     fn it_gets_selected_notaries() {
-        let (tx, rx) = mpsc::channel();
-        let smc = SMCListener::new(tx);
+        let (backend, time) = generate_backend();
+        let smc = generate_smc_listener(&backend, &time);
         let shard_id = ShardIdHash::from_dec_str("0").unwrap();
-        let notary_addr = smc.get_selected_notaries(shard_id);
+        assert_eq!(smc.get_selected_notaries(shard_id), vec![]);
 
         // The dummy "selected notary"
         let notary_addr_bytes: [u8; 20] = [0x6C, 0xaC, 0xE0, 0x52, 0x83, 
@@ -94,13 +247,114 @@ mod tests {
                                            0x57, 0xCe, 0xbA, 0x3c, 0xDd, 
                                            0x2a, 0x27, 0xc4, 0xE2, 0x1f];
         let selected_notary_addr: NotaryAddress = NotaryAddress::from_slice(&notary_addr_bytes);
+        assert!(smc.register_notary_address(selected_notary_addr));
+        let notary_addr = smc.get_selected_notaries(shard_id);
 
         assert_eq!(vec![selected_notary_addr], notary_addr);
     }
 
+    fn received(receiver: &mpsc::Receiver<message::Message>) -> Vec<message::Message> {
+        receiver.try_iter().collect()
+    }
+
     #[test]
-    #[ignore]
     fn it_listens_for_smc_events() {
-        assert!(false);
+        let (backend, time) = generate_backend();
+        let mut smc = generate_smc_listener(&backend, &time);
+        let (notary_tx, notary_rx) = mpsc::channel();
+        let (proposer_tx, proposer_rx) = mpsc::channel();
+        smc.subscribe(notary_tx);
+        smc.subscribe(proposer_tx);
+
+        // The first poll sends the current period
+        smc.poll();
+        for receiver in &[&notary_rx, &proposer_rx] {
+            match received(receiver).as_slice() {
+                [message::Message::PeriodStart { value }, message::Message::Selected { value: false }] => {
+                    assert_eq!(*value, ChunkPeriodHash::zero());
+                },
+                messages => panic!("Expected period start and not selected, got {:?}", messages)
+            }
+        }
+
+        // Nothing changed
+        smc.poll();
+        assert!(received(&notary_rx).is_empty());
+
+        // A new period in which the registered notary is selected
+        let notary_addr = NotaryAddress::from([1; 20]);
+        assert!(smc.register_notary_address(notary_addr));
+        smc.set_notary_address(notary_addr);
+        let period = advance_period(&time);
+        smc.poll();
+        // The only notary sits on every committee
+        match received(&proposer_rx).as_slice() {
+            [message::Message::PeriodStart { value: period_value },
+             shard_ids @ ..,
+             message::Message::Selected { value: true }] => {
+                assert_eq!(*period_value, period);
+                let expected: Vec<message::Message> = (0..SHARD_COUNT)
+                    .map(|shard| message::Message::ShardId { value: ShardIdHash::from(shard) })
+                    .collect();
+                assert_eq!(shard_ids, expected.as_slice());
+            },
+            messages => panic!("Expected period start and selection, got {:?}", messages)
+        }
+        received(&notary_rx);
+
+        // A header added in the period is sent once
+        let proposer_addr = ProposerAddress::from([2; 20]);
+        assert!(smc.register_proposer_address(proposer_addr));
+        let header = Header::new(ShardIdHash::from(5), ChunkRootHash::zero(), period, proposer_addr, ProposerBidHash::zero());
+        backend.add_header(&header).unwrap();
+        smc.poll();
+        smc.poll();
+        for receiver in &[&notary_rx, &proposer_rx] {
+            match received(receiver).as_slice() {
+                [message::Message::Header { value }] => assert_eq!(*value, header),
+                messages => panic!("Expected the added header, got {:?}", messages)
+            }
+        }
+    }
+
+    #[test]
+    fn it_drops_subscribers_that_hung_up() {
+        let (backend, time) = generate_backend();
+        let mut smc = generate_smc_listener(&backend, &time);
+        let (notary_tx, notary_rx) = mpsc::channel();
+        let (proposer_tx, proposer_rx) = mpsc::channel();
+        smc.subscribe(notary_tx);
+        smc.subscribe(proposer_tx);
+        drop(proposer_rx);
+
+        smc.poll();
+        assert_eq!(smc.subscribers.len(), 1);
+        assert_eq!(received(&notary_rx).len(), 2);
+    }
+
+    #[test]
+    fn it_runs_in_its_own_thread_until_terminated() {
+        let (backend, time) = generate_backend();
+        let mut smc = generate_smc_listener(&backend, &time);
+        smc.set_poll_interval(Duration::from_millis(1));
+        let (notary_tx, notary_rx) = mpsc::channel();
+        smc.subscribe(notary_tx);
+
+        let (manager, handle) = smc.spawn();
+        match notary_rx.recv_timeout(Duration::from_secs(5)) {
+            Ok(message::Message::PeriodStart { .. }) => {},
+            result => panic!("Expected period start, got {:?}", result)
+        }
+        let period = advance_period(&time);
+        loop {
+            match notary_rx.recv_timeout(Duration::from_secs(5)) {
+                Ok(message::Message::PeriodStart { value }) => { assert_eq!(value, period); break; },
+                Ok(_) => {},
+                result => panic!("Expected period start, got {:?}", result)
+            }
+        }
+
+        manager.send(Command::Terminate).unwrap();
+        handle.join().unwrap();
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,180 @@
+use modules::collation::header::Header;
+use modules::primitives::{
+    ShardIdHash,
+    ChunkPeriodHash,
+    CollationHeaderHash,
+    NotaryIdHash
+};
+
+use std::collections::{HashMap, HashSet};
+
+/// A notary's vote on whether the body of a collation header is available.
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
+pub struct Vote {
+    pub shard_id: ShardIdHash,
+    pub period: ChunkPeriodHash,
+    pub header_hash: CollationHeaderHash,
+    pub notary_id: NotaryIdHash,
+    pub available: bool
+}
+
+impl Vote {
+    pub fn new(shard_id: ShardIdHash,
+               period: ChunkPeriodHash,
+               header_hash: CollationHeaderHash,
+               notary_id: NotaryIdHash,
+               available: bool) -> Vote {
+        Vote {
+            shard_id,
+            period,
+            header_hash,
+            notary_id,
+            available
+        }
+    }
+
+    /// A vote by the notary on the given collation header.
+    pub fn for_header(header: &Header, notary_id: NotaryIdHash, available: bool) -> Vote {
+        Vote::new(header.shard_id, header.period(), header.hash(), notary_id, available)
+    }
+}
+
+/// Counts the votes of notaries per shard and period, and decides when a collation
+/// header is notarized, i.e. has votes that its body is available from at least
+/// `quorum_size` notaries.
+///
+/// Each notary has one vote per shard and period, so later votes by the same notary
+/// are ignored. Votes that a body is unavailable use up the notary's vote without
+/// counting towards any header.
+pub struct VoteTally {
+    quorum_size: usize,
+    // Notaries that have voted in each shard and period
+    voters: HashMap<(ShardIdHash, ChunkPeriodHash), HashSet<NotaryIdHash>>,
+    // Number of votes that each header's body is available
+    counts: HashMap<(ShardIdHash, ChunkPeriodHash), HashMap<CollationHeaderHash, usize>>,
+    // The header that first reached the quorum in each shard and period
+    notarized: HashMap<(ShardIdHash, ChunkPeriodHash), CollationHeaderHash>
+}
+
+impl VoteTally {
+    pub fn new(quorum_size: usize) -> VoteTally {
+        VoteTally {
+            quorum_size,
+            voters: HashMap::new(),
+            counts: HashMap::new(),
+            notarized: HashMap::new()
+        }
+    }
+
+    /// Count a vote. Returns the hash of the header if this vote notarized it.
+    pub fn add_vote(&mut self, vote: &Vote) -> Option<CollationHeaderHash> {
+        let key = (vote.shard_id, vote.period);
+        if !self.voters.entry(key).or_default().insert(vote.notary_id) {
+            debug!("Ignoring another vote by notary id {} in shard id {} and period {}",
+                   vote.notary_id, vote.shard_id, vote.period);
+            return None;
+        }
+        if !vote.available {
+            return None;
+        }
+
+        let count = {
+            let count = self.counts.entry(key).or_default()
+                .entry(vote.header_hash).or_insert(0);
+            *count += 1;
+            *count
+        };
+        if count >= self.quorum_size && !self.notarized.contains_key(&key) {
+            debug!("Collation {:?} is notarized in shard id {} and period {}",
+                   vote.header_hash, vote.shard_id, vote.period);
+            self.notarized.insert(key, vote.header_hash);
+            return Some(vote.header_hash);
+        }
+        None
+    }
+
+    /// The number of votes that the body of the header is available.
+    pub fn vote_count(&self, shard_id: ShardIdHash, period: ChunkPeriodHash,
+                      header_hash: &CollationHeaderHash) -> usize {
+        self.counts.get(&(shard_id, period))
+            .and_then(|counts| counts.get(header_hash))
+            .cloned()
+            .unwrap_or(0)
+    }
+
+    /// The header notarized in the shard and period, if any.
+    pub fn notarized(&self, shard_id: ShardIdHash, period: ChunkPeriodHash) -> Option<CollationHeaderHash> {
+        self.notarized.get(&(shard_id, period)).cloned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn shard_id(id: u64) -> ShardIdHash {
+        ShardIdHash::from(id)
+    }
+
+    fn period(period: u64) -> ChunkPeriodHash {
+        ChunkPeriodHash::from(period)
+    }
+
+    fn header_hash(byte: u8) -> CollationHeaderHash {
+        CollationHeaderHash::from([byte; 32])
+    }
+
+    fn vote(notary_id: u64, header: u8, available: bool) -> Vote {
+        Vote::new(shard_id(1), period(1), header_hash(header), NotaryIdHash::from(notary_id), available)
+    }
+
+    #[test]
+    fn it_notarizes_header_at_quorum() {
+        let mut tally = VoteTally::new(3);
+        assert_eq!(tally.add_vote(&vote(1, 1, true)), None);
+        assert_eq!(tally.add_vote(&vote(2, 1, true)), None);
+        assert_eq!(tally.notarized(shard_id(1), period(1)), None);
+        assert_eq!(tally.add_vote(&vote(3, 1, true)), Some(header_hash(1)));
+        assert_eq!(tally.notarized(shard_id(1), period(1)), Some(header_hash(1)));
+
+        // Votes after the quorum is reached don't notarize the header again.
+        assert_eq!(tally.add_vote(&vote(4, 1, true)), None);
+        assert_eq!(tally.vote_count(shard_id(1), period(1), &header_hash(1)), 4);
+    }
+
+    #[test]
+    fn it_ignores_repeated_votes_of_a_notary() {
+        let mut tally = VoteTally::new(2);
+        assert_eq!(tally.add_vote(&vote(1, 1, true)), None);
+        assert_eq!(tally.add_vote(&vote(1, 1, true)), None);
+        assert_eq!(tally.add_vote(&vote(1, 2, true)), None);
+        assert_eq!(tally.vote_count(shard_id(1), period(1), &header_hash(1)), 1);
+        assert_eq!(tally.vote_count(shard_id(1), period(1), &header_hash(2)), 0);
+        assert_eq!(tally.notarized(shard_id(1), period(1)), None);
+    }
+
+    #[test]
+    fn it_does_not_count_unavailable_votes() {
+        let mut tally = VoteTally::new(2);
+        assert_eq!(tally.add_vote(&vote(1, 1, false)), None);
+        assert_eq!(tally.add_vote(&vote(2, 1, true)), None);
+        assert_eq!(tally.vote_count(shard_id(1), period(1), &header_hash(1)), 1);
+        assert_eq!(tally.notarized(shard_id(1), period(1)), None);
+        // The notary that voted unavailable can't vote again.
+        assert_eq!(tally.add_vote(&vote(1, 1, true)), None);
+        assert_eq!(tally.add_vote(&vote(3, 1, true)), Some(header_hash(1)));
+    }
+
+    #[test]
+    fn it_tallies_shards_and_periods_separately() {
+        let mut tally = VoteTally::new(2);
+        let other_shard = Vote { shard_id: shard_id(2), ..vote(1, 1, true) };
+        let other_period = Vote { period: period(2), ..vote(1, 1, true) };
+        assert_eq!(tally.add_vote(&vote(1, 1, true)), None);
+        assert_eq!(tally.add_vote(&other_shard), None);
+        assert_eq!(tally.add_vote(&other_period), None);
+        assert_eq!(tally.vote_count(shard_id(1), period(1), &header_hash(1)), 1);
+        assert_eq!(tally.vote_count(shard_id(2), period(1), &header_hash(1)), 1);
+        assert_eq!(tally.vote_count(shard_id(1), period(2), &header_hash(1)), 1);
+    }
+}
@@ -10,13 +10,25 @@ extern crate diamond_drops_env as env;
 extern crate diamond_drops_node as node;
 
 use node::modules::{client_thread, smc_listener};
+use node::modules::blob_pool::BlobPool;
+use node::modules::constants::{BLOCK_TIME_MILLIS, NOTARY_DEPOSIT};
+use node::modules::period_clock::{PeriodClock, WallClock};
+use node::modules::primitives::{NotaryAddress, ProposerAddress, Wei};
+use node::modules::smc::backend::SmcBackend;
+use node::modules::smc::in_memory::InMemorySmc;
 use node::modules::collation::{header, body, collation};
 
 
 // std imports
-use std::sync::mpsc;
+use std::sync::{Arc, Mutex, mpsc};
 use std::thread;
 use std::time::Duration;
+
+/// The account of the node's notary on the in-memory SMC
+const NOTARY_ADDRESS_BYTES: [u8; 20] = [0x11; 20];
+/// The account of the node's proposer on the in-memory SMC
+const PROPOSER_ADDRESS_BYTES: [u8; 20] = [0x22; 20];
+
 /// The main function to run the node.  
 ///
 /// # Inputs
@@ -26,11 +38,15 @@ pub fn run(config: cli::modules::config::Config) -> () {
     debug!("Client Config - Mode: {:?}", config.mode);
     debug!("Client Config - Collation Active: {:?}", config.collation_active);
 
+    // The blobs for the proposer to put into collations
+    let blob_pool = Arc::new(Mutex::new(BlobPool::new()));
+
     if config.collation_active == true {
         let header = header::create_sample_collation_header();
         let body = body::create_sample_collation_body();
         let collation = collation::Collation::new(header, body);
         debug!("Successfully created collation: {:?}", collation);
+        submit_sample_blobs(&blob_pool, &collation);
     }
 
     match config.mode {
@@ -39,11 +55,21 @@ pub fn run(config: cli::modules::config::Config) -> () {
 
             // Create the SMC Listener
             let (smc_tx, smc_rx) = mpsc::channel();
-            let smc = smc_listener::SMCListener::new(smc_tx);
+            let (mut smc, smc_backend) = create_smc_listener(None);
+            smc.subscribe(smc_tx);
 
             // Start a thread to run the proposer
             let mut proposer_thread = client_thread::ClientThread::new(&config.mode);
-            proposer_thread.run(smc_rx);
+            proposer_thread.set_smc_backend(Box::new(smc_backend));
+            proposer_thread.set_proposer_address(ProposerAddress::from(PROPOSER_ADDRESS_BYTES));
+            proposer_thread.set_blob_pool(blob_pool);
+            if let Err(e) = proposer_thread.run(smc_rx) {
+                error!("Error running the proposer: {}", e);
+                return;
+            }
+
+            // Start a thread to run the SMC Listener
+            let (smc_manager, smc_handle) = smc.spawn();
 
             if env::config::is_running_with_cargo_test() {
                 thread::sleep(Duration::from_secs(1));
@@ -63,17 +89,27 @@ pub fn run(config: cli::modules::config::Config) -> () {
                     panic!("Failed proposer thread join {:?}", e);
                 }
             }
+
+            // Stop the SMC Listener once there is no one left to listen to it
+            stop_smc_listener(smc_manager, smc_handle);
         }
         cli::modules::config::Mode::Notary => {
             debug!("Running as a notary");
 
             // Create the SMC Listener
             let (smc_tx, smc_rx) = mpsc::channel();
-            let smc = smc_listener::SMCListener::new(smc_tx);
+            let (mut smc, _) = create_smc_listener(Some(NotaryAddress::from(NOTARY_ADDRESS_BYTES)));
+            smc.subscribe(smc_tx);
 
             // Start a thread to run the notary
             let mut notary_thread = client_thread::ClientThread::new(&config.mode);
-            notary_thread.run(smc_rx);
+            if let Err(e) = notary_thread.run(smc_rx) {
+                error!("Error running the notary: {}", e);
+                return;
+            }
+
+            // Start a thread to run the SMC Listener
+            let (smc_manager, smc_handle) = smc.spawn();
 
             if env::config::is_running_with_cargo_test() {
                 thread::sleep(Duration::from_secs(1));
@@ -93,15 +129,19 @@ pub fn run(config: cli::modules::config::Config) -> () {
                     panic!("Failed notary thread join {:?}", e);
                 }
             }
+
+            // Stop the SMC Listener once there is no one left to listen to it
+            stop_smc_listener(smc_manager, smc_handle);
         }
         cli::modules::config::Mode::Both => {
             debug!("Running as both a proposer and notary");
 
-            // Create the SMC Listeners
+            // Create the SMC Listener, sending to both the proposer and the notary
             let (notary_smc_tx, notary_smc_rx) = mpsc::channel();
             let (proposer_smc_tx, proposer_smc_rx) = mpsc::channel();
-            let notary_smc = smc_listener::SMCListener::new(notary_smc_tx);
-            let proposer_smc = smc_listener::SMCListener::new(proposer_smc_tx);
+            let (mut smc, smc_backend) = create_smc_listener(Some(NotaryAddress::from(NOTARY_ADDRESS_BYTES)));
+            smc.subscribe(notary_smc_tx.clone());
+            smc.subscribe(proposer_smc_tx.clone());
 
             // Start threads for both proposer and notary
             let mut proposer_thread =
@@ -109,8 +149,28 @@ pub fn run(config: cli::modules::config::Config) -> () {
             let mut notary_thread =
                 client_thread::ClientThread::new(&cli::modules::config::Mode::Notary);
 
-            proposer_thread.run(proposer_smc_rx);
-            notary_thread.run(notary_smc_rx);
+            // The proposer commits to and reveals its collations to the notary, which
+            // tells the proposer when its commitment is chosen
+            proposer_thread.set_smc_backend(Box::new(smc_backend));
+            proposer_thread.set_proposer_address(ProposerAddress::from(PROPOSER_ADDRESS_BYTES));
+            proposer_thread.set_blob_pool(blob_pool);
+            proposer_thread.set_proposal_sender(notary_smc_tx);
+            notary_thread.set_chosen_sender(proposer_smc_tx);
+            if let Err(e) = proposer_thread.run(proposer_smc_rx) {
+                error!("Error running the proposer: {}", e);
+                return;
+            }
+            if let Err(e) = notary_thread.run(notary_smc_rx) {
+                error!("Error running the notary: {}", e);
+                let _result = proposer_thread
+                    .manager
+                    .unwrap()
+                    .send(client_thread::Command::Terminate);
+                return;
+            }
+
+            // Start a thread to run the SMC Listener
+            let (smc_manager, smc_handle) = smc.spawn();
 
             if env::config::is_running_with_cargo_test() {
                 thread::sleep(Duration::from_secs(1));
@@ -143,6 +203,58 @@ pub fn run(config: cli::modules::config::Config) -> () {
                     panic!("Failed notary thread join {:?}", e);
                 }
             }
+
+            // Stop the SMC Listener once there is no one left to listen to it
+            stop_smc_listener(smc_manager, smc_handle);
+        }
+    }
+}
+
+/// Create an SMC Listener on an in-memory SMC, whose blocks follow the wall clock. The
+/// SMC is returned too, for the proposer to add collations to.
+///
+/// With a notary address, the notary is registered in the SMC, and the listener tells
+/// the notary when it is selected.
+fn create_smc_listener(notary_address: Option<NotaryAddress>) -> (smc_listener::SMCListener, InMemorySmc) {
+    let time_source = Arc::new(WallClock::new(Duration::from_millis(BLOCK_TIME_MILLIS)));
+    let smc = InMemorySmc::with_time_source(time_source.clone());
+    let mut clock = PeriodClock::new(Box::new(time_source));
+    clock.set_period_length(smc.period_length());
+    let mut listener = smc_listener::SMCListener::new(Box::new(smc.clone()), clock);
+    if let Some(notary_address) = notary_address {
+        match smc.register_notary(notary_address, NOTARY_DEPOSIT) {
+            Ok(()) => { info!("Registered notary address {:?} with SMC Contract", notary_address); },
+            Err(e) => { error!("Error registering notary address {:?} with SMC Contract: {:?}", notary_address, e); }
+        }
+        listener.set_notary_address(notary_address);
+    }
+    (listener, smc)
+}
+
+/// Submit the blobs of a sample collation to the blob pool, for the proposer to propose
+fn submit_sample_blobs(blob_pool: &Mutex<BlobPool>, collation: &collation::Collation) {
+    let blobs = match collation.body.to_blobs() {
+        Ok(blobs) => blobs,
+        Err(e) => { error!("Error getting the blobs of the sample collation: {:?}", e); return; }
+    };
+    let mut blob_pool = blob_pool.lock().expect("The blob pool lock is poisoned");
+    for blob in blobs {
+        match blob_pool.submit(blob, ProposerAddress::from(PROPOSER_ADDRESS_BYTES), Wei::zero()) {
+            Ok(hash) => { debug!("Submitted sample blob {:?} to the blob pool", hash); },
+            Err(e) => { error!("Error submitting a sample blob to the blob pool: {:?}", e); }
+        }
+    }
+}
+
+/// Terminate the SMC Listener thread and wait for it
+fn stop_smc_listener(smc_manager: mpsc::Sender<client_thread::Command>, smc_handle: thread::JoinHandle<()>) {
+    let _result = smc_manager.send(client_thread::Command::Terminate);
+    match smc_handle.join() {
+        Ok(x) => {
+            debug!("Successful SMC Listener thread join {:?}", x);
+        }
+        Err(e) => {
+            panic!("Failed SMC Listener thread join {:?}", e);
         }
     }
 }